@@ -0,0 +1,519 @@
+//! Simplifies a recorded track, and reports how faithful the simplification
+//! is.
+//!
+//! The track is read from a CSV, GPX, GeoJSON, or KML file, and simplified by
+//! selecting the most novel points for a recipient which has received none of
+//! them. The selected points are written in any of the same formats (CSV by
+//! default), and a summary of the selection (including its error metrics, and
+//! the size of the encoded message) is written to standard error.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use clap::{Parser, ValueEnum};
+use position_share::{
+    codec::encode_batch,
+    endpoint_novelty::{
+        AlwaysIncludeEndpoints, AlwaysIncludeLatest, DistanceFromLastTransmitted, EndpointNovelty,
+        Separation,
+    },
+    formats::{
+        self,
+        csv::{Columns, TimestampFormat},
+        LocalProjection,
+    },
+    metrics::ErrorMetrics,
+    rdp, Coordinate, Datum, NodeId, Objective, Optimal, Positions, Search, TimeDecay,
+};
+
+/// The longest track the optimal strategies will search, as longer tracks
+/// would take too long.
+const MAX_OPTIMAL_LEN: usize = 1000;
+
+/// Simplify a track file by selecting its most novel points.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// The track file to read, or `-` to read from standard input.
+    input: PathBuf,
+
+    /// The format of the input.
+    ///
+    /// If omitted, this is inferred from the file extension.
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+
+    /// The file to which the selected points are written.
+    ///
+    /// If omitted, the points are written to standard output.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// The format of the output.
+    ///
+    /// If omitted, this is inferred from the output file extension, or else
+    /// defaults to CSV. Geographic formats can only be written if the input is
+    /// geographic.
+    #[arg(long, value_enum)]
+    output_format: Option<Format>,
+
+    /// The strategy used to select points.
+    #[arg(short, long, value_enum, default_value_t)]
+    strategy: Strategy,
+
+    /// How the novelty of the first and last points is scored (by default,
+    /// `separation`).
+    ///
+    /// This, `--threshold`, `--tolerance` and `--half-life` only apply to the
+    /// `rdp` strategy.
+    #[arg(short, long, value_enum)]
+    endpoints: Option<Endpoints>,
+
+    /// Stop selecting points once the most novel remaining point is less
+    /// than this fraction of the least novel point selected so far.
+    ///
+    /// This is a unitless ratio, not a distance. For a stopping criterion in
+    /// metres, see `--tolerance`.
+    #[arg(short, long)]
+    threshold: Option<f64>,
+
+    /// Stop selecting points once every remaining segment is within this
+    /// distance (in metres) of its chord.
+    ///
+    /// Without a maximum number of points or a budget, this selects every
+    /// point needed to describe the track to within the tolerance.
+    #[arg(long)]
+    tolerance: Option<f64>,
+
+    /// The half-life (in seconds) with which the novelty of older points
+    /// decays.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_half_life)]
+    half_life: Option<Duration>,
+
+    /// The maximum number of points to select.
+    #[arg(short = 'n', long)]
+    max_points: Option<usize>,
+
+    /// The maximum size (in bytes) of the encoded message.
+    #[arg(short, long)]
+    budget: Option<usize>,
+
+    /// The name of the timestamp column in CSV files.
+    #[arg(long, default_value = "timestamp")]
+    timestamp_column: String,
+
+    /// The format of timestamps in CSV files.
+    ///
+    /// One of `rfc3339`, `unix` (seconds), `unix-ms` (milliseconds), or a
+    /// chrono format string (such as `%d/%m/%Y %H:%M:%S`).
+    #[arg(long, default_value = "rfc3339", value_parser = parse_timestamp_format)]
+    timestamp_format: TimestampFormat,
+
+    /// The names of the x, y, and z columns in CSV files.
+    #[arg(long, value_name = "X,Y,Z", default_value = "x,y,z", value_parser = parse_columns)]
+    columns: [String; 3],
+
+    /// The names of the latitude, longitude, and depth columns in CSV files
+    /// with geographic, rather than Cartesian, coordinates.
+    ///
+    /// Latitudes and longitudes are in degrees, and depths in metres
+    /// (positive downwards). Requires `--origin`.
+    #[arg(
+        long,
+        value_name = "LAT,LON,DEPTH",
+        value_parser = parse_columns,
+        conflicts_with = "columns",
+        requires = "origin"
+    )]
+    geographic_columns: Option<[String; 3]>,
+
+    /// The latitude and longitude (in degrees) of the origin of the local
+    /// coordinates onto which geographic CSV files are projected.
+    #[arg(
+        long,
+        value_name = "LAT,LON",
+        value_parser = parse_origin,
+        requires = "geographic_columns"
+    )]
+    origin: Option<LocalProjection>,
+
+    /// The name of the ID column in CSV files, if any.
+    #[arg(long)]
+    id_column: Option<String>,
+
+    /// The field delimiter in CSV files.
+    #[arg(long, default_value = ",", value_parser = parse_delimiter)]
+    delimiter: u8,
+}
+
+impl Args {
+    /// Returns the layout of CSV files.
+    fn csv_columns(&self) -> Columns {
+        let columns = Columns::default()
+            .with_timestamp(&self.timestamp_column, self.timestamp_format.clone())
+            .with_delimiter(self.delimiter);
+        let columns = if let (Some([latitude, longitude, depth]), Some(origin)) =
+            (&self.geographic_columns, self.origin)
+        {
+            columns.with_geographic(latitude, longitude, depth, origin)
+        } else {
+            let [x, y, z] = &self.columns;
+            columns.with_cartesian(x, y, z)
+        };
+        match &self.id_column {
+            Some(id) => columns.with_id(id),
+            None => columns,
+        }
+    }
+}
+
+fn parse_columns(value: &str) -> Result<[String; 3], String> {
+    let columns: Vec<_> = value
+        .split(',')
+        .map(|column| column.trim().to_string())
+        .collect();
+    columns
+        .try_into()
+        .map_err(|_| "expected three comma-separated column names".to_string())
+}
+
+fn parse_origin(value: &str) -> Result<LocalProjection, String> {
+    let (latitude, longitude) = value
+        .split_once(',')
+        .and_then(|(latitude, longitude)| {
+            Some((
+                latitude.trim().parse::<f64>().ok()?,
+                longitude.trim().parse::<f64>().ok()?,
+            ))
+        })
+        .filter(|(latitude, longitude)| {
+            (-90.0..=90.0).contains(latitude) && (-180.0..=180.0).contains(longitude)
+        })
+        .ok_or_else(|| {
+            "expected a latitude and longitude in degrees, such as 50.1,-1.4".to_string()
+        })?;
+    Ok(LocalProjection::new(latitude, longitude))
+}
+
+fn parse_delimiter(value: &str) -> Result<u8, String> {
+    match value.as_bytes() {
+        [delimiter] if delimiter.is_ascii() => Ok(*delimiter),
+        _ => Err("expected a single ASCII character".to_string()),
+    }
+}
+
+fn parse_half_life(value: &str) -> Result<Duration, String> {
+    let seconds: f64 = value.parse().map_err(|error| format!("{error}"))?;
+    std::time::Duration::try_from_secs_f64(seconds)
+        .ok()
+        .filter(|half_life| !half_life.is_zero())
+        .and_then(|half_life| Duration::from_std(half_life).ok())
+        .ok_or_else(|| "expected a positive, finite number of seconds".to_string())
+}
+
+fn parse_timestamp_format(value: &str) -> Result<TimestampFormat, String> {
+    Ok(match value {
+        "rfc3339" => TimestampFormat::Rfc3339,
+        "unix" => TimestampFormat::UnixSeconds,
+        "unix-ms" => TimestampFormat::UnixMilliseconds,
+        format if format.contains('%') => TimestampFormat::Custom(format.to_string()),
+        _ => return Err("expected rfc3339, unix, unix-ms, or a format string".to_string()),
+    })
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    Gpx,
+    Geojson,
+    Kml,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(Self::Csv),
+            "gpx" => Some(Self::Gpx),
+            "geojson" | "json" => Some(Self::Geojson),
+            "kml" => Some(Self::Kml),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum Strategy {
+    /// Recursively select the points furthest from the simplified track.
+    #[default]
+    Rdp,
+    /// Select points evenly spaced through the track.
+    ///
+    /// This ignores geometry entirely, and is a useful baseline.
+    Uniform,
+    /// Select the points which minimise the greatest distance of any point
+    /// from the simplified track.
+    ///
+    /// This is slow, so is limited to tracks of at most 1000 points.
+    Optimal,
+    /// Select the points which minimise the total distance of every point
+    /// from the simplified track.
+    ///
+    /// This is slow, so is limited to tracks of at most 1000 points.
+    OptimalTotal,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum Endpoints {
+    /// Score the endpoints by the distance between them.
+    #[default]
+    Separation,
+    /// Always select the most recent point.
+    Latest,
+    /// Always select both the first and the most recent points.
+    Both,
+    /// Score the endpoints by their distance from the last transmitted point.
+    Distance,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    check_search_options(args)?;
+    let (positions, projection) = read(args)?;
+    let data: Vec<_> = positions.iter().collect();
+
+    if matches!(args.strategy, Strategy::Optimal | Strategy::OptimalTotal)
+        && data.len() > MAX_OPTIMAL_LEN
+    {
+        return Err(format!(
+            "the optimal strategies are limited to {} points, but the track has {}",
+            MAX_OPTIMAL_LEN,
+            data.len()
+        )
+        .into());
+    }
+
+    let recipient = NodeId::new_v4();
+    let n_max = n_max(args, &data);
+    let mut selection = match args.strategy {
+        Strategy::Rdp => match args.endpoints.unwrap_or_default() {
+            Endpoints::Separation => search(args, &positions, Separation, &recipient, n_max),
+            Endpoints::Latest => search(args, &positions, AlwaysIncludeLatest, &recipient, n_max),
+            Endpoints::Both => search(args, &positions, AlwaysIncludeEndpoints, &recipient, n_max),
+            Endpoints::Distance => search(
+                args,
+                &positions,
+                DistanceFromLastTransmitted,
+                &recipient,
+                n_max,
+            ),
+        },
+        Strategy::Uniform => uniform(&data, n_max),
+        Strategy::Optimal => {
+            positions.most_novel_coordinates(&Optimal::new(Objective::MaxError), &recipient, n_max)
+        }
+        Strategy::OptimalTotal => positions.most_novel_coordinates(
+            &Optimal::new(Objective::TotalError),
+            &recipient,
+            n_max,
+        ),
+    };
+    selection.sort();
+
+    let writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    write(args, writer, projection.as_ref(), &selection)?;
+
+    let mut summary = io::stderr().lock();
+    if let Some(projection) = projection {
+        let (latitude, longitude) = projection.origin();
+        writeln!(summary, "origin:    {latitude:.6}, {longitude:.6}")?;
+    }
+    writeln!(summary, "points:    {}", data.len())?;
+    writeln!(summary, "selected:  {}", selection.len())?;
+    writeln!(
+        summary,
+        "encoded:   {} bytes",
+        encode_batch(&selection).len()
+    )?;
+    if let Some(metrics) = ErrorMetrics::new(&data, &selection) {
+        writeln!(summary, "max SED:   {:.3} m", metrics.max_sed)?;
+        writeln!(summary, "mean SED:  {:.3} m", metrics.mean_sed)?;
+        writeln!(summary, "Hausdorff: {:.3} m", metrics.hausdorff)?;
+        writeln!(summary, "Fréchet:   {:.3} m", metrics.frechet)?;
+        writeln!(summary, "area:      {:.3} m²", metrics.area)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that options which only apply to the `rdp` strategy aren't given
+/// with another, where they would be ignored.
+fn check_search_options(args: &Args) -> Result<(), String> {
+    if matches!(args.strategy, Strategy::Rdp) {
+        return Ok(());
+    }
+    let ignored: Vec<_> = [
+        ("--endpoints", args.endpoints.is_some()),
+        ("--threshold", args.threshold.is_some()),
+        ("--tolerance", args.tolerance.is_some()),
+        ("--half-life", args.half_life.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(option, given)| given.then_some(option))
+    .collect();
+    if ignored.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "{} can only be used with the rdp strategy",
+        ignored.join(", ")
+    ))
+}
+
+/// Reads the input track.
+///
+/// Geographic formats are projected onto local coordinates, centred on the
+/// first point (or, for CSV files, on `--origin`), in which case the
+/// projection is also returned.
+fn read(args: &Args) -> Result<(Positions, Option<LocalProjection>), Box<dyn std::error::Error>> {
+    let format = args
+        .format
+        .or_else(|| Format::from_path(&args.input))
+        .ok_or("unable to infer the input format, use --format to specify it")?;
+
+    let reader: Box<dyn Read> = if args.input.as_os_str() == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(&args.input)?)
+    };
+    let reader = BufReader::new(reader);
+
+    let fixes = match format {
+        Format::Csv => return Ok((args.csv_columns().read(reader)?, args.origin)),
+        Format::Gpx => formats::gpx::read(reader)?,
+        Format::Geojson => formats::geojson::read(reader)?,
+        Format::Kml => formats::kml::read(reader)?,
+    };
+    let projection = LocalProjection::from_fixes(&fixes).ok_or("the track is empty")?;
+    Ok((projection.positions(&fixes)?, Some(projection)))
+}
+
+/// Writes the selected points.
+fn write(
+    args: &Args,
+    writer: impl Write,
+    projection: Option<&LocalProjection>,
+    selection: &[&Datum],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let format = args
+        .output_format
+        .or_else(|| args.output.as_deref().and_then(Format::from_path))
+        .unwrap_or(Format::Csv);
+    let data = selection.iter().copied();
+
+    let projection =
+        || projection.ok_or("geographic formats can only be written for geographic input");
+
+    match format {
+        Format::Csv => args.csv_columns().write(writer, data)?,
+        Format::Gpx => formats::gpx::write(writer, projection()?, data)?,
+        Format::Geojson => formats::geojson::write(writer, projection()?, data)?,
+        Format::Kml => formats::kml::write(writer, projection()?, data)?,
+    }
+    Ok(())
+}
+
+/// Calculates the maximum number of points to select.
+fn n_max(args: &Args, data: &[&Datum]) -> usize {
+    let budget = args.budget.map_or(usize::MAX, |budget| {
+        let header = encode_batch::<(), DateTime<Utc>, Coordinate>(&[]).len();
+        let datum = data
+            .first()
+            .map_or(1, |datum| encode_batch(&[*datum]).len() - header);
+        budget.saturating_sub(header) / datum
+    });
+    args.max_points.unwrap_or(data.len()).min(budget)
+}
+
+fn search<'a, E: EndpointNovelty>(
+    args: &Args,
+    positions: &'a Positions,
+    endpoint_novelty: E,
+    recipient: &NodeId,
+    n_max: usize,
+) -> Vec<&'a Datum> {
+    let mut search = Search::new(rdp, args.threshold).with_endpoint_novelty(endpoint_novelty);
+    if let Some(tolerance) = args.tolerance {
+        search = search.with_tolerance(tolerance);
+    }
+    if let Some(half_life) = args.half_life {
+        search = search.with_time_decay(TimeDecay::exponential(half_life));
+    }
+    positions.most_novel_coordinates(&search, recipient, n_max)
+}
+
+fn uniform<'a>(data: &[&'a Datum], n_max: usize) -> Vec<&'a Datum> {
+    if data.len() <= n_max {
+        return data.to_vec();
+    }
+    (0..n_max)
+        .map(|i| data[i * (data.len() - 1) / (n_max - 1).max(1)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn verify_args() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn half_life() {
+        assert_eq!(parse_half_life("1.5"), Ok(Duration::milliseconds(1500)));
+        for invalid in ["0", "-1", "NaN", "inf", "1e300", "soon"] {
+            assert!(parse_half_life(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn origin() {
+        assert_eq!(
+            parse_origin("50.5, -1.25"),
+            Ok(LocalProjection::new(50.5, -1.25))
+        );
+        for invalid in ["50.5", "91,0", "0,181", "NaN,0", "north,west"] {
+            assert!(parse_origin(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn delimiter() {
+        assert_eq!(parse_delimiter(";"), Ok(b';'));
+        for invalid in ["", ";;", "é"] {
+            assert!(parse_delimiter(invalid).is_err(), "{invalid}");
+        }
+    }
+}
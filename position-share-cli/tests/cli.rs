@@ -0,0 +1,168 @@
+//! Runs the command-line tool end to end.
+
+use std::{
+    fmt::Write,
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+/// Writes a zigzag track of `n` points to a CSV file, returning its path.
+fn track(name: &str, n: u32) -> PathBuf {
+    let mut csv = String::from("timestamp,x,y,z\n");
+    for i in 0..n {
+        writeln!(
+            csv,
+            "2024-01-01T{:02}:{:02}:{:02}Z,{},{},0",
+            i / 3600,
+            i / 60 % 60,
+            i % 60,
+            f64::from(i) * 10.0,
+            f64::from(i % 2) * 5.0
+        )
+        .unwrap();
+    }
+    let path =
+        std::env::temp_dir().join(format!("position-share-{}-{name}.csv", std::process::id()));
+    fs::write(&path, csv).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_position-share"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn simplifies_a_csv_track() {
+    let input = track("simplify", 20);
+    let output = run(&[input.to_str().unwrap(), "-n", "5"]);
+    fs::remove_file(&input).unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("timestamp,x,y,z"));
+    assert_eq!(lines.count(), 5);
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("points:    20"));
+    assert!(stderr.contains("selected:  5"));
+}
+
+#[test]
+fn tolerance_selects_every_point_needed() {
+    // Every other point is 5 m off the line through its neighbours, so none
+    // can be dropped within a 1 m tolerance
+    let input = track("tolerance", 10);
+    let output = run(&[input.to_str().unwrap(), "--tolerance", "1", "-e", "both"]);
+    fs::remove_file(&input).unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("selected:  10"));
+}
+
+#[test]
+fn unknown_format_is_an_error() {
+    let output = run(&["track.txt"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("unable to infer the input format"));
+}
+
+#[test]
+fn optimal_strategies_are_limited_to_short_tracks() {
+    let input = track("optimal", 1001);
+    let output = ["optimal", "optimal-total"]
+        .map(|strategy| run(&[input.to_str().unwrap(), "-n", "5", "-s", strategy]));
+    fs::remove_file(&input).unwrap();
+
+    for output in output {
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("limited to 1000 points, but the track has 1001"));
+    }
+}
+
+#[test]
+fn search_options_need_the_rdp_strategy() {
+    let input = track("search-options", 10);
+    let path = input.to_str().unwrap();
+    let outputs = [
+        run(&[path, "-s", "uniform", "--tolerance", "1"]),
+        run(&[path, "-s", "optimal", "-e", "both", "--half-life", "60"]),
+        run(&[path, "-s", "optimal-total", "--threshold", "0.5"]),
+    ];
+    let rdp = run(&[path, "-s", "rdp", "-e", "both", "--tolerance", "1"]);
+    fs::remove_file(&input).unwrap();
+
+    let expected = [
+        "--tolerance can only be used with the rdp strategy",
+        "--endpoints, --half-life can only be used with the rdp strategy",
+        "--threshold can only be used with the rdp strategy",
+    ];
+    for (output, expected) in outputs.iter().zip(expected) {
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains(expected));
+    }
+    assert!(rdp.status.success());
+}
+
+#[test]
+fn geographic_csv_columns() {
+    let mut csv = String::from("time;lat;lon;depth\n");
+    for i in 0..10 {
+        writeln!(
+            csv,
+            "{};{};{};10",
+            1_704_067_200 + i * 10,
+            f64::from(i).mul_add(1e-4, 50.0),
+            f64::from(i % 2).mul_add(1e-4, -1.0)
+        )
+        .unwrap();
+    }
+    let input = std::env::temp_dir().join(format!(
+        "position-share-{}-geographic.csv",
+        std::process::id()
+    ));
+    fs::write(&input, csv).unwrap();
+    let path = input.to_str().unwrap();
+    let columns = [
+        "--timestamp-column",
+        "time",
+        "--timestamp-format",
+        "unix",
+        "--geographic-columns",
+        "lat,lon,depth",
+        "--origin",
+        "50,-1",
+        "--delimiter",
+        ";",
+    ];
+    let csv = run(&[&[path, "-n", "4"], columns.as_slice()].concat());
+    let geojson = run(&[
+        &[path, "-n", "4", "--output-format", "geojson"],
+        columns.as_slice(),
+    ]
+    .concat());
+    let without_origin = run(&[path, "--geographic-columns", "lat,lon,depth"]);
+    fs::remove_file(&input).unwrap();
+
+    assert!(csv.status.success());
+    let stdout = String::from_utf8(csv.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("time;lat;lon;depth"));
+    assert_eq!(lines.count(), 4);
+
+    assert!(geojson.status.success());
+    assert!(String::from_utf8(geojson.stdout)
+        .unwrap()
+        .contains("LineString"));
+
+    assert!(!without_origin.status.success());
+    assert!(String::from_utf8_lossy(&without_origin.stderr).contains("--origin"));
+}
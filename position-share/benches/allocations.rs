@@ -0,0 +1,58 @@
+//! Compares repeated searches with a [`SearchContext`] with searches which
+//! allocate fresh buffers every time.
+//!
+//! That the searches with a context don't allocate at all is checked by
+//! `tests/allocations.rs`.
+
+use std::hint::black_box;
+
+use chrono::{Duration, Utc};
+use criterion::{criterion_group, criterion_main, Criterion};
+use position_share::{rdp, Coordinate, Positions, Search, SearchContext};
+use uuid::Uuid;
+
+/// A meandering track, short enough to be searched on a single thread.
+fn generate_path(num_points: u32) -> Positions {
+    let mut positions = Positions::default();
+    let start_time = Utc::now();
+    for i in 0..num_points {
+        let t = f64::from(i) / 10.0;
+        let coordinate = Coordinate::new(10.0 * t, 50.0 * t.sin(), -5.0 * (t / 3.0).cos());
+        positions
+            .add(start_time + Duration::seconds(i.into()), coordinate)
+            .unwrap();
+    }
+    positions
+}
+
+fn bench_steady_state(c: &mut Criterion) {
+    let positions = generate_path(2000);
+    let recipient = Uuid::new_v4();
+    let search = Search::new(rdp, Some(0.4));
+    let mut context = SearchContext::new();
+
+    let mut group = c.benchmark_group("steady_state");
+    group.bench_function("with_context", |b| {
+        b.iter(|| {
+            positions
+                .most_novel_coordinates_with_context(
+                    &mut context,
+                    &search,
+                    black_box(&recipient),
+                    black_box(100),
+                )
+                .len()
+        });
+    });
+    group.bench_function("without_context", |b| {
+        b.iter(|| {
+            positions
+                .most_novel_coordinates(&search, black_box(&recipient), black_box(100))
+                .len()
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_steady_state);
+criterion_main!(benches);
@@ -1,6 +1,10 @@
+use std::hint::black_box;
+
 use chrono::{Duration, Utc};
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use position_share::{rdp, Coordinate, Positions, Search};
+use criterion::{criterion_group, criterion_main, Criterion};
+use position_share::{
+    rdp, Coordinate, Coordinate2D, HullRdp, IdGenerator, Point, Positions, Search,
+};
 use uuid::Uuid;
 
 /// Generates a simulated path for an object.
@@ -44,7 +48,7 @@ fn generate_path(num_points: usize) -> Positions {
         let timestamp = start_time + Duration::seconds(i as i64 * 10);
 
         // Add the coordinate and timestamp to the Positions object
-        positions.add(timestamp, coordinate);
+        positions.add(timestamp, coordinate).unwrap();
     }
 
     positions
@@ -64,5 +68,140 @@ fn bench_most_novel_coordinates(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_most_novel_coordinates);
+/// Searches a history of the size synced after a long mission.
+///
+/// With the `rayon` feature enabled, the search is also run on a single thread
+/// for comparison, which shows the speedup from searching in parallel.
+fn bench_large_history(c: &mut Criterion) {
+    let positions = generate_path(50_000);
+    let recipient = Uuid::new_v4();
+    let search = || {
+        positions.most_novel_coordinates(
+            &Search::new(rdp, Some(0.4)),
+            black_box(&recipient),
+            black_box(1000),
+        )
+    };
+
+    let mut group = c.benchmark_group("large_history");
+    group.sample_size(20);
+    group.bench_function("default", |b| b.iter(search));
+    #[cfg(feature = "rayon")]
+    {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        group.bench_function("single_thread", |b| b.iter(|| pool.install(search)));
+    }
+    group.finish();
+}
+
+/// Scores a single long segment, which is done in parallel with the `rayon`
+/// feature.
+fn bench_rdp_long_segment(c: &mut Criterion) {
+    let positions = generate_path(100_000);
+    let segment: Vec<_> = positions.iter().collect();
+
+    let mut group = c.benchmark_group("rdp_long_segment");
+    group.bench_function("default", |b| b.iter(|| rdp(black_box(&segment))));
+    #[cfg(feature = "rayon")]
+    {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        group.bench_function("single_thread", |b| {
+            b.iter(|| pool.install(|| rdp(black_box(&segment))));
+        });
+    }
+    group.finish();
+}
+
+/// Searches the same surface track in 2D and in 3D (with a dummy `z`
+/// ordinate), which shows the saving from using planar coordinates.
+fn bench_planar(c: &mut Criterion) {
+    let mut spatial = Positions::with_id_generator(IdGenerator::sequential(0));
+    let mut planar: Positions<_, _, Coordinate2D> =
+        Positions::with_id_generator(IdGenerator::sequential(0));
+    for datum in generate_path(5000).iter() {
+        let coordinate = datum.coordinate.horizontal();
+        spatial
+            .add(
+                datum.timestamp,
+                Coordinate::new(coordinate.x, coordinate.y, 0.0),
+            )
+            .unwrap();
+        planar.add(datum.timestamp, coordinate).unwrap();
+    }
+    let recipient = Uuid::new_v4();
+
+    let mut group = c.benchmark_group("planar");
+    group.bench_function("3d", |b| {
+        b.iter(|| {
+            spatial.most_novel_coordinates(
+                &Search::new(rdp, Some(0.4)),
+                black_box(&recipient),
+                black_box(100),
+            )
+        });
+    });
+    group.bench_function("2d", |b| {
+        b.iter(|| {
+            planar.most_novel_coordinates(
+                &Search::new(rdp, Some(0.4)),
+                black_box(&recipient),
+                black_box(100),
+            )
+        });
+    });
+    group.finish();
+}
+
+/// Ranks every point of an expanding search spiral, on which `rdp` splits
+/// segments unevenly and so takes quadratic time, in 2D and in 3D (descending
+/// as it spirals).
+fn bench_spiral(c: &mut Criterion) {
+    const LEN: i32 = 20_000;
+    let mut planar: Positions<_, _, Coordinate2D> =
+        Positions::with_id_generator(IdGenerator::sequential(0));
+    let mut spatial = Positions::with_id_generator(IdGenerator::sequential(0));
+    let start_time = Utc::now();
+    for i in 0..LEN {
+        let angle = f64::from(i) / 3.0;
+        let (x, y) = (angle * angle.cos(), angle * angle.sin());
+        let timestamp = start_time + Duration::seconds(i.into());
+        planar.add(timestamp, Coordinate2D::new(x, y)).unwrap();
+        spatial
+            .add(timestamp, Coordinate::new(x, y, -angle))
+            .unwrap();
+    }
+    let recipient = Uuid::new_v4();
+    let n_max = usize::try_from(LEN).unwrap();
+
+    let mut group = c.benchmark_group("spiral");
+    group.sample_size(10);
+    group.bench_function("2d_rdp", |b| {
+        b.iter(|| planar.most_novel_coordinates(&Search::new(rdp, None), &recipient, n_max));
+    });
+    group.bench_function("2d_hull_rdp", |b| {
+        b.iter(|| planar.most_novel_coordinates(&Search::new(HullRdp, None), &recipient, n_max));
+    });
+    group.bench_function("3d_rdp", |b| {
+        b.iter(|| spatial.most_novel_coordinates(&Search::new(rdp, None), &recipient, n_max));
+    });
+    group.bench_function("3d_hull_rdp", |b| {
+        b.iter(|| spatial.most_novel_coordinates(&Search::new(HullRdp, None), &recipient, n_max));
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_most_novel_coordinates,
+    bench_large_history,
+    bench_rdp_long_segment,
+    bench_planar,
+    bench_spiral
+);
 criterion_main!(benches);
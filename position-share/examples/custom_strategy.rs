@@ -0,0 +1,82 @@
+//! Implements a custom [`SearchStrategy`] outside of the `position-share`
+//! crate.
+//!
+//! The strategy ignores geometry entirely, and simply samples the positions
+//! which the recipient doesn't yet have at evenly spaced intervals. This is a
+//! useful baseline against which to compare [`Search`].
+
+use chrono::{Duration, Utc};
+use position_share::{
+    rdp, Coordinate, Datum, NodeId, Positions, Probability, Search, SearchStrategy,
+    TransmissionHistory,
+};
+
+/// Selects up to `n_max` evenly spaced positions which the recipient is not
+/// known to have.
+struct EvenlySpaced;
+
+impl<P> SearchStrategy<P> for EvenlySpaced {
+    fn search<'a>(
+        &self,
+        transmission_history: &TransmissionHistory,
+        positions: &[&'a Datum<P>],
+        n_max: usize,
+        recipient: &NodeId,
+    ) -> Vec<&'a Datum<P>> {
+        let candidates: Vec<_> = positions
+            .iter()
+            .copied()
+            .filter(|datum| {
+                transmission_history.probability_recipient_has_datum(recipient, &datum.id)
+                    < Probability::ONE_HUNDRED
+            })
+            .collect();
+
+        if candidates.len() <= n_max {
+            return candidates;
+        }
+
+        (0..n_max)
+            .map(|i| candidates[i * (candidates.len() - 1) / (n_max - 1).max(1)])
+            .collect()
+    }
+}
+
+fn main() {
+    let start = Utc::now();
+    let mut positions = Positions::default();
+    for i in 0..20 {
+        let t = f64::from(i);
+        positions
+            .add(
+                start + Duration::seconds(i64::from(i)),
+                Coordinate::new(t, (t / 3.0).sin() * 10.0, 0.0),
+            )
+            .unwrap();
+    }
+
+    let recipient = NodeId::new_v4();
+
+    // Mark the first position as received
+    let first = positions.iter().next().map(|datum| datum.id).unwrap();
+    positions
+        .transmission_history_mut()
+        .record(recipient, first, Probability::ONE_HUNDRED);
+
+    for (name, selection) in [
+        (
+            "evenly spaced",
+            positions.most_novel_coordinates(&EvenlySpaced, &recipient, 5),
+        ),
+        (
+            "recursive search",
+            positions.most_novel_coordinates(&Search::new(rdp, None), &recipient, 5),
+        ),
+    ] {
+        println!("{name}:");
+        for datum in selection {
+            let Coordinate { x, y, .. } = datum.coordinate;
+            println!("  {}: ({x:.1}, {y:.1})", datum.timestamp.format("%H:%M:%S"));
+        }
+    }
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use position_share::formats::csv;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = csv::read(data);
+});
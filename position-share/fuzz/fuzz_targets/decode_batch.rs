@@ -0,0 +1,30 @@
+#![no_main]
+
+use std::time::SystemTime;
+
+use libfuzzer_sys::fuzz_target;
+use position_share::{
+    codec::{decode_batch, encode_batch},
+    Coordinate, Coordinate2D, Datum,
+};
+
+fuzz_target!(|data: &[u8]| {
+    // Anything which decodes successfully must encode to the same bytes.
+    let batch: Result<Vec<Datum>, _> = decode_batch(data);
+    if let Ok(batch) = batch {
+        assert_eq!(encode_batch(&batch.iter().collect::<Vec<_>>()), data);
+    }
+    let batch: Result<Vec<Datum<(u8, Option<f64>, bool)>>, _> = decode_batch(data);
+    if let Ok(batch) = batch {
+        assert_eq!(encode_batch(&batch.iter().collect::<Vec<_>>()), data);
+    }
+    if let Ok(batch) = decode_batch::<(), u64, Coordinate>(data) {
+        assert_eq!(encode_batch(&batch.iter().collect::<Vec<_>>()), data);
+    }
+    if let Ok(batch) = decode_batch::<(), SystemTime, Coordinate>(data) {
+        assert_eq!(encode_batch(&batch.iter().collect::<Vec<_>>()), data);
+    }
+    if let Ok(batch) = decode_batch::<(), u64, Coordinate2D>(data) {
+        assert_eq!(encode_batch(&batch.iter().collect::<Vec<_>>()), data);
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use position_share::formats::geojson;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = geojson::read(data);
+});
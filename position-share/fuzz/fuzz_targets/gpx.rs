@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use position_share::formats::gpx;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = gpx::read(data);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use position_share::formats::kml;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = kml::read(data);
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use position_share::{
+    formats::nmea::{Receiver, Sentence},
+    Positions,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Receiver::default().read(data, &mut Positions::default());
+
+    if let Ok(data) = std::str::from_utf8(data) {
+        let mut receiver = Receiver::default();
+        for line in data.lines() {
+            if let Ok(Some(sentence)) = Sentence::parse(line) {
+                receiver.push(&sentence);
+            }
+        }
+    }
+});
@@ -0,0 +1,31 @@
+#![no_main]
+
+use core::time::Duration;
+use libfuzzer_sys::fuzz_target;
+use position_share::{
+    codec::framing::{Reassembler, HEADER_LEN},
+    Coordinate, Datum,
+};
+
+fuzz_target!(|data: &[u8]| {
+    // The input is a sequence of frames, each preceded by its length. Malformed
+    // frames must be rejected rather than panicking, whatever order they
+    // arrive in.
+    let mut reassembler = Reassembler::<u64>::new(Duration::from_secs(1));
+    let mut data = data;
+    let mut now = 0;
+    while let Some((&len, rest)) = data.split_first() {
+        let len = usize::from(len).min(rest.len());
+        let (frame, rest) = rest.split_at(len);
+        data = rest;
+        now += 100;
+        if let Ok(Some(message)) = reassembler.receive(frame, now) {
+            assert!(message.len() + HEADER_LEN >= frame.len());
+        }
+    }
+    for incomplete in reassembler.expire(now + 1_000) {
+        assert!(!incomplete.lost().is_empty());
+        let _ = incomplete.decode::<(u8, Option<f64>, bool), u64, Coordinate>();
+        let _: Result<Vec<Datum>, _> = incomplete.decode();
+    }
+});
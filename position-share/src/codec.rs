@@ -0,0 +1,412 @@
+//! A compact binary wire format for sharing data points between nodes.
+//!
+//! Every value sent over the wire implements the [`Codec`] trait. Implementations
+//! are provided for the primitive types, tuples, and [`Option`], so simple
+//! payloads can be shared without any additional code. Custom payloads should
+//! implement [`Codec`] directly.
+//!
+//! A batch of data points is encoded as a little-endian `u32` count, followed
+//! by each data point in turn. A data point is encoded as its ID (16 bytes),
+//! its timestamp, its coordinate, and finally its payload. A [`Coordinate`]
+//! is encoded as three `f64`s, and a [`Coordinate2D`] as just two, so planar
+//! tracks don't pay for an unused `z` ordinate.
+//! [`DateTime<Utc>`] and [`SystemTime`](std::time::SystemTime) timestamps are
+//! both encoded as microseconds since the Unix epoch (as an `i64`), and
+//! integer timestamps as themselves.
+//!
+//! Links whose frames are smaller than a message can use the [`framing`]
+//! layer on top of this format.
+//!
+//! # Example
+#![cfg_attr(feature = "std", doc = "```")]
+#![cfg_attr(not(feature = "std"), doc = "```ignore")]
+//! use chrono::{DateTime, Utc};
+//! use position_share::{codec, Coordinate, NodeId, Positions, Search, rdp};
+//!
+//! // The payload is (heading, speed)
+//! let mut positions: Positions<(f32, f32)> = Positions::default();
+//! positions.add_with_payload(Utc::now(), Coordinate::new(0.0, 0.0, 0.0), (90.0, 1.5)).unwrap();
+//! positions.add_with_payload(Utc::now(), Coordinate::new(1.0, 1.0, 0.0), (45.0, 1.5)).unwrap();
+//! positions.add_with_payload(Utc::now(), Coordinate::new(2.0, 1.0, 0.0), (90.0, 2.0)).unwrap();
+//!
+//! let selected = positions.most_novel_coordinates(&Search::new(rdp, None), &NodeId::new_v4(), 3);
+//!
+//! let bytes = codec::encode_batch(&selected);
+//! let decoded = codec::decode_batch::<(f32, f32), DateTime<Utc>, Coordinate>(&bytes).unwrap();
+//! assert_eq!(decoded.len(), 3);
+//! ```
+
+use alloc::vec::Vec;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{positions::Datum, Coordinate, Coordinate2D, Point};
+
+pub mod framing;
+
+/// A type which can be encoded to, and decoded from, the wire format.
+pub trait Codec: Sized {
+    /// Appends the encoded representation of `self` to `buf`.
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    /// Decodes a value from the front of `buf`.
+    ///
+    /// On success, `buf` is advanced past the consumed bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `buf` does not begin with a valid encoding of the
+    /// type.
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+/// An error encountered while decoding the wire format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before the value was complete.
+    UnexpectedEof,
+    /// The timestamp is outside of the representable range.
+    InvalidTimestamp,
+    /// An enum discriminant (such as the tag of an [`Option`]) was not
+    /// recognised.
+    InvalidTag(u8),
+    /// A coordinate has a NaN or infinite ordinate.
+    NonFiniteCoordinate,
+    /// There were unconsumed bytes left over after decoding a batch.
+    TrailingBytes(usize),
+}
+
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnexpectedEof => write!(f, "unexpected end of input"),
+            Self::InvalidTimestamp => write!(f, "timestamp out of range"),
+            Self::InvalidTag(tag) => write!(f, "invalid tag: {tag}"),
+            Self::NonFiniteCoordinate => write!(f, "non-finite coordinate"),
+            Self::TrailingBytes(n) => write!(f, "{n} trailing bytes after batch"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// Splits `N` bytes off the front of `buf`.
+fn take<const N: usize>(buf: &mut &[u8]) -> Result<[u8; N], DecodeError> {
+    let (head, tail) = buf
+        .split_first_chunk::<N>()
+        .ok_or(DecodeError::UnexpectedEof)?;
+    *buf = tail;
+    Ok(*head)
+}
+
+macro_rules! impl_codec_for_numeric {
+    ($($t:ty),*) => {
+        $(
+            impl Codec for $t {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+                    take(buf).map(Self::from_le_bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_for_numeric!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl Codec for () {
+    fn encode(&self, _buf: &mut Vec<u8>) {}
+
+    fn decode(_buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+
+impl Codec for bool {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(u8::from(*self));
+    }
+
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(buf)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(DecodeError::InvalidTag(tag)),
+        }
+    }
+}
+
+impl<T: Codec> Codec for Option<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.is_some().encode(buf);
+        if let Some(value) = self {
+            value.encode(buf);
+        }
+    }
+
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        if bool::decode(buf)? {
+            T::decode(buf).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+macro_rules! impl_codec_for_tuple {
+    ($($t:ident),*) => {
+        impl<$($t: Codec),*> Codec for ($($t,)*) {
+            #[allow(non_snake_case)]
+            fn encode(&self, buf: &mut Vec<u8>) {
+                let ($($t,)*) = self;
+                $($t.encode(buf);)*
+            }
+
+            fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+                Ok(($($t::decode(buf)?,)*))
+            }
+        }
+    };
+}
+
+impl_codec_for_tuple!(A);
+impl_codec_for_tuple!(A, B);
+impl_codec_for_tuple!(A, B, C);
+impl_codec_for_tuple!(A, B, C, D);
+
+impl Codec for Uuid {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        take(buf).map(Self::from_bytes)
+    }
+}
+
+impl Codec for DateTime<Utc> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.timestamp_micros().encode(buf);
+    }
+
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        Self::from_timestamp_micros(i64::decode(buf)?).ok_or(DecodeError::InvalidTimestamp)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Codec for std::time::SystemTime {
+    /// Encoded in the same way as a [`DateTime<Utc>`].
+    fn encode(&self, buf: &mut Vec<u8>) {
+        #[allow(clippy::cast_possible_truncation)]
+        let micros = match self.duration_since(Self::UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_micros() as i64,
+            Err(error) => (error.duration().as_micros() as i64).wrapping_neg(),
+        };
+        micros.encode(buf);
+    }
+
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let micros = i64::decode(buf)?;
+        let offset = std::time::Duration::from_micros(micros.unsigned_abs());
+        if micros >= 0 {
+            Self::UNIX_EPOCH.checked_add(offset)
+        } else {
+            Self::UNIX_EPOCH.checked_sub(offset)
+        }
+        .ok_or(DecodeError::InvalidTimestamp)
+    }
+}
+
+impl Codec for Coordinate {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.x.encode(buf);
+        self.y.encode(buf);
+        self.z.encode(buf);
+    }
+
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let coordinate = Self::new(f64::decode(buf)?, f64::decode(buf)?, f64::decode(buf)?);
+        if coordinate.is_finite() {
+            Ok(coordinate)
+        } else {
+            Err(DecodeError::NonFiniteCoordinate)
+        }
+    }
+}
+
+impl Codec for Coordinate2D {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.x.encode(buf);
+        self.y.encode(buf);
+    }
+
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let coordinate = Self::new(f64::decode(buf)?, f64::decode(buf)?);
+        if coordinate.is_finite() {
+            Ok(coordinate)
+        } else {
+            Err(DecodeError::NonFiniteCoordinate)
+        }
+    }
+}
+
+impl<P: Codec, T: Codec, C: Codec> Codec for Datum<P, T, C> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.id.encode(buf);
+        self.timestamp.encode(buf);
+        self.coordinate.encode(buf);
+        self.payload.encode(buf);
+    }
+
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(Self {
+            id: Uuid::decode(buf)?,
+            timestamp: T::decode(buf)?,
+            coordinate: C::decode(buf)?,
+            payload: P::decode(buf)?,
+        })
+    }
+}
+
+/// Encodes a batch of data points into a single message.
+///
+/// # Panics
+///
+/// Panics if the batch contains more than [`u32::MAX`] data points.
+#[must_use]
+pub fn encode_batch<P: Codec, T: Codec, C: Codec>(datums: &[&Datum<P, T, C>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    u32::try_from(datums.len())
+        .expect("batch is too large to encode")
+        .encode(&mut buf);
+    for datum in datums {
+        datum.encode(&mut buf);
+    }
+    buf
+}
+
+/// Decodes a message produced by [`encode_batch`].
+///
+/// The payload, timestamp, and coordinate types must match those of the
+/// encoded data points.
+///
+/// # Errors
+///
+/// Returns an error if the message is truncated, malformed, or contains
+/// trailing bytes.
+pub fn decode_batch<P: Codec, T: Codec, C: Codec>(
+    mut buf: &[u8],
+) -> Result<Vec<Datum<P, T, C>>, DecodeError> {
+    let count = u32::decode(&mut buf)?;
+    // Don't trust the count when preallocating, since the input may be malformed.
+    let mut datums = Vec::with_capacity(usize::try_from(count).unwrap_or(0).min(buf.len()));
+    for _ in 0..count {
+        datums.push(Datum::decode(&mut buf)?);
+    }
+    if buf.is_empty() {
+        Ok(datums)
+    } else {
+        Err(DecodeError::TrailingBytes(buf.len()))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_batch() {
+        let datum = Datum {
+            id: Uuid::new_v4(),
+            timestamp: DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap(),
+            coordinate: Coordinate::new(1.0, -2.0, 3.5),
+            payload: (270.0_f32, Some(12_u8), true),
+        };
+
+        let bytes = encode_batch(&[&datum, &datum]);
+        let decoded =
+            decode_batch::<(f32, Option<u8>, bool), DateTime<Utc>, Coordinate>(&bytes).unwrap();
+
+        assert_eq!(decoded.len(), 2);
+        for d in decoded {
+            assert_eq!(d, datum);
+            assert_eq!(d.coordinate, datum.coordinate);
+            assert_eq!(d.payload, datum.payload);
+        }
+    }
+
+    #[test]
+    fn round_trip_timestamps() {
+        let now = std::time::SystemTime::now();
+        let datum = Datum::new(Uuid::new_v4(), now, Coordinate::new(0.0, 0.0, 0.0), ());
+        let decoded =
+            decode_batch::<(), std::time::SystemTime, Coordinate>(&encode_batch(&[&datum]))
+                .unwrap();
+        // Only microsecond precision is preserved.
+        let error = decoded[0]
+            .timestamp
+            .duration_since(now)
+            .unwrap_or_else(|error| error.duration());
+        assert!(error.as_micros() < 1);
+
+        let datum = Datum::new(
+            Uuid::new_v4(),
+            1_234_u64,
+            Coordinate::new(0.0, 0.0, 0.0),
+            (),
+        );
+        let decoded = decode_batch::<(), u64, Coordinate>(&encode_batch(&[&datum])).unwrap();
+        assert_eq!(decoded[0].timestamp, 1_234);
+    }
+
+    #[test]
+    fn round_trip_2d() {
+        let planar = Datum::new(Uuid::new_v4(), 1_234_u64, Coordinate2D::new(1.0, -2.0), ());
+        let spatial = Datum::new(planar.id, 1_234_u64, Coordinate::new(1.0, -2.0, 0.0), ());
+
+        let bytes = encode_batch(&[&planar]);
+        // The absent z ordinate isn't sent
+        assert_eq!(bytes.len() + 8, encode_batch(&[&spatial]).len());
+
+        let decoded = decode_batch::<(), u64, Coordinate2D>(&bytes).unwrap();
+        assert_eq!(decoded[0].coordinate, planar.coordinate);
+    }
+
+    #[test]
+    fn truncated_batch() {
+        let datum = Datum {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            coordinate: Coordinate::new(0.0, 0.0, 0.0),
+            payload: 1.0_f64,
+        };
+        let bytes = encode_batch(&[&datum]);
+
+        assert_eq!(
+            decode_batch::<f64, DateTime<Utc>, Coordinate>(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn non_finite_coordinate() {
+        let datum = Datum {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            coordinate: Coordinate::new(0.0, f64::NAN, 0.0),
+            payload: (),
+        };
+        let bytes = encode_batch(&[&datum]);
+
+        assert_eq!(
+            decode_batch::<(), DateTime<Utc>, Coordinate>(&bytes),
+            Err(DecodeError::NonFiniteCoordinate)
+        );
+    }
+}
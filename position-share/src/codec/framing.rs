@@ -0,0 +1,643 @@
+//! Fragmentation of messages into frames for links with a small MTU.
+//!
+//! Some links (such as acoustic modems) can only send frames of a few tens of
+//! bytes, which is smaller than a single encoded data point. A [`Fragmenter`]
+//! splits an encoded batch into numbered frames of at most the MTU, and a
+//! [`Reassembler`] at the receiver puts them back together.
+//!
+//! A frame is a 6-byte header followed by a fragment of the encoded batch:
+//!
+//! - the message ID (a little-endian `u16`, incremented for each message)
+//! - the index of the fragment within the message (a `u8`)
+//! - the number of fragments in the message (a `u8`)
+//! - the offset within the fragment at which the first data point starting in
+//!   it begins (a little-endian `u16`, or [`u16::MAX`] if none do)
+//!
+//! So a message can have at most 255 fragments. The offsets let the receiver
+//! decode the data points in the fragments it did receive if others are lost
+//! (see [`Incomplete::decode`]). Messages which aren't complete within the
+//! timeout are reported along with their missing fragments, which the
+//! receiver can send back to the sender, so that it can mark the data points
+//! which weren't delivered as such (see [`SentMessage::mark_lost`]).
+//!
+//! # Example
+#![cfg_attr(feature = "std", doc = "```")]
+#![cfg_attr(not(feature = "std"), doc = "```ignore")]
+//! use chrono::{DateTime, Duration, Utc};
+//! use position_share::{
+//!     codec::framing::{Fragmenter, Reassembler},
+//!     Coordinate, NodeId, Positions,
+//! };
+//!
+//! let mut positions = Positions::default();
+//! let now = Utc::now();
+//! positions.add(now, Coordinate::new(0.0, 0.0, 0.0)).unwrap();
+//! positions.add(now, Coordinate::new(1.0, 1.0, 0.0)).unwrap();
+//! positions.add(now, Coordinate::new(2.0, 1.0, 0.0)).unwrap();
+//! let batch: Vec<_> = positions.iter().collect();
+//!
+//! let mut fragmenter = Fragmenter::new(32).unwrap();
+//! let message = fragmenter.fragment(&batch).unwrap();
+//! assert!(message.frames().iter().all(|frame| frame.len() <= 32));
+//!
+//! // The second frame is lost
+//! let mut reassembler = Reassembler::new(Duration::seconds(30));
+//! for (i, frame) in message.frames().iter().enumerate() {
+//!     if i != 1 {
+//!         assert_eq!(reassembler.receive(frame, now).unwrap(), None);
+//!     }
+//! }
+//! let incomplete = reassembler.expire(now + Duration::seconds(31));
+//! let lost = incomplete[0].lost();
+//! assert_eq!(lost, [1]);
+//!
+//! // The receiver keeps what it can, and the sender marks the rest as lost
+//! let received = incomplete[0].decode::<(), DateTime<Utc>, Coordinate>().unwrap();
+//! let recipient = NodeId::new_v4();
+//! message.mark_lost(positions.transmission_history_mut(), recipient, &lost);
+//! assert_eq!(received.len() + message.lost_datums(&lost).count(), 3);
+//! ```
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::ops::Range;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::{Codec, DecodeError};
+use crate::{
+    positions::Datum, probability::Probability, timestamp::Timestamp,
+    transmission_history::TransmissionHistory, NodeId,
+};
+
+/// The length of the header of each frame, in bytes.
+pub const HEADER_LEN: usize = 6;
+
+/// The offset recorded for a fragment in which no data point starts.
+const NO_BOUNDARY: u16 = u16::MAX;
+
+/// An error encountered while fragmenting or reassembling a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// The MTU leaves no room for a fragment after the header, or is too large
+    /// for the offsets in the header.
+    InvalidMtu(usize),
+    /// The encoded message (of the given length) needs more than 255
+    /// fragments.
+    MessageTooLarge(usize),
+    /// The frame is shorter than its header.
+    Truncated,
+    /// The frame's header is inconsistent, either with itself or with earlier
+    /// fragments of the same message.
+    InvalidFragment {
+        message_id: u16,
+        index: u8,
+        count: u8,
+    },
+}
+
+impl core::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidMtu(mtu) => write!(f, "invalid MTU: {mtu}"),
+            Self::MessageTooLarge(len) => {
+                write!(f, "message of {len} bytes needs more than 255 fragments")
+            }
+            Self::Truncated => write!(f, "frame is shorter than its header"),
+            Self::InvalidFragment {
+                message_id,
+                index,
+                count,
+            } => write!(
+                f,
+                "invalid fragment {index} of {count} in message {message_id}"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrameError {}
+
+/// Splits batches of data points into frames of at most the MTU.
+#[derive(Debug, Clone)]
+pub struct Fragmenter {
+    mtu: usize,
+    next_message_id: u16,
+}
+
+impl Fragmenter {
+    /// Creates a fragmenter for frames of at most `mtu` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the MTU isn't larger than [`HEADER_LEN`], or is
+    /// larger than 65,540 bytes.
+    pub const fn new(mtu: usize) -> Result<Self, FrameError> {
+        if mtu <= HEADER_LEN || mtu - HEADER_LEN >= NO_BOUNDARY as usize {
+            return Err(FrameError::InvalidMtu(mtu));
+        }
+        Ok(Self {
+            mtu,
+            next_message_id: 0,
+        })
+    }
+
+    /// Encodes a batch of data points (as [`encode_batch`](super::encode_batch)
+    /// does) and splits it into frames.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoded batch needs more than 255 frames.
+    pub fn fragment<P: Codec, T: Codec, C: Codec>(
+        &mut self,
+        batch: &[&Datum<P, T, C>],
+    ) -> Result<SentMessage, FrameError> {
+        let mut buf = Vec::new();
+        u32::try_from(batch.len())
+            .map_err(|_| FrameError::MessageTooLarge(usize::MAX))?
+            .encode(&mut buf);
+        let data: Vec<_> = batch
+            .iter()
+            .map(|datum| {
+                let start = buf.len();
+                datum.encode(&mut buf);
+                (datum.id, start..buf.len())
+            })
+            .collect();
+
+        let fragment_len = self.mtu - HEADER_LEN;
+        let count = u8::try_from(buf.len().div_ceil(fragment_len))
+            .map_err(|_| FrameError::MessageTooLarge(buf.len()))?;
+        let message_id = self.next_message_id;
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+
+        let frames = buf
+            .chunks(fragment_len)
+            .zip(0..count)
+            .map(|(fragment, index)| {
+                let start = usize::from(index) * fragment_len;
+                let first = data.partition_point(|(_, range)| range.start < start);
+                let boundary = data
+                    .get(first)
+                    .map(|(_, range)| range.start - start)
+                    .filter(|&offset| offset < fragment.len())
+                    .and_then(|offset| u16::try_from(offset).ok())
+                    .unwrap_or(NO_BOUNDARY);
+
+                let mut frame = Vec::with_capacity(HEADER_LEN + fragment.len());
+                (message_id, index, count, boundary).encode(&mut frame);
+                frame.extend_from_slice(fragment);
+                frame
+            })
+            .collect();
+
+        Ok(SentMessage {
+            message_id,
+            frames,
+            fragment_len,
+            data,
+        })
+    }
+}
+
+/// A message which has been split into frames, as returned by
+/// [`Fragmenter::fragment`].
+///
+/// The sender should keep it until it knows whether the message was
+/// delivered, so that it can mark the data points in any lost fragments as
+/// not delivered.
+#[derive(Debug, Clone)]
+pub struct SentMessage {
+    message_id: u16,
+    frames: Vec<Vec<u8>>,
+    fragment_len: usize,
+    /// The ID of each data point, and its range in the encoded batch.
+    data: Vec<(Uuid, Range<usize>)>,
+}
+
+impl SentMessage {
+    /// Returns the ID of the message.
+    #[must_use]
+    pub const fn message_id(&self) -> u16 {
+        self.message_id
+    }
+
+    /// Returns the frames to send, in order.
+    #[must_use]
+    pub fn frames(&self) -> &[Vec<u8>] {
+        &self.frames
+    }
+
+    /// Returns the IDs of the data points which were (at least partly) in the
+    /// `lost` fragments, so couldn't be decoded by the receiver.
+    pub fn lost_datums<'s>(&'s self, lost: &'s [u8]) -> impl Iterator<Item = Uuid> + 's {
+        self.data
+            .iter()
+            .filter(move |(_, range)| {
+                lost.iter().any(|&index| {
+                    let start = usize::from(index) * self.fragment_len;
+                    range.start < start + self.fragment_len && start < range.end
+                })
+            })
+            .map(|(id, _)| *id)
+    }
+
+    /// Records in the transmission history that the recipient doesn't have
+    /// the data points in the `lost` fragments, so that they are considered
+    /// for transmission again.
+    pub fn mark_lost(&self, history: &mut TransmissionHistory, recipient: NodeId, lost: &[u8]) {
+        for id in self.lost_datums(lost) {
+            history.record(recipient, id, Probability::ZERO);
+        }
+    }
+}
+
+/// Reassembles the frames of messages from a single sender.
+///
+/// Frames may arrive in any order, and duplicates are ignored. A message
+/// which hasn't been completed within the timeout of receiving its first
+/// frame is given up on (see [`Reassembler::expire`]). Frames of a message
+/// which has been completed or given up on are ignored for the timeout
+/// afterwards, rather than starting it again, after which its ID may be
+/// reused.
+///
+/// Timestamps may be of any [`Timestamp`] type, and are supplied by the
+/// caller. The timeout is a [`Timestamp::Duration`] of the same type.
+#[derive(Debug, Clone)]
+pub struct Reassembler<T: Timestamp = DateTime<Utc>> {
+    timeout: T::Duration,
+    messages: BTreeMap<u16, Partial<T>>,
+    // The messages which have been completed or given up on recently, and
+    // when
+    finished: BTreeMap<u16, T>,
+}
+
+/// A message which has been partly received.
+#[derive(Debug, Clone)]
+struct Partial<T> {
+    first_received: T,
+    fragments: Vec<Option<Fragment>>,
+}
+
+/// A fragment of a message, and the offset of the first data point starting
+/// in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fragment {
+    boundary: u16,
+    bytes: Vec<u8>,
+}
+
+impl<T: Timestamp> Reassembler<T> {
+    /// Creates a reassembler which gives up on messages after `timeout`.
+    #[must_use]
+    pub const fn new(timeout: T::Duration) -> Self {
+        Self {
+            timeout,
+            messages: BTreeMap::new(),
+            finished: BTreeMap::new(),
+        }
+    }
+
+    /// Receives a frame at time `now`.
+    ///
+    /// Returns the encoded batch (to be decoded with
+    /// [`decode_batch`](super::decode_batch)) if this frame completes its
+    /// message. Frames of a message which was recently completed or given up
+    /// on are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame's header is malformed, in which case the
+    /// frame is ignored.
+    pub fn receive(&mut self, frame: &[u8], now: T) -> Result<Option<Vec<u8>>, FrameError> {
+        let mut buf = frame;
+        let Ok((message_id, index, count, boundary)) = <(u16, u8, u8, u16)>::decode(&mut buf)
+        else {
+            return Err(FrameError::Truncated);
+        };
+        let invalid = FrameError::InvalidFragment {
+            message_id,
+            index,
+            count,
+        };
+        if index >= count || (boundary != NO_BOUNDARY && usize::from(boundary) >= buf.len()) {
+            return Err(invalid);
+        }
+
+        if let Some(finished) = self.finished.get(&message_id) {
+            if now.seconds_since(finished) < T::duration_seconds(self.timeout) {
+                return Ok(None);
+            }
+            self.finished.remove(&message_id);
+        }
+
+        let partial = self.messages.entry(message_id).or_insert_with(|| Partial {
+            first_received: now,
+            fragments: alloc::vec![None; count.into()],
+        });
+        if partial.fragments.len() != usize::from(count) {
+            return Err(invalid);
+        }
+        partial.fragments[usize::from(index)].get_or_insert_with(|| Fragment {
+            boundary,
+            bytes: buf.to_vec(),
+        });
+
+        if partial.fragments.iter().any(Option::is_none) {
+            return Ok(None);
+        }
+        self.finished.insert(message_id, now);
+        Ok(self.messages.remove(&message_id).map(|partial| {
+            partial
+                .fragments
+                .into_iter()
+                .flatten()
+                .flat_map(|fragment| fragment.bytes)
+                .collect()
+        }))
+    }
+
+    /// Gives up on the messages which haven't been completed within the
+    /// timeout, as of `now`.
+    ///
+    /// The missing fragments of each should be reported to the sender.
+    pub fn expire(&mut self, now: T) -> Vec<Incomplete> {
+        let timeout = T::duration_seconds(self.timeout);
+        let within_timeout = |then: &T| now.seconds_since(then) < timeout;
+        self.finished.retain(|_, finished| within_timeout(finished));
+
+        let mut expired = Vec::new();
+        self.messages.retain(|&message_id, partial| {
+            if within_timeout(&partial.first_received) {
+                return true;
+            }
+            expired.push(Incomplete {
+                message_id,
+                fragments: core::mem::take(&mut partial.fragments),
+            });
+            false
+        });
+        for incomplete in &expired {
+            self.finished.insert(incomplete.message_id, now);
+        }
+        expired
+    }
+}
+
+/// A message which wasn't completed in time, as returned by
+/// [`Reassembler::expire`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incomplete {
+    message_id: u16,
+    fragments: Vec<Option<Fragment>>,
+}
+
+impl Incomplete {
+    /// Returns the ID of the message.
+    #[must_use]
+    pub const fn message_id(&self) -> u16 {
+        self.message_id
+    }
+
+    /// Returns the indices of the fragments which weren't received.
+    #[must_use]
+    pub fn lost(&self) -> Vec<u8> {
+        (0..=u8::MAX)
+            .zip(&self.fragments)
+            .filter(|(_, fragment)| fragment.is_none())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Decodes every data point which lies entirely within the fragments
+    /// which were received.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fragments don't contain valid data points.
+    pub fn decode<P: Codec, T: Codec, C: Codec>(&self) -> Result<Vec<Datum<P, T, C>>, DecodeError> {
+        let mut datums = Vec::new();
+        // Each run of consecutive fragments is decoded from the first data
+        // point which starts in it, up to the first which doesn't end in it
+        for run in self.fragments.split(Option::is_none) {
+            let mut bytes = Vec::new();
+            let mut start = None;
+            for fragment in run.iter().flatten() {
+                if start.is_none() && fragment.boundary != NO_BOUNDARY {
+                    start = Some(bytes.len() + usize::from(fragment.boundary));
+                }
+                bytes.extend_from_slice(&fragment.bytes);
+            }
+            let Some(start) = start else {
+                continue;
+            };
+            let mut buf = &bytes[start..];
+            while !buf.is_empty() {
+                match Datum::decode(&mut buf) {
+                    Ok(datum) => datums.push(datum),
+                    Err(DecodeError::UnexpectedEof) => break,
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+        Ok(datums)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use core::time::Duration;
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{codec::decode_batch, Coordinate};
+
+    fn batch(len: usize) -> Vec<Datum<Option<u8>, u64, Coordinate>> {
+        (0..len)
+            .map(|i| {
+                let i = u32::try_from(i).unwrap();
+                let x = f64::from(i);
+                // Payloads of different lengths, so data points straddle
+                // fragments in different ways
+                let payload = (i % 3 == 0).then_some(7);
+                Datum::new(
+                    Uuid::new_v4(),
+                    u64::from(i),
+                    Coordinate::new(x, -x, 0.5),
+                    payload,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn invalid_mtu() {
+        assert_eq!(
+            Fragmenter::new(HEADER_LEN).unwrap_err(),
+            FrameError::InvalidMtu(HEADER_LEN)
+        );
+        assert!(Fragmenter::new(HEADER_LEN + 1).is_ok());
+        assert!(Fragmenter::new(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn message_too_large() {
+        let batch = batch(200);
+        let batch: Vec<_> = batch.iter().collect();
+        let mut fragmenter = Fragmenter::new(32).unwrap();
+        assert!(matches!(
+            fragmenter.fragment(&batch),
+            Err(FrameError::MessageTooLarge(_))
+        ));
+        assert_eq!(fragmenter.fragment(&batch[..50]).unwrap().message_id(), 0);
+        assert_eq!(fragmenter.fragment(&batch[..50]).unwrap().message_id(), 1);
+    }
+
+    #[test]
+    fn malformed_frames() {
+        let mut reassembler = Reassembler::<u64>::new(Duration::from_secs(1));
+        assert_eq!(reassembler.receive(&[0; 5], 0), Err(FrameError::Truncated));
+
+        let invalid = FrameError::InvalidFragment {
+            message_id: 0,
+            index: 2,
+            count: 2,
+        };
+        assert_eq!(reassembler.receive(&[0, 0, 2, 2, 0, 0, 1], 0), Err(invalid));
+        // The offset of the first data point is past the end of the fragment
+        assert!(reassembler.receive(&[0, 0, 0, 2, 1, 0, 1], 0).is_err());
+        // The fragment count changed
+        assert_eq!(reassembler.receive(&[0, 0, 0, 2, 0, 0, 1], 0), Ok(None));
+        assert!(reassembler.receive(&[0, 0, 1, 3, 0, 0, 1], 0).is_err());
+    }
+
+    #[test]
+    fn timeout() {
+        let batch = batch(3);
+        let batch: Vec<_> = batch.iter().collect();
+        let message = Fragmenter::new(32).unwrap().fragment(&batch).unwrap();
+        let frames = message.frames();
+
+        let mut reassembler = Reassembler::<u64>::new(Duration::from_secs(10));
+        assert_eq!(reassembler.receive(&frames[0], 0), Ok(None));
+        assert!(reassembler.expire(9_999).is_empty());
+        let expired = reassembler.expire(10_000);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(
+            expired[0].lost(),
+            (1..u8::try_from(frames.len()).unwrap()).collect::<Vec<_>>()
+        );
+
+        // Late frames of the expired message are ignored, rather than
+        // starting it again and reporting it lost twice
+        assert_eq!(reassembler.receive(&frames[1], 10_001), Ok(None));
+        assert!(reassembler.expire(30_000).is_empty());
+
+        // Until the timeout has passed again, when its ID may be reused
+        assert_eq!(reassembler.receive(&frames[1], 30_000), Ok(None));
+        assert_eq!(reassembler.expire(40_000).len(), 1);
+    }
+
+    #[test]
+    fn late_frames_of_completed_messages() {
+        let batch = batch(3);
+        let batch: Vec<_> = batch.iter().collect();
+        let message = Fragmenter::new(32).unwrap().fragment(&batch).unwrap();
+        let frames = message.frames();
+
+        let mut reassembler = Reassembler::<u64>::new(Duration::from_secs(10));
+        let mut complete = None;
+        for frame in frames {
+            complete = reassembler.receive(frame, 0).unwrap();
+        }
+        assert!(complete.is_some());
+
+        // A duplicate of the first frame arrives after the message completed,
+        // which mustn't be reported as a message missing every other fragment
+        assert_eq!(reassembler.receive(&frames[0], 5_000), Ok(None));
+        assert!(reassembler.expire(20_000).is_empty());
+
+        // Once the timeout has passed, the ID may be reused
+        for frame in frames {
+            complete = reassembler.receive(frame, 20_000).unwrap();
+        }
+        assert!(complete.is_some());
+    }
+
+    proptest! {
+        #[test]
+        fn out_of_order_and_duplicated(
+            len in 0..60usize,
+            mtu in HEADER_LEN + 1..100,
+            order in prop::collection::vec(any::<prop::sample::Index>(), 0..300),
+        ) {
+            let batch = batch(len);
+            let batch: Vec<_> = batch.iter().collect();
+            let Ok(message) = Fragmenter::new(mtu).unwrap().fragment(&batch) else {
+                return Ok(());
+            };
+            let frames = message.frames();
+            prop_assert!(frames.iter().all(|frame| frame.len() <= mtu));
+
+            // Some frames are received more than once, and out of order,
+            // before they are all received
+            let mut reassembler = Reassembler::<u64>::new(Duration::from_secs(1));
+            let mut complete = None;
+            for index in order.iter().map(|index| index.index(frames.len())).chain(0..frames.len()) {
+                if let Some(bytes) = reassembler.receive(&frames[index], 0).unwrap() {
+                    prop_assert!(complete.is_none());
+                    complete = Some(bytes);
+                }
+                if complete.is_some() {
+                    break;
+                }
+            }
+            prop_assert_eq!(complete, Some(crate::codec::encode_batch(&batch)));
+        }
+
+        #[test]
+        fn lost_fragments_are_reported(
+            len in 0..60usize,
+            mtu in HEADER_LEN + 1..100,
+            lost in prop::collection::vec(any::<bool>(), 255),
+        ) {
+            let batch = batch(len);
+            let batch: Vec<_> = batch.iter().collect();
+            let Ok(message) = Fragmenter::new(mtu).unwrap().fragment(&batch) else {
+                return Ok(());
+            };
+
+            let mut reassembler = Reassembler::<u64>::new(Duration::from_secs(1));
+            let mut received = None;
+            for (frame, _) in message.frames().iter().zip(&lost).filter(|(_, &lost)| !lost) {
+                if let Some(bytes) = reassembler.receive(frame, 0).unwrap() {
+                    received = Some(decode_batch::<Option<u8>, u64, Coordinate>(&bytes).unwrap());
+                }
+            }
+            let expired = reassembler.expire(1_000);
+            let (received, lost) = match (received, expired.as_slice()) {
+                (Some(received), []) => (received, Vec::new()),
+                (None, [incomplete]) => (incomplete.decode().unwrap(), incomplete.lost()),
+                // Every frame was lost, so the receiver doesn't know there was
+                // a message
+                (None, []) => (Vec::new(), (0..=u8::MAX).take(message.frames().len()).collect()),
+                _ => return Err(TestCaseError::fail("expected one message")),
+            };
+
+            // Every data point is either received, or reported as lost
+            let mut history = TransmissionHistory::default();
+            let recipient = NodeId::new_v4();
+            for datum in &batch {
+                history.record(recipient, datum.id, Probability::ONE_HUNDRED);
+            }
+            message.mark_lost(&mut history, recipient, &lost);
+            for datum in &batch {
+                let is_received = received.iter().any(|received| received == *datum);
+                let probability = history.probability_recipient_has_datum(&recipient, &datum.id);
+                prop_assert_eq!(is_received, probability == Probability::ONE_HUNDRED);
+            }
+        }
+    }
+}
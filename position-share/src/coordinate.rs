@@ -1,3 +1,50 @@
+//! Points in 2D and 3D Cartesian space.
+//!
+//! The novelty machinery is generic over the [`Point`] trait, so vehicles
+//! which only ever move in the plane (such as surface vessels) can use
+//! [`Coordinate2D`] rather than filling in a dummy `z` ordinate, which saves
+//! both computation and (with the [`codec`](crate::codec)) bandwidth.
+
+use core::{
+    fmt::Debug,
+    ops::{Add, Mul, Sub},
+};
+
+use crate::parallel::MaybeSync;
+
+/// A position in 2D or 3D Cartesian space, measured in metres.
+///
+/// Implemented by [`Coordinate`] (3D) and [`Coordinate2D`]. The difference
+/// between two points is a [`Displacement`], which may be scaled and added
+/// back onto a point.
+pub trait Point:
+    Copy + Debug + PartialEq + MaybeSync + Sub<Output = Self::Vector> + Add<Self::Vector, Output = Self>
+{
+    /// The displacement between two points.
+    type Vector: Displacement;
+
+    /// Returns `true` if none of the ordinates are NaN or infinite.
+    fn is_finite(&self) -> bool;
+
+    /// Returns the horizontal (x, y) components of the point.
+    fn horizontal(&self) -> Coordinate2D;
+}
+
+/// The displacement between two [`Point`]s.
+pub trait Displacement: Copy + Debug + Mul<f64, Output = Self> {
+    /// Returns the dot product of two vectors.
+    fn dot_product(&self, other: &Self) -> f64;
+
+    /// Returns the magnitude of the cross product of two vectors (the area of
+    /// the parallelogram they span).
+    fn cross_product_magnitude(&self, other: &Self) -> f64;
+
+    /// Returns the length of the vector.
+    fn magnitude(&self) -> f64 {
+        crate::math::sqrt(self.dot_product(self))
+    }
+}
+
 /// Represents a 3D coordinate.
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub struct Coordinate {
@@ -14,7 +61,75 @@ impl Coordinate {
     }
 }
 
-impl std::ops::Sub for Coordinate {
+impl Point for Coordinate {
+    type Vector = Vector;
+
+    fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
+    fn horizontal(&self) -> Coordinate2D {
+        Coordinate2D::new(self.x, self.y)
+    }
+}
+
+impl core::fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+/// Represents a 2D coordinate.
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub struct Coordinate2D {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Coordinate2D {
+    /// Creates a new `Coordinate2D`.
+    #[must_use]
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Point for Coordinate2D {
+    type Vector = Vector2D;
+
+    fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+
+    fn horizontal(&self) -> Coordinate2D {
+        *self
+    }
+}
+
+impl core::fmt::Display for Coordinate2D {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// An error returned when adding a coordinate with a NaN or infinite ordinate
+/// to a collection.
+///
+/// Such coordinates have no meaningful distance from anything else, so would
+/// make the novelty of every nearby data point meaningless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonFiniteCoordinate<C = Coordinate>(pub C);
+
+impl<C: core::fmt::Display> core::fmt::Display for NonFiniteCoordinate<C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "non-finite coordinate {}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<C: core::fmt::Display + Debug> std::error::Error for NonFiniteCoordinate<C> {}
+
+impl Sub for Coordinate {
     type Output = Vector;
 
     fn sub(self, other: Self) -> Self::Output {
@@ -22,7 +137,7 @@ impl std::ops::Sub for Coordinate {
     }
 }
 
-impl std::ops::Sub for &Coordinate {
+impl Sub for &Coordinate {
     type Output = Vector;
 
     fn sub(self, other: Self) -> Self::Output {
@@ -30,6 +145,39 @@ impl std::ops::Sub for &Coordinate {
     }
 }
 
+impl Add<Vector> for Coordinate {
+    type Output = Self;
+
+    fn add(self, vector: Vector) -> Self::Output {
+        Self::new(self.x + vector.x, self.y + vector.y, self.z + vector.z)
+    }
+}
+
+impl Sub for Coordinate2D {
+    type Output = Vector2D;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Vector2D::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Sub for &Coordinate2D {
+    type Output = Vector2D;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Vector2D::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Add<Vector2D> for Coordinate2D {
+    type Output = Self;
+
+    fn add(self, vector: Vector2D) -> Self::Output {
+        Self::new(self.x + vector.x, self.y + vector.y)
+    }
+}
+
+/// The displacement between two [`Coordinate`]s.
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub struct Vector {
     pub x: f64,
@@ -38,11 +186,13 @@ pub struct Vector {
 }
 
 impl Vector {
+    #[must_use]
     pub const fn new(x: f64, y: f64, z: f64) -> Self {
         Self { x, y, z }
     }
 
     #[allow(clippy::suboptimal_flops)] // benchmarking shows this is actually faster
+    #[must_use]
     pub fn cross_product(&self, other: &Self) -> Self {
         Self {
             x: self.y * other.z - self.z * other.y,
@@ -50,10 +200,59 @@ impl Vector {
             z: self.x * other.y - self.y * other.x,
         }
     }
+}
+
+impl Displacement for Vector {
+    #[allow(clippy::suboptimal_flops)]
+    fn dot_product(&self, other: &Self) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
 
-    #[allow(clippy::suboptimal_flops)] // benchmarking shows this is actually faster
-    pub fn magnitude(&self) -> f64 {
-        (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
+    fn cross_product_magnitude(&self, other: &Self) -> f64 {
+        self.cross_product(other).magnitude()
+    }
+}
+
+impl Mul<f64> for Vector {
+    type Output = Self;
+
+    fn mul(self, scale: f64) -> Self::Output {
+        Self::new(self.x * scale, self.y * scale, self.z * scale)
+    }
+}
+
+/// The displacement between two [`Coordinate2D`]s.
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub struct Vector2D {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vector2D {
+    #[must_use]
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Displacement for Vector2D {
+    #[allow(clippy::suboptimal_flops)]
+    fn dot_product(&self, other: &Self) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    // In the plane, the cross product only has a z component
+    #[allow(clippy::suboptimal_flops)]
+    fn cross_product_magnitude(&self, other: &Self) -> f64 {
+        crate::math::abs(self.x * other.y - self.y * other.x)
+    }
+}
+
+impl Mul<f64> for Vector2D {
+    type Output = Self;
+
+    fn mul(self, scale: f64) -> Self::Output {
+        Self::new(self.x * scale, self.y * scale)
     }
 }
 
@@ -71,9 +270,50 @@ mod tests {
         assert_eq!(v1.cross_product(&v2), expected);
     }
 
+    #[test]
+    fn test_cross_product_2d() {
+        let v1 = Vector2D::new(1.0, 2.0);
+        let v2 = Vector2D::new(4.0, 5.0);
+        assert_approx_eq!(f64, v1.cross_product_magnitude(&v2), 3.0);
+        assert_approx_eq!(
+            f64,
+            v1.cross_product_magnitude(&v2),
+            Vector::new(1.0, 2.0, 0.0).cross_product_magnitude(&Vector::new(4.0, 5.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn test_dot_product() {
+        let v1 = Vector::new(1.0, 2.0, 3.0);
+        let v2 = Vector::new(4.0, 5.0, 6.0);
+        assert_approx_eq!(f64, v1.dot_product(&v2), 32.0);
+        assert_approx_eq!(
+            f64,
+            Vector2D::new(1.0, 2.0).dot_product(&Vector2D::new(4.0, 5.0)),
+            14.0
+        );
+    }
+
+    #[test]
+    fn test_is_finite() {
+        assert!(Coordinate::new(1.0, -2.0, 0.0).is_finite());
+        assert!(!Coordinate::new(f64::NAN, 0.0, 0.0).is_finite());
+        assert!(!Coordinate::new(0.0, 0.0, f64::NEG_INFINITY).is_finite());
+        assert!(Coordinate2D::new(1.0, -2.0).is_finite());
+        assert!(!Coordinate2D::new(0.0, f64::INFINITY).is_finite());
+    }
+
     #[test]
     fn test_magnitude() {
         let v = Vector::new(3.0, 4.0, 0.0);
         assert_approx_eq!(f64, v.magnitude(), 5.0);
+        assert_approx_eq!(f64, Vector2D::new(3.0, 4.0).magnitude(), 5.0);
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let start = Coordinate2D::new(1.0, 1.0);
+        let end = Coordinate2D::new(3.0, 5.0);
+        assert_eq!(start + (end - start) * 0.5, Coordinate2D::new(2.0, 3.0));
     }
 }
@@ -0,0 +1,267 @@
+//! Import and export of tracks in common file formats.
+//!
+//! Each format is enabled by a feature flag of the same name:
+//!
+//! - `csv`: comma-separated values, with Cartesian coordinates
+//! - `gpx`: GPS Exchange Format tracks
+//! - `geojson`: GeoJSON features
+//! - `kml`: Keyhole Markup Language `gx:Track`s
+//! - `nmea`: NMEA 0183 sentences, as emitted by GPS receivers and echo
+//!   sounders (import only)
+//!
+//! Geographic formats (such as GPX and GeoJSON) describe positions by their
+//! latitude, longitude, and elevation, whereas [`Positions`] uses local
+//! Cartesian coordinates, measured in metres. These formats are read as a
+//! sequence of [`Fix`]es, which are converted to (and from) [`Coordinate`]s
+//! using a [`LocalProjection`].
+//!
+//! Each exporter accepts any sequence of data points, so the same function
+//! writes both a full track and the result of a selection.
+//!
+//! ```
+//! # #[cfg(feature = "gpx")]
+//! # {
+//! use position_share::{formats::{gpx, LocalProjection}, rdp, NodeId, Search};
+//!
+//! # let document = r#"<gpx><trk><trkseg>
+//! #   <trkpt lat="50.0" lon="-1.0"><time>2024-01-01T00:00:00Z</time></trkpt>
+//! #   <trkpt lat="50.001" lon="-1.001"><time>2024-01-01T00:00:10Z</time></trkpt>
+//! #   <trkpt lat="50.002" lon="-1.0"><time>2024-01-01T00:00:20Z</time></trkpt>
+//! # </trkseg></trk></gpx>"#;
+//! let fixes = gpx::read(document.as_bytes()).unwrap();
+//! let projection = LocalProjection::from_fixes(&fixes).unwrap();
+//! let positions = projection.positions(&fixes).unwrap();
+//!
+//! let selection =
+//!     positions.most_novel_coordinates(&Search::new(rdp, None), &NodeId::new_v4(), 2);
+//!
+//! let mut output = Vec::new();
+//! gpx::write(&mut output, &projection, selection).unwrap();
+//! # }
+//! ```
+
+use chrono::{DateTime, Utc};
+
+use crate::{Coordinate, NonFiniteCoordinate, Positions};
+
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(feature = "geojson")]
+pub mod geojson;
+#[cfg(feature = "gpx")]
+pub mod gpx;
+#[cfg(feature = "kml")]
+pub mod kml;
+#[cfg(feature = "nmea")]
+pub mod nmea;
+
+/// The mean radius of the Earth, in metres.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// A position fix in geographic coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fix {
+    /// The time at which the fix was recorded.
+    pub timestamp: DateTime<Utc>,
+    /// The latitude, in degrees north.
+    pub latitude: f64,
+    /// The longitude, in degrees east.
+    pub longitude: f64,
+    /// The height above the reference surface, in metres.
+    ///
+    /// Depths are represented by negative elevations.
+    pub elevation: f64,
+}
+
+/// Converts between geographic coordinates and local Cartesian coordinates.
+///
+/// The local coordinate system is centred on an origin, with the x-axis
+/// pointing east, the y-axis pointing north, and the z-axis pointing up (all in
+/// metres). This is an equirectangular projection, which is accurate over the
+/// distances typically covered by a single track, but not for tracks which
+/// span large distances or approach the poles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalProjection {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl LocalProjection {
+    /// Creates a projection centred on the given latitude and longitude (in
+    /// degrees).
+    #[must_use]
+    pub const fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+        }
+    }
+
+    /// Creates a projection centred on the first fix.
+    ///
+    /// Returns `None` if there are no fixes.
+    #[must_use]
+    pub fn from_fixes(fixes: &[Fix]) -> Option<Self> {
+        fixes
+            .first()
+            .map(|fix| Self::new(fix.latitude, fix.longitude))
+    }
+
+    /// Returns the latitude and longitude of the origin, in degrees.
+    #[must_use]
+    pub const fn origin(&self) -> (f64, f64) {
+        (self.latitude, self.longitude)
+    }
+
+    /// Converts a geographic position to local coordinates.
+    #[must_use]
+    pub fn project(&self, latitude: f64, longitude: f64, elevation: f64) -> Coordinate {
+        Coordinate::new(
+            EARTH_RADIUS
+                * (longitude - self.longitude).to_radians()
+                * self.latitude.to_radians().cos(),
+            EARTH_RADIUS * (latitude - self.latitude).to_radians(),
+            elevation,
+        )
+    }
+
+    /// Converts local coordinates to a geographic (latitude, longitude,
+    /// elevation) position.
+    #[must_use]
+    pub fn unproject(&self, coordinate: &Coordinate) -> (f64, f64, f64) {
+        (
+            self.latitude + (coordinate.y / EARTH_RADIUS).to_degrees(),
+            self.longitude
+                + (coordinate.x / (EARTH_RADIUS * self.latitude.to_radians().cos())).to_degrees(),
+            coordinate.z,
+        )
+    }
+
+    /// Builds a time-series of local coordinates from a sequence of fixes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a fix has a NaN or infinite latitude, longitude, or
+    /// elevation, or can't be projected (such as a fix at a pole).
+    pub fn positions(&self, fixes: &[Fix]) -> Result<Positions, NonFiniteCoordinate> {
+        let mut positions = Positions::default();
+        for fix in fixes {
+            positions.add(
+                fix.timestamp,
+                self.project(fix.latitude, fix.longitude, fix.elevation),
+            )?;
+        }
+        Ok(positions)
+    }
+}
+
+/// An error encountered while reading or writing a track.
+#[derive(Debug)]
+pub enum Error {
+    /// An error reading from, or writing to, the underlying stream.
+    Io(std::io::Error),
+    /// The input is malformed.
+    Parse {
+        /// The line on which the error occurred, if known.
+        line: Option<u64>,
+        /// A description of the error.
+        message: String,
+    },
+}
+
+impl Error {
+    /// Creates a new parse error.
+    #[cfg(any(
+        feature = "csv",
+        feature = "geojson",
+        feature = "gpx",
+        feature = "kml",
+        feature = "nmea"
+    ))]
+    pub(crate) fn parse(line: Option<u64>, message: impl Into<String>) -> Self {
+        Self::Parse {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Parse {
+                line: Some(line),
+                message,
+            } => write!(f, "line {line}: {message}"),
+            Self::Parse {
+                line: None,
+                message,
+            } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Parses an RFC 3339 timestamp (as used by all of the supported formats).
+#[cfg(any(feature = "csv", feature = "geojson", feature = "gpx", feature = "kml"))]
+fn parse_timestamp(line: Option<u64>, value: &str) -> Result<DateTime<Utc>, Error> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .map(|timestamp| timestamp.to_utc())
+        .map_err(|error| Error::parse(line, format!("invalid timestamp '{value}': {error}")))
+}
+
+/// Formats a timestamp in RFC 3339 format.
+#[cfg(any(feature = "csv", feature = "geojson", feature = "gpx", feature = "kml"))]
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+}
+
+/// Creates a parse error at an XML reader's current position.
+///
+/// XML has no natural notion of lines, so the error is reported by its byte
+/// offset instead.
+#[cfg(any(feature = "gpx", feature = "kml"))]
+fn xml_error<R>(reader: &quick_xml::Reader<R>, message: impl std::fmt::Display) -> Error {
+    Error::parse(
+        None,
+        format!("byte {}: {message}", reader.buffer_position()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn projection_round_trip() {
+        let projection = LocalProjection::new(50.0, -1.0);
+
+        // One degree of latitude is roughly 111km
+        let coordinate = projection.project(51.0, -1.0, -20.0);
+        assert_approx_eq!(f64, coordinate.x, 0.0);
+        assert!((coordinate.y - 111_195.0).abs() < 1.0);
+
+        let (latitude, longitude, elevation) =
+            projection.unproject(&projection.project(50.1, -0.9, -20.0));
+        assert_approx_eq!(f64, latitude, 50.1, epsilon = 1e-9);
+        assert_approx_eq!(f64, longitude, -0.9, epsilon = 1e-9);
+        assert_approx_eq!(f64, elevation, -20.0);
+    }
+}
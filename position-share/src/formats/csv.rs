@@ -0,0 +1,542 @@
+//! Reading and writing tracks as comma-separated values.
+//!
+//! The file must have a header row. By default, the columns are `timestamp`,
+//! `x`, `y`, and `z`, with timestamps in RFC 3339 format and coordinates in
+//! metres. Any other columns are ignored.
+//!
+//! ```text
+//! timestamp,x,y,z
+//! 2024-01-01T00:00:00Z,0.0,0.0,-10.0
+//! 2024-01-01T00:00:10Z,12.5,3.0,-10.5
+//! ```
+//!
+//! The names of the columns, the format of the timestamps, and whether the
+//! coordinates are Cartesian or geographic are all configured using
+//! [`Columns`].
+//!
+//! ```
+//! use position_share::formats::{
+//!     csv::{Columns, TimestampFormat},
+//!     LocalProjection,
+//! };
+//!
+//! let input = "\
+//! id,time,lat,lon,depth
+//! 67e55044-10b1-426f-9247-bb680e5fe0c8,1704067200,50.0,-1.0,10.0
+//! 1f1f7bd9-8d6c-4b8e-a1c4-56cd3bd3b4a4,1704067210,50.001,-1.001,10.5
+//! ";
+//!
+//! let columns = Columns::default()
+//!     .with_timestamp("time", TimestampFormat::UnixSeconds)
+//!     .with_geographic("lat", "lon", "depth", LocalProjection::new(50.0, -1.0))
+//!     .with_id("id");
+//!
+//! let positions = columns.read(input.as_bytes()).unwrap();
+//! assert_eq!(positions.len(), 2);
+//! ```
+
+use std::{
+    collections::HashSet,
+    io::{Read, Write},
+};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+use super::{format_timestamp, parse_timestamp, Error, LocalProjection};
+use crate::{Coordinate, Datum, Positions};
+
+/// Reads a track from CSV, using the default [`Columns`].
+///
+/// # Errors
+///
+/// See [`Columns::read`].
+pub fn read(reader: impl Read) -> Result<Positions, Error> {
+    Columns::default().read(reader)
+}
+
+/// Writes data points as CSV, in the order given, using the default
+/// [`Columns`].
+///
+/// # Errors
+///
+/// See [`Columns::write`].
+pub fn write<'a, P: 'a>(
+    writer: impl Write,
+    data: impl IntoIterator<Item = &'a Datum<P>>,
+) -> Result<(), Error> {
+    Columns::default().write(writer, data)
+}
+
+/// The format of timestamps.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum TimestampFormat {
+    /// RFC 3339 (for example, `2024-01-01T00:00:00Z`).
+    #[default]
+    Rfc3339,
+    /// Seconds since the Unix epoch, which may be fractional.
+    UnixSeconds,
+    /// Whole milliseconds since the Unix epoch.
+    UnixMilliseconds,
+    /// A custom [`chrono` format string](chrono::format::strftime).
+    ///
+    /// If the format has no time zone, timestamps are in UTC.
+    Custom(String),
+}
+
+impl TimestampFormat {
+    fn parse(&self, value: &str) -> Result<DateTime<Utc>, String> {
+        let value = value.trim();
+        let timestamp = match self {
+            Self::Rfc3339 => {
+                return parse_timestamp(None, value).map_err(|error| error.to_string())
+            }
+            Self::UnixSeconds => value.parse::<f64>().ok().and_then(|seconds| {
+                // Casting would saturate (or turn NaN into the epoch), so
+                // anything outside the range of `i64` is rejected first
+                let micros = (seconds * 1e6).round();
+                #[allow(clippy::cast_precision_loss)]
+                let range = i64::MIN as f64..i64::MAX as f64;
+                #[allow(clippy::cast_possible_truncation)]
+                range
+                    .contains(&micros)
+                    .then(|| DateTime::from_timestamp_micros(micros as i64))
+                    .flatten()
+            }),
+            Self::UnixMilliseconds => value.parse().ok().and_then(DateTime::from_timestamp_millis),
+            Self::Custom(format) => DateTime::parse_from_str(value, format)
+                .map(|timestamp| timestamp.to_utc())
+                .or_else(|_| {
+                    NaiveDateTime::parse_from_str(value, format)
+                        .map(|timestamp| timestamp.and_utc())
+                })
+                .ok(),
+        };
+        timestamp.ok_or_else(|| format!("invalid timestamp '{value}'"))
+    }
+
+    fn format(&self, timestamp: &DateTime<Utc>) -> String {
+        match self {
+            Self::Rfc3339 => format_timestamp(timestamp),
+            #[allow(clippy::cast_precision_loss)]
+            Self::UnixSeconds => (timestamp.timestamp_micros() as f64 / 1e6).to_string(),
+            Self::UnixMilliseconds => timestamp.timestamp_millis().to_string(),
+            Self::Custom(format) => timestamp.format(format).to_string(),
+        }
+    }
+}
+
+/// The columns holding the coordinate of each data point.
+#[derive(Debug, Clone, PartialEq)]
+enum Coordinates {
+    /// Local Cartesian coordinates, in metres.
+    Cartesian { x: String, y: String, z: String },
+    /// Geographic coordinates, in degrees, with depth in metres.
+    Geographic {
+        latitude: String,
+        longitude: String,
+        depth: String,
+        projection: LocalProjection,
+    },
+}
+
+/// The layout of a CSV file.
+///
+/// By default, the columns are `timestamp`, `x`, `y`, and `z`, with
+/// timestamps in RFC 3339 format, and no ID column. Column names are matched
+/// case-insensitively when reading.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Columns {
+    timestamp: String,
+    timestamp_format: TimestampFormat,
+    coordinates: Coordinates,
+    id: Option<String>,
+    delimiter: u8,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Self {
+            timestamp: "timestamp".to_string(),
+            timestamp_format: TimestampFormat::default(),
+            coordinates: Coordinates::Cartesian {
+                x: "x".to_string(),
+                y: "y".to_string(),
+                z: "z".to_string(),
+            },
+            id: None,
+            delimiter: b',',
+        }
+    }
+}
+
+impl Columns {
+    /// Sets the name and format of the timestamp column.
+    #[must_use]
+    pub fn with_timestamp(mut self, column: impl Into<String>, format: TimestampFormat) -> Self {
+        self.timestamp = column.into();
+        self.timestamp_format = format;
+        self
+    }
+
+    /// Sets the names of the columns holding local Cartesian coordinates.
+    #[must_use]
+    pub fn with_cartesian(
+        mut self,
+        x: impl Into<String>,
+        y: impl Into<String>,
+        z: impl Into<String>,
+    ) -> Self {
+        self.coordinates = Coordinates::Cartesian {
+            x: x.into(),
+            y: y.into(),
+            z: z.into(),
+        };
+        self
+    }
+
+    /// Sets the names of the columns holding the latitude and longitude (in
+    /// degrees), and the depth (in metres, positive downwards).
+    ///
+    /// Positions are converted to (and from) local coordinates using the given
+    /// projection.
+    #[must_use]
+    pub fn with_geographic(
+        mut self,
+        latitude: impl Into<String>,
+        longitude: impl Into<String>,
+        depth: impl Into<String>,
+        projection: LocalProjection,
+    ) -> Self {
+        self.coordinates = Coordinates::Geographic {
+            latitude: latitude.into(),
+            longitude: longitude.into(),
+            depth: depth.into(),
+            projection,
+        };
+        self
+    }
+
+    /// Sets the name of the column holding the ID of each data point.
+    ///
+    /// If there is no ID column, data points are assigned new IDs when they are
+    /// read, and their IDs aren't written.
+    #[must_use]
+    pub fn with_id(mut self, column: impl Into<String>) -> Self {
+        self.id = Some(column.into());
+        self
+    }
+
+    /// Sets the field delimiter (a comma, by default).
+    #[must_use]
+    pub const fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Reads a track.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input can't be read, is missing a required
+    /// column, contains a malformed value, or contains the same ID more than
+    /// once. Errors in the data are reported along with the line on which
+    /// they occur.
+    pub fn read(&self, reader: impl Read) -> Result<Positions, Error> {
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .from_reader(reader);
+
+        let headers = reader.headers().map_err(csv_error)?.clone();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header.trim().eq_ignore_ascii_case(name))
+                .ok_or_else(|| Error::parse(Some(1), format!("missing column '{name}'")))
+        };
+
+        let timestamp = column(&self.timestamp)?;
+        let id = self.id.as_deref().map(column).transpose()?;
+        let [a, b, c] = self.coordinate_columns().map(column);
+        let (a, b, c) = (a?, b?, c?);
+
+        let mut positions = Positions::default();
+        let mut ids = HashSet::new();
+        for record in reader.records() {
+            let record = record.map_err(csv_error)?;
+            let line = record.position().map(::csv::Position::line);
+            let field = |index: usize| {
+                record
+                    .get(index)
+                    .ok_or_else(|| Error::parse(line, format!("missing '{}'", &headers[index])))
+            };
+            let number = |index: usize| {
+                field(index)?.trim().parse::<f64>().map_err(|error| {
+                    Error::parse(line, format!("invalid {}: {error}", &headers[index]))
+                })
+            };
+
+            let timestamp = self
+                .timestamp_format
+                .parse(field(timestamp)?)
+                .map_err(|message| Error::parse(line, message))?;
+            let coordinate = match &self.coordinates {
+                Coordinates::Cartesian { .. } => {
+                    Coordinate::new(number(a)?, number(b)?, number(c)?)
+                }
+                Coordinates::Geographic { projection, .. } => {
+                    projection.project(number(a)?, number(b)?, -number(c)?)
+                }
+            };
+
+            match id {
+                Some(id) => {
+                    let value = field(id)?.trim();
+                    let id = Uuid::parse_str(value).map_err(|error| {
+                        Error::parse(line, format!("invalid ID '{value}': {error}"))
+                    })?;
+                    if !ids.insert(id) {
+                        return Err(Error::parse(line, format!("duplicate ID '{value}'")));
+                    }
+                    positions
+                        .insert(Datum::new(id, timestamp, coordinate, ()))
+                        .map_err(|error| Error::parse(line, error.to_string()))?;
+                }
+                None => {
+                    positions
+                        .add(timestamp, coordinate)
+                        .map_err(|error| Error::parse(line, error.to_string()))?;
+                }
+            }
+        }
+
+        Ok(positions)
+    }
+
+    /// Writes data points, in the order given.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the output can't be written.
+    pub fn write<'a, P: 'a>(
+        &self,
+        writer: impl Write,
+        data: impl IntoIterator<Item = &'a Datum<P>>,
+    ) -> Result<(), Error> {
+        let mut writer = ::csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(writer);
+
+        let mut headers = vec![self.timestamp.as_str()];
+        headers.extend(self.id.as_deref());
+        headers.extend(self.coordinate_columns());
+        writer.write_record(&headers).map_err(csv_error)?;
+
+        for datum in data {
+            let mut record = vec![self.timestamp_format.format(&datum.timestamp)];
+            if self.id.is_some() {
+                record.push(datum.id.to_string());
+            }
+            match &self.coordinates {
+                Coordinates::Cartesian { .. } => {
+                    let Coordinate { x, y, z } = datum.coordinate;
+                    record.extend([x, y, z].map(|ordinate| ordinate.to_string()));
+                }
+                Coordinates::Geographic { projection, .. } => {
+                    let (latitude, longitude, elevation) = projection.unproject(&datum.coordinate);
+                    record.extend(
+                        [latitude, longitude, -elevation].map(|ordinate| ordinate.to_string()),
+                    );
+                }
+            }
+            writer.write_record(&record).map_err(csv_error)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Returns the names of the three coordinate columns, in order.
+    fn coordinate_columns(&self) -> [&str; 3] {
+        match &self.coordinates {
+            Coordinates::Cartesian { x, y, z } => [x, y, z],
+            Coordinates::Geographic {
+                latitude,
+                longitude,
+                depth,
+                ..
+            } => [latitude, longitude, depth],
+        }
+    }
+}
+
+fn csv_error(error: ::csv::Error) -> Error {
+    let line = error.position().map(::csv::Position::line);
+    let message = error.to_string();
+    match error.into_kind() {
+        ::csv::ErrorKind::Io(error) => Error::Io(error),
+        _ => Error::parse(line, message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let input = "timestamp,x,y,z\n\
+                     2024-01-01T00:00:00Z,0,0,-10\n\
+                     2024-01-01T00:00:10Z,12.5,3,-10.5\n";
+
+        let positions = read(input.as_bytes()).unwrap();
+        assert_eq!(positions.len(), 2);
+
+        let mut output = Vec::new();
+        write(&mut output, positions.iter()).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), input);
+    }
+
+    #[test]
+    fn invalid_value() {
+        let input = "timestamp,x,y,z\n\
+                     2024-01-01T00:00:00Z,0,0,-10\n\
+                     2024-01-01T00:00:10Z,12.5,north,-10.5\n";
+
+        let error = read(input.as_bytes()).unwrap_err();
+        assert!(
+            matches!(error, Error::Parse { line: Some(3), .. }),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn custom_columns() {
+        let input = "When;id;east;north;up;speed\n\
+                     01/01/2024 00:00:00.5;67e55044-10b1-426f-9247-bb680e5fe0c8;1;2;3;4.5\n";
+        let columns = Columns::default()
+            .with_timestamp(
+                "when",
+                TimestampFormat::Custom("%d/%m/%Y %H:%M:%S%.f".to_string()),
+            )
+            .with_cartesian("east", "north", "up")
+            .with_id("id")
+            .with_delimiter(b';');
+
+        let positions = columns.read(input.as_bytes()).unwrap();
+        let datum = positions.iter().next().unwrap();
+        assert_eq!(datum.id.to_string(), "67e55044-10b1-426f-9247-bb680e5fe0c8");
+        assert_eq!(
+            datum.timestamp,
+            "2024-01-01T00:00:00.5Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(datum.coordinate, Coordinate::new(1.0, 2.0, 3.0));
+
+        let mut output = Vec::new();
+        columns.write(&mut output, positions.iter()).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "when;id;east;north;up\n\
+             01/01/2024 00:00:00.500;67e55044-10b1-426f-9247-bb680e5fe0c8;1;2;3\n"
+        );
+    }
+
+    #[test]
+    fn geographic_columns() {
+        let input = "time,lat,lon,depth\n\
+                     1704067200000,50.0,-1.0,10\n\
+                     1704067210000,50.001,-1.001,10.5\n";
+        let columns = Columns::default()
+            .with_timestamp("time", TimestampFormat::UnixMilliseconds)
+            .with_geographic("lat", "lon", "depth", LocalProjection::new(50.0, -1.0));
+
+        let positions = columns.read(input.as_bytes()).unwrap();
+        let data: Vec<_> = positions.iter().collect();
+        assert_eq!(data[0].coordinate, Coordinate::new(0.0, 0.0, -10.0));
+        assert!(data[1].coordinate.y > 100.0);
+
+        let mut output = Vec::new();
+        columns.write(&mut output, positions.iter()).unwrap();
+        let read_back = columns.read(output.as_slice()).unwrap();
+        for (datum, expected) in read_back.iter().zip(positions.iter()) {
+            assert_eq!(datum.timestamp, expected.timestamp);
+            assert_approx_eq!(
+                f64,
+                datum.coordinate.x,
+                expected.coordinate.x,
+                epsilon = 1e-6
+            );
+            assert_approx_eq!(
+                f64,
+                datum.coordinate.y,
+                expected.coordinate.y,
+                epsilon = 1e-6
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_unix_seconds() {
+        let columns = Columns::default().with_timestamp("timestamp", TimestampFormat::UnixSeconds);
+        let read = |timestamp: &str| {
+            columns.read(format!("timestamp,x,y,z\n0,0,0,0\n{timestamp},1,1,1\n").as_bytes())
+        };
+
+        let positions = read("1704067200.25").unwrap();
+        assert_eq!(
+            positions.iter().last().unwrap().timestamp,
+            "2024-01-01T00:00:00.25Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        for timestamp in ["NaN", "inf", "-inf", "1e300", "-1e300", "1e13"] {
+            let error = read(timestamp).unwrap_err();
+            assert!(
+                matches!(error, Error::Parse { line: Some(3), .. }),
+                "{timestamp}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_record() {
+        let input = "timestamp,x,y,z\n\
+                     2024-01-01T00:00:00Z,0,0,-10\n\
+                     2024-01-01T00:00:10Z,12.5\n";
+
+        let error = read(input.as_bytes()).unwrap_err();
+        assert!(
+            matches!(&error, Error::Parse { line: Some(3), message } if message.contains("found record with 2 fields")),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn duplicate_id() {
+        let columns = Columns::default().with_id("id");
+        for timestamp in ["2024-01-01T00:00:00Z", "2024-01-01T00:00:10Z"] {
+            let input = format!(
+                "timestamp,id,x,y,z\n\
+                 2024-01-01T00:00:00Z,67e55044-10b1-426f-9247-bb680e5fe0c8,0,0,0\n\
+                 {timestamp},67e55044-10b1-426f-9247-bb680e5fe0c8,1,1,1\n"
+            );
+            let error = columns.read(input.as_bytes()).unwrap_err();
+            assert!(
+                matches!(&error, Error::Parse { line: Some(3), message } if message.contains("duplicate ID")),
+                "{timestamp}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_column() {
+        let columns = Columns::default().with_id("id");
+        let error = columns
+            .read("timestamp,x,y,z\n2024-01-01T00:00:00Z,0,0,0\n".as_bytes())
+            .unwrap_err();
+        assert!(
+            matches!(error, Error::Parse { line: Some(1), .. }),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Reading and writing tracks as GeoJSON.
+//!
+//! GeoJSON has no standard representation of time, so tracks are read from
+//! features with a `LineString` (or `MultiLineString`) geometry, and a
+//! `coordTimes` (or `times`) property holding an RFC 3339 timestamp for each
+//! vertex. This is the convention used by most GPS tools which export GeoJSON.
+//!
+//! ```json
+//! {
+//!   "type": "Feature",
+//!   "geometry": {
+//!     "type": "LineString",
+//!     "coordinates": [[-1.0, 50.0, -10.0], [-1.001, 50.001, -10.5]]
+//!   },
+//!   "properties": {
+//!     "coordTimes": ["2024-01-01T00:00:00Z", "2024-01-01T00:00:10Z"]
+//!   }
+//! }
+//! ```
+//!
+//! The document may also be a `FeatureCollection`, in which case the vertices
+//! of every track in the collection are read, and any other features (such as
+//! waypoints) are ignored.
+//!
+//! Data points are written as a single `LineString` feature.
+
+use std::io::{Read, Write};
+
+use serde_json::{json, Value};
+
+use super::{format_timestamp, parse_timestamp, Error, Fix, LocalProjection};
+use crate::Datum;
+
+/// The names of the properties from which vertex times are read, in order of
+/// preference.
+const TIME_PROPERTIES: [&str; 2] = ["coordTimes", "times"];
+
+/// Reads the tracks in a GeoJSON `Feature` or `FeatureCollection`.
+///
+/// # Errors
+///
+/// Returns an error if the input can't be read, isn't valid JSON, or contains
+/// a track without a valid timestamp for each vertex. A single `Feature` must
+/// be a track.
+pub fn read(reader: impl Read) -> Result<Vec<Fix>, Error> {
+    let document: Value = serde_json::from_reader(reader).map_err(json_error)?;
+
+    let mut fixes = Vec::new();
+    match document["type"].as_str() {
+        Some("FeatureCollection") => {
+            let features = document["features"]
+                .as_array()
+                .ok_or_else(|| Error::parse(None, "missing features"))?;
+            for feature in features {
+                if is_track(feature) {
+                    read_track(feature, &mut fixes)?;
+                }
+            }
+        }
+        Some("Feature") if is_track(&document) => read_track(&document, &mut fixes)?,
+        Some("Feature") => {
+            return Err(Error::parse(
+                None,
+                "expected a LineString or MultiLineString geometry",
+            ))
+        }
+        _ => {
+            return Err(Error::parse(
+                None,
+                "expected a GeoJSON Feature or FeatureCollection",
+            ))
+        }
+    }
+
+    Ok(fixes)
+}
+
+/// Writes data points as a GeoJSON `LineString` feature, in the order given.
+///
+/// # Errors
+///
+/// Returns an error if the output can't be written.
+pub fn write<'a, P: 'a>(
+    mut writer: impl Write,
+    projection: &LocalProjection,
+    data: impl IntoIterator<Item = &'a Datum<P>>,
+) -> Result<(), Error> {
+    let (coordinates, times): (Vec<_>, Vec<_>) = data
+        .into_iter()
+        .map(|datum| {
+            let (latitude, longitude, elevation) = projection.unproject(&datum.coordinate);
+            (
+                json!([longitude, latitude, elevation]),
+                json!(format_timestamp(&datum.timestamp)),
+            )
+        })
+        .unzip();
+
+    let feature = json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "LineString",
+            "coordinates": coordinates,
+        },
+        "properties": {
+            "coordTimes": times,
+        },
+    });
+
+    serde_json::to_writer_pretty(&mut writer, &feature).map_err(json_error)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn is_track(feature: &Value) -> bool {
+    matches!(
+        feature["geometry"]["type"].as_str(),
+        Some("LineString" | "MultiLineString")
+    )
+}
+
+fn read_track(feature: &Value, fixes: &mut Vec<Fix>) -> Result<(), Error> {
+    let geometry = &feature["geometry"];
+    let times = TIME_PROPERTIES
+        .iter()
+        .map(|name| &feature["properties"][name])
+        .find(|times| !times.is_null())
+        .ok_or_else(|| Error::parse(None, "missing 'coordTimes' property"))?;
+
+    if geometry["type"] == "LineString" {
+        return read_line(&geometry["coordinates"], times, fixes);
+    }
+
+    let lines = array(&geometry["coordinates"], "coordinates")?;
+    let times = array(times, "times")?;
+    check_lengths(lines, times)?;
+    for (line, times) in lines.iter().zip(times) {
+        read_line(line, times, fixes)?;
+    }
+    Ok(())
+}
+
+fn read_line(coordinates: &Value, times: &Value, fixes: &mut Vec<Fix>) -> Result<(), Error> {
+    let coordinates = array(coordinates, "coordinates")?;
+    let times = array(times, "times")?;
+    check_lengths(coordinates, times)?;
+
+    for (index, (position, time)) in coordinates.iter().zip(times).enumerate() {
+        let invalid = || Error::parse(None, format!("invalid position at index {index}"));
+        let position = position.as_array().ok_or_else(invalid)?;
+        let ordinate = |i: usize| position.get(i).and_then(Value::as_f64);
+
+        fixes.push(Fix {
+            timestamp: parse_timestamp(
+                None,
+                time.as_str()
+                    .ok_or_else(|| Error::parse(None, format!("invalid time at index {index}")))?,
+            )?,
+            latitude: ordinate(1).ok_or_else(invalid)?,
+            longitude: ordinate(0).ok_or_else(invalid)?,
+            elevation: ordinate(2).unwrap_or_default(),
+        });
+    }
+    Ok(())
+}
+
+fn array<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>, Error> {
+    value
+        .as_array()
+        .ok_or_else(|| Error::parse(None, format!("invalid {name}")))
+}
+
+fn check_lengths(coordinates: &[Value], times: &[Value]) -> Result<(), Error> {
+    if coordinates.len() == times.len() {
+        Ok(())
+    } else {
+        Err(Error::parse(
+            None,
+            format!(
+                "found {} coordinates but {} times",
+                coordinates.len(),
+                times.len()
+            ),
+        ))
+    }
+}
+
+fn json_error(error: serde_json::Error) -> Error {
+    if error.is_io() {
+        return Error::Io(error.into());
+    }
+    let line = u64::try_from(error.line()).ok().filter(|&line| line > 0);
+    Error::parse(line, error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn read_feature() {
+        let document = r#"{
+            "type": "Feature",
+            "geometry": {
+                "type": "LineString",
+                "coordinates": [[-1.0, 50.0, -10.0], [-1.001, 50.001]]
+            },
+            "properties": {
+                "coordTimes": ["2024-01-01T00:00:00Z", "2024-01-01T00:00:10Z"]
+            }
+        }"#;
+
+        let fixes = read(document.as_bytes()).unwrap();
+
+        assert_eq!(fixes.len(), 2);
+        assert_approx_eq!(f64, fixes[0].latitude, 50.0);
+        assert_approx_eq!(f64, fixes[0].longitude, -1.0);
+        assert_approx_eq!(f64, fixes[0].elevation, -10.0);
+        assert_approx_eq!(f64, fixes[1].elevation, 0.0);
+    }
+
+    #[test]
+    fn mismatched_times() {
+        let document = r#"{
+            "type": "Feature",
+            "geometry": { "type": "LineString", "coordinates": [[-1.0, 50.0]] },
+            "properties": { "times": [] }
+        }"#;
+
+        assert!(read(document.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn read_feature_collection() {
+        let document = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "Point", "coordinates": [-1.0, 50.0] },
+                    "properties": {}
+                },
+                {
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "MultiLineString",
+                        "coordinates": [[[-1.0, 50.0]], [[-1.001, 50.001], [-1.002, 50.002]]]
+                    },
+                    "properties": {
+                        "coordTimes": [
+                            ["2024-01-01T00:00:00Z"],
+                            ["2024-01-01T00:00:10Z", "2024-01-01T00:00:20Z"]
+                        ]
+                    }
+                }
+            ]
+        }"#;
+
+        let fixes = read(document.as_bytes()).unwrap();
+
+        assert_eq!(fixes.len(), 3);
+        assert_approx_eq!(f64, fixes[2].latitude, 50.002);
+    }
+
+    #[test]
+    fn round_trip() {
+        let projection = LocalProjection::new(50.0, -1.0);
+        let fixes = [
+            Fix {
+                timestamp: "2024-01-01T00:00:00Z".parse().unwrap(),
+                latitude: 50.0,
+                longitude: -1.0,
+                elevation: -10.0,
+            },
+            Fix {
+                timestamp: "2024-01-01T00:00:10Z".parse().unwrap(),
+                latitude: 50.001,
+                longitude: -1.001,
+                elevation: -10.5,
+            },
+        ];
+        let positions = projection.positions(&fixes).unwrap();
+
+        let mut output = Vec::new();
+        write(&mut output, &projection, positions.iter()).unwrap();
+        let read_back = read(output.as_slice()).unwrap();
+
+        assert_eq!(read_back.len(), 2);
+        for (fix, expected) in read_back.iter().zip(&fixes) {
+            assert_eq!(fix.timestamp, expected.timestamp);
+            assert_approx_eq!(f64, fix.latitude, expected.latitude, epsilon = 1e-9);
+            assert_approx_eq!(f64, fix.longitude, expected.longitude, epsilon = 1e-9);
+            assert_approx_eq!(f64, fix.elevation, expected.elevation);
+        }
+    }
+}
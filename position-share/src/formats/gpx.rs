@@ -0,0 +1,233 @@
+//! Reading and writing tracks in the GPS Exchange Format (GPX).
+//!
+//! Every track point (`<trkpt>`) in the document is read, across all tracks and
+//! track segments, in document order. Track points must have a `<time>`
+//! element; the `<ele>` element is optional, and defaults to zero.
+//!
+//! Data points are written as the points of a single track segment.
+
+use std::io::{BufRead, Write};
+
+use chrono::{DateTime, Utc};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use super::{format_timestamp, parse_timestamp, xml_error, Error, Fix, LocalProjection};
+use crate::Datum;
+
+/// Reads every track point in a GPX document.
+///
+/// # Errors
+///
+/// Returns an error if the input can't be read, isn't well-formed XML, or
+/// contains a track point with a missing or malformed position or time.
+pub fn read(reader: impl BufRead) -> Result<Vec<Fix>, Error> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buffer = Vec::new();
+
+    let mut fixes = Vec::new();
+    let mut point: Option<Point> = None;
+    let mut element: Option<Element> = None;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buffer)
+            .map_err(|error| xml_error(&reader, &error))?;
+        match event {
+            Event::Start(start) => match start.local_name().as_ref() {
+                b"trkpt" => point = Some(Point::new(&reader, &start)?),
+                b"time" if point.is_some() => element = Some(Element::Time),
+                b"ele" if point.is_some() => element = Some(Element::Elevation),
+                _ => {}
+            },
+            Event::Text(text) => {
+                if let (Some(point), Some(element)) = (point.as_mut(), element) {
+                    let text = text
+                        .unescape()
+                        .map_err(|error| xml_error(&reader, &error))?;
+                    point.set(&reader, element, &text)?;
+                }
+            }
+            Event::End(end) => match end.local_name().as_ref() {
+                b"trkpt" => {
+                    if let Some(point) = point.take() {
+                        fixes.push(point.into_fix(&reader)?);
+                    }
+                }
+                b"time" | b"ele" => element = None,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buffer.clear();
+    }
+
+    Ok(fixes)
+}
+
+/// Writes data points as a GPX track, in the order given.
+///
+/// # Errors
+///
+/// Returns an error if the output can't be written.
+pub fn write<'a, P: 'a>(
+    mut writer: impl Write,
+    projection: &LocalProjection,
+    data: impl IntoIterator<Item = &'a Datum<P>>,
+) -> Result<(), Error> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<gpx version="1.1" creator="position-share" xmlns="http://www.topografix.com/GPX/1/1">"#
+    )?;
+    writeln!(writer, "  <trk>")?;
+    writeln!(writer, "    <trkseg>")?;
+    for datum in data {
+        let (latitude, longitude, elevation) = projection.unproject(&datum.coordinate);
+        writeln!(
+            writer,
+            r#"      <trkpt lat="{latitude}" lon="{longitude}">"#
+        )?;
+        writeln!(writer, "        <ele>{elevation}</ele>")?;
+        writeln!(
+            writer,
+            "        <time>{}</time>",
+            format_timestamp(&datum.timestamp)
+        )?;
+        writeln!(writer, "      </trkpt>")?;
+    }
+    writeln!(writer, "    </trkseg>")?;
+    writeln!(writer, "  </trk>")?;
+    writeln!(writer, "</gpx>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// A child element of a track point whose text is of interest.
+#[derive(Debug, Clone, Copy)]
+enum Element {
+    Time,
+    Elevation,
+}
+
+/// A partially read track point.
+struct Point {
+    latitude: f64,
+    longitude: f64,
+    elevation: f64,
+    timestamp: Option<DateTime<Utc>>,
+}
+
+impl Point {
+    fn new<R>(reader: &Reader<R>, start: &BytesStart) -> Result<Self, Error> {
+        let attribute = |name: &str| -> Result<f64, Error> {
+            let value = start
+                .try_get_attribute(name)
+                .map_err(|error| xml_error(reader, &error))?
+                .ok_or_else(|| xml_error(reader, format!("track point is missing '{name}'")))?
+                .unescape_value()
+                .map_err(|error| xml_error(reader, &error))?;
+            value
+                .trim()
+                .parse()
+                .map_err(|error| xml_error(reader, format!("invalid {name} '{value}': {error}")))
+        };
+
+        Ok(Self {
+            latitude: attribute("lat")?,
+            longitude: attribute("lon")?,
+            elevation: 0.0,
+            timestamp: None,
+        })
+    }
+
+    fn set<R>(&mut self, reader: &Reader<R>, element: Element, text: &str) -> Result<(), Error> {
+        match element {
+            Element::Time => {
+                self.timestamp = Some(
+                    parse_timestamp(None, text)
+                        .map_err(|error| xml_error(reader, error.to_string()))?,
+                );
+            }
+            Element::Elevation => {
+                self.elevation = text.trim().parse().map_err(|error| {
+                    xml_error(reader, format!("invalid elevation '{text}': {error}"))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn into_fix<R>(self, reader: &Reader<R>) -> Result<Fix, Error> {
+        Ok(Fix {
+            timestamp: self
+                .timestamp
+                .ok_or_else(|| xml_error(reader, "track point has no time"))?,
+            latitude: self.latitude,
+            longitude: self.longitude,
+            elevation: self.elevation,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    const DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <trk>
+    <trkseg>
+      <trkpt lat="50.0" lon="-1.0">
+        <ele>-10.5</ele>
+        <time>2024-01-01T00:00:00Z</time>
+      </trkpt>
+      <trkpt lat="50.001" lon="-1.001">
+        <time>2024-01-01T00:00:10Z</time>
+      </trkpt>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+    #[test]
+    fn read_track() {
+        let fixes = read(DOCUMENT.as_bytes()).unwrap();
+
+        assert_eq!(fixes.len(), 2);
+        assert_approx_eq!(f64, fixes[0].elevation, -10.5);
+        assert_approx_eq!(f64, fixes[1].latitude, 50.001);
+        assert_approx_eq!(f64, fixes[1].elevation, 0.0);
+        assert_eq!(
+            fixes[1].timestamp,
+            "2024-01-01T00:00:10Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_time() {
+        let document = DOCUMENT.replace("<time>2024-01-01T00:00:10Z</time>", "");
+        assert!(read(document.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let projection = LocalProjection::new(50.0, -1.0);
+        let positions = projection
+            .positions(&read(DOCUMENT.as_bytes()).unwrap())
+            .unwrap();
+
+        let mut output = Vec::new();
+        write(&mut output, &projection, positions.iter()).unwrap();
+        let fixes = read(output.as_slice()).unwrap();
+
+        assert_eq!(fixes.len(), 2);
+        assert_approx_eq!(f64, fixes[0].elevation, -10.5);
+        assert_approx_eq!(f64, fixes[1].latitude, 50.001, epsilon = 1e-9);
+        assert_approx_eq!(f64, fixes[1].longitude, -1.001, epsilon = 1e-9);
+    }
+}
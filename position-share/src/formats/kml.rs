@@ -0,0 +1,221 @@
+//! Reading and writing tracks in the Keyhole Markup Language (KML).
+//!
+//! Tracks are read from `<gx:Track>` elements, which hold a `<when>` element
+//! (an RFC 3339 timestamp) for each `<gx:coord>` element (a space-separated
+//! longitude, latitude, and altitude). Every track in the document is read, in
+//! document order.
+//!
+//! Data points are written as a single `<gx:Track>` in a `<Placemark>`.
+
+use std::io::{BufRead, Write};
+
+use chrono::{DateTime, Utc};
+use quick_xml::{events::Event, Reader};
+
+use super::{format_timestamp, parse_timestamp, xml_error, Error, Fix, LocalProjection};
+use crate::Datum;
+
+/// Reads every `gx:Track` in a KML document.
+///
+/// # Errors
+///
+/// Returns an error if the input can't be read, isn't well-formed XML, or
+/// contains a track with a malformed time or position, or with differing
+/// numbers of times and positions.
+pub fn read(reader: impl BufRead) -> Result<Vec<Fix>, Error> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buffer = Vec::new();
+
+    let mut fixes = Vec::new();
+    let mut track: Option<Track> = None;
+    let mut element: Option<Element> = None;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buffer)
+            .map_err(|error| xml_error(&reader, &error))?;
+        match event {
+            Event::Start(start) => match start.local_name().as_ref() {
+                b"Track" => track = Some(Track::default()),
+                b"when" if track.is_some() => element = Some(Element::When),
+                b"coord" if track.is_some() => element = Some(Element::Coord),
+                _ => {}
+            },
+            Event::Text(text) => {
+                if let (Some(track), Some(element)) = (track.as_mut(), element) {
+                    let text = text
+                        .unescape()
+                        .map_err(|error| xml_error(&reader, &error))?;
+                    track.push(&reader, element, &text)?;
+                }
+            }
+            Event::End(end) => match end.local_name().as_ref() {
+                b"Track" => {
+                    if let Some(track) = track.take() {
+                        track.into_fixes(&reader, &mut fixes)?;
+                    }
+                }
+                b"when" | b"coord" => element = None,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buffer.clear();
+    }
+
+    Ok(fixes)
+}
+
+/// Writes data points as a KML track, in the order given.
+///
+/// # Errors
+///
+/// Returns an error if the output can't be written.
+pub fn write<'a, P: 'a>(
+    mut writer: impl Write,
+    projection: &LocalProjection,
+    data: impl IntoIterator<Item = &'a Datum<P>>,
+) -> Result<(), Error> {
+    let data: Vec<_> = data.into_iter().collect();
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<kml xmlns="http://www.opengis.net/kml/2.2" xmlns:gx="http://www.google.com/kml/ext/2.2">"#
+    )?;
+    writeln!(writer, "  <Placemark>")?;
+    writeln!(writer, "    <gx:Track>")?;
+    for datum in &data {
+        writeln!(
+            writer,
+            "      <when>{}</when>",
+            format_timestamp(&datum.timestamp)
+        )?;
+    }
+    for datum in &data {
+        let (latitude, longitude, elevation) = projection.unproject(&datum.coordinate);
+        writeln!(
+            writer,
+            "      <gx:coord>{longitude} {latitude} {elevation}</gx:coord>"
+        )?;
+    }
+    writeln!(writer, "    </gx:Track>")?;
+    writeln!(writer, "  </Placemark>")?;
+    writeln!(writer, "</kml>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// A child element of a track whose text is of interest.
+#[derive(Debug, Clone, Copy)]
+enum Element {
+    When,
+    Coord,
+}
+
+/// A partially read track.
+#[derive(Default)]
+struct Track {
+    times: Vec<DateTime<Utc>>,
+    coordinates: Vec<(f64, f64, f64)>,
+}
+
+impl Track {
+    fn push<R>(&mut self, reader: &Reader<R>, element: Element, text: &str) -> Result<(), Error> {
+        match element {
+            Element::When => self
+                .times
+                .push(parse_timestamp(None, text).map_err(|error| xml_error(reader, error))?),
+            Element::Coord => {
+                let invalid = || xml_error(reader, format!("invalid coordinate '{text}'"));
+                let mut ordinates = text
+                    .split_whitespace()
+                    .map(|ordinate| ordinate.parse::<f64>().map_err(|_| invalid()));
+                let longitude = ordinates.next().ok_or_else(invalid)??;
+                let latitude = ordinates.next().ok_or_else(invalid)??;
+                let altitude = ordinates.next().transpose()?.unwrap_or_default();
+                self.coordinates.push((latitude, longitude, altitude));
+            }
+        }
+        Ok(())
+    }
+
+    fn into_fixes<R>(self, reader: &Reader<R>, fixes: &mut Vec<Fix>) -> Result<(), Error> {
+        if self.times.len() != self.coordinates.len() {
+            return Err(xml_error(
+                reader,
+                format!(
+                    "track has {} times but {} coordinates",
+                    self.times.len(),
+                    self.coordinates.len()
+                ),
+            ));
+        }
+
+        fixes.extend(self.times.into_iter().zip(self.coordinates).map(
+            |(timestamp, (latitude, longitude, elevation))| Fix {
+                timestamp,
+                latitude,
+                longitude,
+                elevation,
+            },
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    const DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<kml xmlns="http://www.opengis.net/kml/2.2" xmlns:gx="http://www.google.com/kml/ext/2.2">
+  <Document>
+    <Placemark>
+      <gx:Track>
+        <when>2024-01-01T00:00:00Z</when>
+        <when>2024-01-01T00:00:10Z</when>
+        <gx:coord>-1.0 50.0 -10.5</gx:coord>
+        <gx:coord>-1.001 50.001</gx:coord>
+      </gx:Track>
+    </Placemark>
+  </Document>
+</kml>"#;
+
+    #[test]
+    fn read_track() {
+        let fixes = read(DOCUMENT.as_bytes()).unwrap();
+
+        assert_eq!(fixes.len(), 2);
+        assert_approx_eq!(f64, fixes[0].longitude, -1.0);
+        assert_approx_eq!(f64, fixes[0].elevation, -10.5);
+        assert_approx_eq!(f64, fixes[1].latitude, 50.001);
+        assert_approx_eq!(f64, fixes[1].elevation, 0.0);
+    }
+
+    #[test]
+    fn mismatched_track() {
+        let document = DOCUMENT.replace("<gx:coord>-1.001 50.001</gx:coord>", "");
+        assert!(read(document.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let projection = LocalProjection::new(50.0, -1.0);
+        let positions = projection
+            .positions(&read(DOCUMENT.as_bytes()).unwrap())
+            .unwrap();
+
+        let mut output = Vec::new();
+        write(&mut output, &projection, positions.iter()).unwrap();
+        let fixes = read(output.as_slice()).unwrap();
+
+        assert_eq!(fixes.len(), 2);
+        assert_approx_eq!(f64, fixes[0].elevation, -10.5);
+        assert_approx_eq!(f64, fixes[1].latitude, 50.001, epsilon = 1e-9);
+        assert_approx_eq!(f64, fixes[1].longitude, -1.001, epsilon = 1e-9);
+    }
+}
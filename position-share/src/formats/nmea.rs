@@ -0,0 +1,412 @@
+//! Reading positions from NMEA 0183 sentences.
+//!
+//! The following sentences are understood, from any talker:
+//!
+//! - `GGA`: time, position, and altitude
+//! - `RMC`: date, time, and position
+//! - `DBT`: depth below the transducer
+//!
+//! All other sentences are ignored. Every sentence must have a valid checksum.
+//!
+//! A [`Receiver`] fuses the sentences in a stream into timestamped
+//! [`Coordinate`]s. NMEA reports the time of day with each fix, but only
+//! reports the date in `RMC` sentences, so fixes are discarded until the first
+//! `RMC` sentence has been received. Each fix is reported once, even if it is
+//! described by both `GGA` and `RMC` sentences.
+//!
+//! Once a depth has been received, each fix is placed at the most recently
+//! reported depth (as a negative z coordinate). Otherwise, fixes are placed at
+//! the altitude reported by `GGA` sentences.
+//!
+//! ```
+//! use position_share::{formats::nmea::Receiver, Positions};
+//!
+//! let stream = "\
+//! $GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A
+//! $GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47
+//! $SDDBT,36.1,f,11.0,M,6.0,F*04
+//! $GPGGA,123520,4807.040,N,01131.002,E,1,08,0.9,545.4,M,46.9,M,,*40
+//! ";
+//!
+//! let mut positions = Positions::default();
+//! let mut receiver = Receiver::default();
+//! let added = receiver.read(stream.as_bytes(), &mut positions).unwrap();
+//!
+//! assert_eq!(added, 2);
+//! ```
+
+use std::io::BufRead;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, Utc};
+
+use super::{Error, LocalProjection};
+use crate::{Coordinate, Positions};
+
+/// A parsed NMEA 0183 sentence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sentence {
+    /// Global positioning system fix data.
+    Gga {
+        /// The time of day of the fix.
+        time: NaiveTime,
+        /// The latitude and longitude of the fix (in degrees), if it is valid.
+        position: Option<(f64, f64)>,
+        /// The altitude of the fix above mean sea level, in metres.
+        altitude: Option<f64>,
+    },
+    /// Recommended minimum specific GNSS data.
+    Rmc {
+        /// The time of day of the fix.
+        time: NaiveTime,
+        /// The date of the fix.
+        date: NaiveDate,
+        /// The latitude and longitude of the fix (in degrees), if it is valid.
+        position: Option<(f64, f64)>,
+    },
+    /// Depth below transducer.
+    Dbt {
+        /// The depth, in metres.
+        depth: f64,
+    },
+}
+
+impl Sentence {
+    /// Parses a single sentence.
+    ///
+    /// Returns `None` if the sentence is of a type which isn't understood.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sentence is malformed, or its checksum is
+    /// missing or incorrect.
+    pub fn parse(sentence: &str) -> Result<Option<Self>, Error> {
+        parse_sentence(sentence).map_err(|message| Error::parse(None, message))
+    }
+}
+
+/// Fuses a stream of NMEA sentences into timestamped coordinates.
+///
+/// Geographic positions are converted to local coordinates using a
+/// [`LocalProjection`]. Unless one is provided, the projection is centred on
+/// the first fix.
+#[derive(Debug, Clone, Default)]
+pub struct Receiver {
+    projection: Option<LocalProjection>,
+    date: Option<NaiveDate>,
+    time: Option<NaiveTime>,
+    depth: Option<f64>,
+    last: Option<DateTime<Utc>>,
+}
+
+impl Receiver {
+    /// Creates a receiver which converts positions using the given projection.
+    #[must_use]
+    pub const fn with_projection(projection: LocalProjection) -> Self {
+        Self {
+            projection: Some(projection),
+            date: None,
+            time: None,
+            depth: None,
+            last: None,
+        }
+    }
+
+    /// Returns the projection used to convert positions to local coordinates.
+    ///
+    /// This is `None` until the first fix, unless a projection was provided.
+    #[must_use]
+    pub const fn projection(&self) -> Option<&LocalProjection> {
+        self.projection.as_ref()
+    }
+
+    /// Updates the receiver with a sentence.
+    ///
+    /// Returns the timestamp and coordinate of the fix, if the sentence
+    /// completes a new one.
+    pub fn push(&mut self, sentence: &Sentence) -> Option<(DateTime<Utc>, Coordinate)> {
+        match *sentence {
+            Sentence::Gga {
+                time,
+                position,
+                altitude,
+            } => {
+                self.advance(time);
+                self.fix(time, position?, altitude)
+            }
+            Sentence::Rmc {
+                time,
+                date,
+                position,
+            } => {
+                self.date = Some(date);
+                self.time = Some(time);
+                self.fix(time, position?, None)
+            }
+            Sentence::Dbt { depth } => {
+                self.depth = Some(depth);
+                None
+            }
+        }
+    }
+
+    /// Reads a stream of sentences (one per line), adding each fix to
+    /// `positions`.
+    ///
+    /// Returns the number of positions added.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stream can't be read, or contains a malformed
+    /// sentence. To skip over malformed sentences instead, parse each one with
+    /// [`Sentence::parse`] and [`push`](Self::push) it.
+    pub fn read(
+        &mut self,
+        reader: impl BufRead,
+        positions: &mut Positions,
+    ) -> Result<usize, Error> {
+        let mut added = 0;
+        for (line, sentence) in (1..).zip(reader.lines()) {
+            let sentence = sentence?;
+            if sentence.trim().is_empty() {
+                continue;
+            }
+            let sentence =
+                parse_sentence(&sentence).map_err(|message| Error::parse(Some(line), message))?;
+            if let Some((timestamp, coordinate)) =
+                sentence.and_then(|sentence| self.push(&sentence))
+            {
+                positions
+                    .add(timestamp, coordinate)
+                    .map_err(|error| Error::parse(Some(line), error.to_string()))?;
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Advances the time of day, rolling over to the next day at midnight.
+    fn advance(&mut self, time: NaiveTime) {
+        if let (Some(date), Some(previous)) = (self.date, self.time) {
+            if previous - time > TimeDelta::hours(12) {
+                self.date = date.succ_opt();
+            }
+        }
+        self.time = Some(time);
+    }
+
+    fn fix(
+        &mut self,
+        time: NaiveTime,
+        (latitude, longitude): (f64, f64),
+        altitude: Option<f64>,
+    ) -> Option<(DateTime<Utc>, Coordinate)> {
+        let timestamp = self.date?.and_time(time).and_utc();
+        if self.last.is_some_and(|last| timestamp <= last) {
+            return None;
+        }
+        self.last = Some(timestamp);
+
+        let elevation = self
+            .depth
+            .map_or_else(|| altitude.unwrap_or_default(), |depth| -depth);
+        let projection = self
+            .projection
+            .get_or_insert_with(|| LocalProjection::new(latitude, longitude));
+        Some((
+            timestamp,
+            projection.project(latitude, longitude, elevation),
+        ))
+    }
+}
+
+fn parse_sentence(sentence: &str) -> Result<Option<Sentence>, String> {
+    let sentence = sentence.trim();
+    let body = sentence
+        .strip_prefix('$')
+        .ok_or_else(|| format!("sentence '{sentence}' doesn't start with '$'"))?;
+    let (body, checksum) = body
+        .rsplit_once('*')
+        .ok_or_else(|| format!("sentence '{sentence}' has no checksum"))?;
+    let expected =
+        u8::from_str_radix(checksum, 16).map_err(|_| format!("invalid checksum '{checksum}'"))?;
+    let actual = body.bytes().fold(0, |checksum, byte| checksum ^ byte);
+    if actual != expected {
+        return Err(format!(
+            "checksum mismatch (expected {expected:02X}, calculated {actual:02X})"
+        ));
+    }
+
+    let fields: Vec<&str> = body.split(',').collect();
+    let field = |index: usize| fields.get(index).copied().unwrap_or_default();
+
+    let sentence = match fields[0].get(2..) {
+        Some("GGA") => Sentence::Gga {
+            time: time(field(1))?,
+            position: if field(6) == "0" {
+                None
+            } else {
+                position(field(2), field(3), field(4), field(5))?
+            },
+            altitude: optional_number(field(9))?,
+        },
+        Some("RMC") => Sentence::Rmc {
+            time: time(field(1))?,
+            date: NaiveDate::parse_from_str(field(9), "%d%m%y")
+                .map_err(|error| format!("invalid date '{}': {error}", field(9)))?,
+            position: if field(2) == "A" {
+                position(field(3), field(4), field(5), field(6))?
+            } else {
+                None
+            },
+        },
+        Some("DBT") => {
+            let depth = match optional_number(field(3))? {
+                Some(metres) => metres,
+                None => optional_number(field(1))?.ok_or("missing depth")? * 0.3048,
+            };
+            Sentence::Dbt { depth }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(sentence))
+}
+
+fn time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H%M%S%.f")
+        .map_err(|error| format!("invalid time '{value}': {error}"))
+}
+
+fn optional_number(value: &str) -> Result<Option<f64>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|error| format!("invalid number '{value}': {error}"))
+}
+
+/// Parses a latitude and longitude, in degrees and minutes.
+///
+/// Returns `None` if the position is empty.
+fn position(
+    latitude: &str,
+    north_south: &str,
+    longitude: &str,
+    east_west: &str,
+) -> Result<Option<(f64, f64)>, String> {
+    if latitude.is_empty() || longitude.is_empty() {
+        return Ok(None);
+    }
+    let sign = |hemisphere: &str, positive: &str, negative: &str| match hemisphere {
+        _ if hemisphere == positive => Ok(1.0),
+        _ if hemisphere == negative => Ok(-1.0),
+        _ => Err(format!("invalid hemisphere '{hemisphere}'")),
+    };
+
+    Ok(Some((
+        sign(north_south, "N", "S")? * degrees(latitude)?,
+        sign(east_west, "E", "W")? * degrees(longitude)?,
+    )))
+}
+
+/// Parses an angle in the NMEA `dddmm.mmmm` format.
+fn degrees(value: &str) -> Result<f64, String> {
+    let invalid = || format!("invalid angle '{value}'");
+    let split = value
+        .find('.')
+        .unwrap_or(value.len())
+        .checked_sub(2)
+        .ok_or_else(invalid)?;
+    let (degrees, minutes) = (value.get(..split), value.get(split..));
+    let degrees: f64 = degrees.and_then(|d| d.parse().ok()).ok_or_else(invalid)?;
+    let minutes: f64 = minutes.and_then(|m| m.parse().ok()).ok_or_else(invalid)?;
+    Ok(degrees + minutes / 60.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let sentence =
+            Sentence::parse("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47")
+                .unwrap()
+                .unwrap();
+        let Sentence::Gga {
+            time,
+            position: Some((latitude, longitude)),
+            altitude,
+        } = sentence
+        else {
+            panic!("unexpected sentence: {sentence:?}");
+        };
+
+        assert_eq!(time, NaiveTime::from_hms_opt(12, 35, 19).unwrap());
+        assert_approx_eq!(f64, latitude, 48.1173);
+        assert_approx_eq!(f64, longitude, 11.516_666_666_666_667);
+        assert_eq!(altitude, Some(545.4));
+    }
+
+    #[test]
+    fn checksum() {
+        // Missing
+        assert!(Sentence::parse("$SDDBT,36.1,f,11.0,M,6.0,F").is_err());
+        // Incorrect
+        assert!(Sentence::parse("$SDDBT,36.1,f,11.0,M,6.0,F*05").is_err());
+        // Unsupported sentences must still have a valid checksum
+        assert!(Sentence::parse("$GPGSA,A,3,,,,,,,,,,,,,,,*1C")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn fusion() {
+        let stream = "\
+            $GPGGA,235958,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*4A\n\
+            $GPRMC,235959,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*66\n\
+            $GPGGA,235959,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*4B\n\
+            $SDDBT,36.1,f,11.0,M,6.0,F*04\n\
+            $GPGGA,000000,4807.040,N,01131.002,E,1,08,0.9,545.4,M,46.9,M,,*47\n";
+
+        let mut positions = Positions::default();
+        let mut receiver = Receiver::default();
+        receiver.read(stream.as_bytes(), &mut positions).unwrap();
+
+        // The first fix has no date, and the second is reported twice
+        let data: Vec<_> = positions.iter().collect();
+        assert_eq!(data.len(), 2);
+
+        assert_eq!(
+            data[0].timestamp,
+            "1994-03-23T23:59:59Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_approx_eq!(f64, data[0].coordinate.x, 0.0);
+        assert_approx_eq!(f64, data[0].coordinate.z, 0.0);
+
+        assert_eq!(
+            data[1].timestamp,
+            "1994-03-24T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_approx_eq!(f64, data[1].coordinate.z, -11.0);
+    }
+
+    #[test]
+    fn error_line() {
+        let stream = "\
+            $SDDBT,36.1,f,11.0,M,6.0,F*04\n\
+            $SDDBT,36.1,f,11.0,M,6.0,F*05\n";
+
+        let error = Receiver::default()
+            .read(stream.as_bytes(), &mut Positions::default())
+            .unwrap_err();
+        assert!(
+            matches!(error, Error::Parse { line: Some(2), .. }),
+            "{error}"
+        );
+    }
+}
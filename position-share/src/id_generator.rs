@@ -0,0 +1,108 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use uuid::Uuid;
+
+/// A source of IDs for new data points.
+///
+/// By default, IDs are random (version 4) UUIDs. A deterministic generator can
+/// be used instead, so that repeated runs over the same input produce the
+/// same IDs (and therefore the same selections), which is useful for
+/// regression testing and simulation.
+///
+/// Without the `std` feature there is no random number generator, so there is
+/// no default, and a generator must always be given explicitly.
+///
+/// Cloning a generator shares its state, so clones never produce the same ID
+/// twice.
+///
+/// # Example
+#[cfg_attr(feature = "std", doc = "```")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// use chrono::Utc;
+/// use position_share::{Coordinate, IdGenerator, Positions};
+///
+/// let mut a = Positions::with_id_generator(IdGenerator::sequential(42));
+/// let mut b = Positions::with_id_generator(IdGenerator::sequential(42));
+///
+/// let now = Utc::now();
+/// let origin = Coordinate::new(0.0, 0.0, 0.0);
+/// assert_eq!(a.add(now, origin), b.add(now, origin));
+/// ```
+#[derive(Clone)]
+pub struct IdGenerator(Generator);
+
+#[derive(Clone)]
+enum Generator {
+    #[cfg(feature = "std")]
+    Random,
+    Custom(Arc<dyn Fn() -> Uuid + Send + Sync>),
+}
+
+impl IdGenerator {
+    /// Generates random (version 4) UUIDs.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub const fn random() -> Self {
+        Self(Generator::Random)
+    }
+
+    /// Generates IDs by counting upwards from zero, prefixed by `seed`.
+    ///
+    /// Generators with different seeds never produce the same ID.
+    #[must_use]
+    pub fn sequential(seed: u64) -> Self {
+        // Not every target has 64-bit atomics, so the counter is a `usize`.
+        let counter = AtomicUsize::new(0);
+        Self::new(move || Uuid::from_u64_pair(seed, counter.fetch_add(1, Ordering::Relaxed) as u64))
+    }
+
+    /// Generates IDs using a custom function.
+    ///
+    /// The function must never return the same ID twice.
+    pub fn new(generate: impl Fn() -> Uuid + Send + Sync + 'static) -> Self {
+        Self(Generator::Custom(Arc::new(generate)))
+    }
+
+    /// Returns the next ID.
+    #[must_use]
+    pub fn next_id(&self) -> Uuid {
+        match &self.0 {
+            #[cfg(feature = "std")]
+            Generator::Random => Uuid::new_v4(),
+            Generator::Custom(generate) => generate(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for IdGenerator {
+    fn default() -> Self {
+        Self::random()
+    }
+}
+
+impl core::fmt::Debug for IdGenerator {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0 {
+            #[cfg(feature = "std")]
+            Generator::Random => f.write_str("IdGenerator::Random"),
+            Generator::Custom(_) => f.write_str("IdGenerator::Custom"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequential() {
+        let generator = IdGenerator::sequential(1);
+        let clone = generator.clone();
+
+        assert_eq!(generator.next_id(), Uuid::from_u64_pair(1, 0));
+        assert_eq!(clone.next_id(), Uuid::from_u64_pair(1, 1));
+        assert_eq!(generator.next_id(), Uuid::from_u64_pair(1, 2));
+    }
+}
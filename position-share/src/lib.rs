@@ -6,20 +6,104 @@
 //!
 //! It also has support for situations where nodes have incomplete knowledge of
 //! which data points the other nodes have already received.
+//!
+//! Each position may carry an arbitrary payload (heading, speed, sensor
+//! readings, etc.) which is shared alongside it. Payloads are serialised using
+//! the [`Codec`](codec::Codec) trait.
+//!
+//! # 2D and 3D coordinates
+//!
+//! Positions are 3D [`Coordinate`]s by default, but the collection and the
+//! search are generic over the [`Point`] trait. Vehicles which only move in
+//! the plane (such as surface vessels) can use [`Coordinate2D`] instead, which
+//! is cheaper to search and to encode:
+//!
+#![cfg_attr(feature = "std", doc = "```")]
+#![cfg_attr(not(feature = "std"), doc = "```ignore")]
+//! use position_share::{rdp, Coordinate2D, NodeId, Positions, Search};
+//!
+//! let mut positions: Positions<(), u64, Coordinate2D> = Positions::default();
+//! positions.add(0, Coordinate2D::new(0.0, 0.0)).unwrap();
+//! positions.add(1_000, Coordinate2D::new(1.0, 1.0)).unwrap();
+//! positions.add(2_000, Coordinate2D::new(2.0, 0.0)).unwrap();
+//!
+//! let most_novel = positions.most_novel_coordinates(&Search::new(rdp, None), &NodeId::new_v4(), 2);
+//! ```
+//!
+//! # Custom search strategies
+//!
+//! The search is driven by the [`SearchStrategy`] trait, which is given the
+//! time-series as a slice of [`Datum`]s, along with the [`TransmissionHistory`]
+//! for estimating the [`Probability`] that the recipient already has each one.
+//! [`Search`] is the provided implementation, but custom strategies may be
+//! implemented outside of this crate (see the `custom_strategy` example).
+//!
+//! # Parallel search
+//!
+//! With the `rayon` feature enabled, [`Search`] searches long segments of the
+//! time-series in parallel, which speeds up the search of large histories. In
+//! this case geometric novelty strategies and payloads must be [`Sync`] (see
+//! [`MaybeSync`]). The selection is identical either way.
+//!
+//! # `no_std` support
+//!
+//! The `std` feature is enabled by default. Without it the crate only depends
+//! on `core` and `alloc`, so it can run on microcontrollers without an
+//! operating system. [`Positions`], [`Search`], [`rdp`], [`Probability`], and
+//! the [`codec`] are all available, but the `formats`, `metrics`, and
+//! `simulation` modules are not. There is no clock or random number
+//! generator either, so timestamps (of any [`Timestamp`] type) are always
+//! supplied by the caller, and IDs
+//! must be generated by an explicit [`IdGenerator`] (see
+//! [`Positions::with_id_generator`]).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![forbid(unsafe_code)]
+
+extern crate alloc;
 
 use uuid::Uuid;
+
+mod id_generator;
+pub use id_generator::IdGenerator;
+
+mod math;
+
+mod parallel;
+pub use parallel::MaybeSync;
+
 mod positions;
 mod probability;
+pub use probability::Probability;
+
+mod timestamp;
+pub use timestamp::Timestamp;
 
 mod transmission_history;
+pub use transmission_history::TransmissionHistory;
+
+pub mod codec;
+#[cfg(feature = "std")]
+pub mod formats;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod simulation;
 
 mod coordinate;
-pub use coordinate::Coordinate;
+pub use coordinate::{
+    Coordinate, Coordinate2D, Displacement, NonFiniteCoordinate, Point, Vector, Vector2D,
+};
 
 pub type NodeId = Uuid;
 
 pub use positions::{
-    geometric_novelty::{rdp, GeometricNovelty},
-    search_strategy::{Search, SearchStrategy},
-    Positions,
+    endpoint_novelty,
+    geometric_novelty::{rdp, GeometricNovelty, HullRdp, RdpWithPayload, SegmentIndex},
+    progressive::Progressive,
+    search_strategy::{
+        Candidate, Explanation, Novelty, Objective, Optimal, Rejection, Search, SearchContext,
+        SearchStrategy, Selection, TimeDecay,
+    },
+    Datum, Positions,
 };
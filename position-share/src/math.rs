@@ -0,0 +1,40 @@
+//! Floating point functions which aren't available in `core`.
+//!
+//! These use the standard library's implementations when it is available, and
+//! otherwise `libm`'s.
+
+/// Returns the square root of `x`.
+#[cfg(feature = "std")]
+pub fn sqrt(x: f64) -> f64 {
+    x.sqrt()
+}
+
+/// Returns the square root of `x`.
+#[cfg(not(feature = "std"))]
+pub fn sqrt(x: f64) -> f64 {
+    libm::sqrt(x)
+}
+
+/// Returns `2^x`.
+#[cfg(feature = "std")]
+pub fn exp2(x: f64) -> f64 {
+    x.exp2()
+}
+
+/// Returns `2^x`.
+#[cfg(not(feature = "std"))]
+pub fn exp2(x: f64) -> f64 {
+    libm::exp2(x)
+}
+
+/// Returns the absolute value of `x`.
+#[cfg(feature = "std")]
+pub fn abs(x: f64) -> f64 {
+    x.abs()
+}
+
+/// Returns the absolute value of `x`.
+#[cfg(not(feature = "std"))]
+pub fn abs(x: f64) -> f64 {
+    libm::fabs(x)
+}
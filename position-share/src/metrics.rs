@@ -0,0 +1,417 @@
+//! Metrics for quantifying how well a selection of data points represents the
+//! original track.
+//!
+//! Each metric compares the `original` time-series with a `simplified` one,
+//! which is typically the selection returned by
+//! [`Positions::most_novel_coordinates`](crate::Positions::most_novel_coordinates)
+//! (or whatever a recipient has reconstructed from the messages it received).
+//! The `original` data must be in time order (as returned by
+//! [`Positions::iter`](crate::Positions::iter)), but the `simplified` data may
+//! be in any order.
+//!
+//! The simplified track is interpreted as a polyline joining its data points in
+//! time order. All metrics return `None` if either track is empty.
+//!
+//! - [`max_sed`] and [`mean_sed`] measure the 'synchronised Euclidean
+//!   distance' (SED) between each original data point and the simplified
+//!   track, linearly interpolated at the same time. This is the error a
+//!   recipient would see if it estimated the sender's historical position from
+//!   the data it has.
+//! - [`hausdorff`] measures the greatest distance from a vertex of either track
+//!   to the nearest point on the other, ignoring time.
+//! - [`frechet`] is the discrete Fréchet distance, which additionally accounts
+//!   for the order in which the vertices are visited.
+//! - [`area`] measures the (horizontal) area enclosed between the two tracks.
+//!
+//! # Example
+//! ```
+//! use chrono::{Duration, Utc};
+//! use position_share::{metrics::ErrorMetrics, rdp, Coordinate, NodeId, Positions, Search};
+//!
+//! let start = Utc::now();
+//! let mut positions = Positions::default();
+//! for (i, (x, y)) in [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 1.0), (4.0, 0.0)]
+//!     .into_iter()
+//!     .enumerate()
+//! {
+//!     positions.add(start + Duration::seconds(i as i64), Coordinate::new(x, y, 0.0)).unwrap();
+//! }
+//!
+//! let original: Vec<_> = positions.iter().collect();
+//! let selection = positions.most_novel_coordinates(&Search::new(rdp, None), &NodeId::new_v4(), 3);
+//!
+//! let errors = ErrorMetrics::new(&original, &selection).unwrap();
+//! assert_eq!(errors.max_sed, 0.0);
+//! ```
+
+use crate::{Datum, Displacement, Point, Timestamp};
+
+/// A summary of all of the error metrics for a simplified track.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErrorMetrics {
+    /// See [`max_sed`].
+    pub max_sed: f64,
+    /// See [`mean_sed`].
+    pub mean_sed: f64,
+    /// See [`hausdorff`].
+    pub hausdorff: f64,
+    /// See [`frechet`].
+    pub frechet: f64,
+    /// See [`area`].
+    pub area: f64,
+}
+
+impl ErrorMetrics {
+    /// Calculates all of the error metrics.
+    ///
+    /// Returns `None` if either track is empty.
+    #[must_use]
+    pub fn new<P, T: Timestamp, C: Point>(
+        original: &[&Datum<P, T, C>],
+        simplified: &[&Datum<P, T, C>],
+    ) -> Option<Self> {
+        Some(Self {
+            max_sed: max_sed(original, simplified)?,
+            mean_sed: mean_sed(original, simplified)?,
+            hausdorff: hausdorff(original, simplified)?,
+            frechet: frechet(original, simplified)?,
+            area: area(original, simplified)?,
+        })
+    }
+}
+
+/// Returns the maximum synchronised Euclidean distance between the original
+/// and simplified tracks.
+#[must_use]
+pub fn max_sed<P, T: Timestamp, C: Point>(
+    original: &[&Datum<P, T, C>],
+    simplified: &[&Datum<P, T, C>],
+) -> Option<f64> {
+    sed(original, simplified)?.into_iter().reduce(f64::max)
+}
+
+/// Returns the mean synchronised Euclidean distance between the original and
+/// simplified tracks.
+#[must_use]
+pub fn mean_sed<P, T: Timestamp, C: Point>(
+    original: &[&Datum<P, T, C>],
+    simplified: &[&Datum<P, T, C>],
+) -> Option<f64> {
+    #[allow(clippy::cast_precision_loss)]
+    Some(sed(original, simplified)?.iter().sum::<f64>() / original.len() as f64)
+}
+
+/// Returns the synchronised Euclidean distance of each original data point.
+fn sed<P, T: Timestamp, C: Point>(
+    original: &[&Datum<P, T, C>],
+    simplified: &[&Datum<P, T, C>],
+) -> Option<Vec<f64>> {
+    if original.is_empty() {
+        return None;
+    }
+    let simplified = sorted(simplified)?;
+
+    Some(
+        original
+            .iter()
+            .map(|datum| (datum.coordinate - interpolate(&simplified, datum.timestamp)).magnitude())
+            .collect(),
+    )
+}
+
+/// Returns the Hausdorff distance between the original and simplified tracks.
+///
+/// This is the greatest distance from any vertex of either track to the
+/// nearest point on the other track.
+#[must_use]
+pub fn hausdorff<P, T: Timestamp, C: Point>(
+    original: &[&Datum<P, T, C>],
+    simplified: &[&Datum<P, T, C>],
+) -> Option<f64> {
+    let simplified = sorted(simplified)?;
+    let directed = |from: &[&Datum<P, T, C>], to: &[&Datum<P, T, C>]| {
+        from.iter()
+            .map(|datum| distance_from_polyline(&datum.coordinate, to))
+            .fold(0.0, f64::max)
+    };
+
+    (!original.is_empty()).then(|| {
+        f64::max(
+            directed(original, &simplified),
+            directed(&simplified, original),
+        )
+    })
+}
+
+/// Returns the discrete Fréchet distance between the original and simplified
+/// tracks.
+///
+/// This is the shortest 'leash' which could join two walkers, each stepping
+/// forwards (but never backwards) from vertex to vertex along one of the
+/// tracks.
+#[must_use]
+pub fn frechet<P, T: Timestamp, C: Point>(
+    original: &[&Datum<P, T, C>],
+    simplified: &[&Datum<P, T, C>],
+) -> Option<f64> {
+    let simplified = sorted(simplified)?;
+    if original.is_empty() {
+        return None;
+    }
+    let distance =
+        |i: usize, j: usize| (original[i].coordinate - simplified[j].coordinate).magnitude();
+
+    // Dynamic programming over the coupling matrix, keeping only one row at a
+    // time.
+    let mut row: Vec<f64> = Vec::with_capacity(simplified.len());
+    for j in 0..simplified.len() {
+        let previous = row.last().copied().unwrap_or(0.0);
+        row.push(previous.max(distance(0, j)));
+    }
+    for i in 1..original.len() {
+        let mut diagonal = row[0];
+        row[0] = row[0].max(distance(i, 0));
+        for j in 1..simplified.len() {
+            let reachable = diagonal.min(row[j]).min(row[j - 1]);
+            diagonal = row[j];
+            row[j] = reachable.max(distance(i, j));
+        }
+    }
+
+    row.last().copied()
+}
+
+/// Returns the area enclosed between the original and simplified tracks,
+/// projected onto the horizontal (x-y) plane.
+///
+/// Each original data point is compared with the segment of the simplified
+/// track which spans its timestamp. Where the original track crosses the
+/// simplified track, the areas on either side are added (rather than
+/// cancelling out).
+#[must_use]
+pub fn area<P, T: Timestamp, C: Point>(
+    original: &[&Datum<P, T, C>],
+    simplified: &[&Datum<P, T, C>],
+) -> Option<f64> {
+    let simplified = sorted(simplified)?;
+    if original.is_empty() {
+        return None;
+    }
+
+    let area = original
+        .windows(2)
+        .map(|pair| {
+            let (from, to) = (pair[0], pair[1]);
+            let (start, end) = spanning_segment(&simplified, from.timestamp);
+            area_from_chord(
+                &start.coordinate,
+                &end.coordinate,
+                &from.coordinate,
+                &to.coordinate,
+            )
+        })
+        .sum();
+
+    Some(area)
+}
+
+/// Returns the data points sorted by time, or `None` if there aren't any.
+fn sorted<'a, P, T: Timestamp, C>(data: &[&'a Datum<P, T, C>]) -> Option<Vec<&'a Datum<P, T, C>>> {
+    if data.is_empty() {
+        return None;
+    }
+    let mut data = data.to_vec();
+    data.sort_unstable();
+    Some(data)
+}
+
+/// Returns the segment of a time-ordered, non-empty sequence of data points
+/// which spans the given time.
+///
+/// Times outside of the sequence are assigned to the first or last segment.
+fn spanning_segment<'a, P, T: Timestamp, C>(
+    data: &[&'a Datum<P, T, C>],
+    time: T,
+) -> (&'a Datum<P, T, C>, &'a Datum<P, T, C>) {
+    if let [only] = data {
+        return (only, only);
+    }
+    let i = data
+        .partition_point(|datum| datum.timestamp <= time)
+        .clamp(1, data.len() - 1);
+    (data[i - 1], data[i])
+}
+
+/// Linearly interpolates a time-ordered, non-empty sequence of data points at
+/// the given time.
+///
+/// Times outside of the sequence are clamped to the first or last data point.
+fn interpolate<P, T: Timestamp, C: Point>(data: &[&Datum<P, T, C>], time: T) -> C {
+    let i = data.partition_point(|datum| datum.timestamp <= time);
+    match (i.checked_sub(1).map(|i| data[i]), data.get(i).copied()) {
+        (Some(a), Some(b)) => {
+            let fraction =
+                time.seconds_since(&a.timestamp) / b.timestamp.seconds_since(&a.timestamp);
+            a.coordinate + (b.coordinate - a.coordinate) * fraction
+        }
+        (Some(datum), None) | (None, Some(datum)) => datum.coordinate,
+        (None, None) => unreachable!("data must not be empty"),
+    }
+}
+
+/// Returns the distance from a coordinate to the nearest point on a polyline.
+fn distance_from_polyline<P, T: Timestamp, C: Point>(
+    coordinate: &C,
+    polyline: &[&Datum<P, T, C>],
+) -> f64 {
+    match polyline {
+        [only] => (*coordinate - only.coordinate).magnitude(),
+        _ => polyline
+            .windows(2)
+            .map(|pair| distance_from_segment(&pair[0].coordinate, &pair[1].coordinate, coordinate))
+            .fold(f64::INFINITY, f64::min),
+    }
+}
+
+/// Returns the distance from a coordinate to the nearest point on the segment
+/// between `start` and `end`.
+fn distance_from_segment<C: Point>(start: &C, end: &C, coordinate: &C) -> f64 {
+    let segment = *end - *start;
+    let length_squared = segment.dot_product(&segment);
+    if length_squared == 0.0 {
+        return (*coordinate - *start).magnitude();
+    }
+
+    let t = ((*coordinate - *start).dot_product(&segment) / length_squared).clamp(0.0, 1.0);
+    let closest = *start + segment * t;
+    (*coordinate - closest).magnitude()
+}
+
+/// Returns the horizontal area between the segment `from`-`to` and the chord
+/// `start`-`end`, measured along the chord.
+fn area_from_chord<C: Point>(start: &C, end: &C, from: &C, to: &C) -> f64 {
+    let chord = end.horizontal() - start.horizontal();
+    let chord_length = chord.x.hypot(chord.y);
+
+    // A degenerate chord has no direction, so approximate the area as a
+    // trapezoid of the distances from it.
+    if chord_length == 0.0 {
+        let (from_distance, to_distance) =
+            ((*from - *start).magnitude(), (*to - *start).magnitude());
+        return (*to - *from).magnitude() * (from_distance + to_distance) / 2.0;
+    }
+
+    // Project each point onto the chord, and measure its signed perpendicular
+    // distance from it.
+    let project = |coordinate: &C| {
+        let offset = coordinate.horizontal() - start.horizontal();
+        let along = chord.x.mul_add(offset.x, chord.y * offset.y) / chord_length;
+        let across = chord.x.mul_add(offset.y, -chord.y * offset.x) / chord_length;
+        (along, across)
+    };
+    let ((from_along, from_across), (to_along, to_across)) = (project(from), project(to));
+    let width = (to_along - from_along).abs();
+
+    if from_across.signum() == to_across.signum() {
+        // A trapezoid
+        width * (from_across.abs() + to_across.abs()) / 2.0
+    } else {
+        // The segment crosses the chord, forming two triangles
+        width * from_across.mul_add(from_across, to_across * to_across)
+            / (2.0 * (from_across.abs() + to_across.abs()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration};
+    use float_cmp::assert_approx_eq;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{Coordinate, Coordinate2D};
+
+    /// Coordinate arrangement:
+    ///
+    /// ```text
+    ///     y
+    ///     ^
+    ///   2 |           * (2,2)
+    ///     |
+    ///   1 |     * (1,1)     * (3,1)
+    ///     |
+    ///   0 * (0,0)                 * (4,0)
+    ///     +-----|-----|-----|-----|---->
+    ///     0     1     2     3     4     x
+    /// ```
+    fn track() -> Vec<Datum> {
+        [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 1.0), (4.0, 0.0)]
+            .into_iter()
+            .zip(0..)
+            .map(|((x, y), seconds)| {
+                Datum::new(
+                    Uuid::new_v4(),
+                    DateTime::UNIX_EPOCH + Duration::seconds(seconds),
+                    Coordinate::new(x, y, 0.0),
+                    (),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lossless_simplification() {
+        let track = track();
+        let original: Vec<_> = track.iter().collect();
+        let simplified = [&track[4], &track[0], &track[2]];
+
+        let errors = ErrorMetrics::new(&original, &simplified).unwrap();
+
+        assert_approx_eq!(f64, errors.max_sed, 0.0);
+        assert_approx_eq!(f64, errors.mean_sed, 0.0);
+        assert_approx_eq!(f64, errors.hausdorff, 0.0);
+        assert_approx_eq!(f64, errors.area, 0.0);
+        // The vertices (1,1) and (3,1) must be coupled with a vertex of the
+        // simplified track
+        assert_approx_eq!(f64, errors.frechet, 2.0_f64.sqrt());
+    }
+
+    #[test]
+    fn lossy_simplification() {
+        let track = track();
+        let original: Vec<_> = track.iter().collect();
+        let simplified = [&track[0], &track[4]];
+
+        let errors = ErrorMetrics::new(&original, &simplified).unwrap();
+
+        assert_approx_eq!(f64, errors.max_sed, 2.0);
+        assert_approx_eq!(f64, errors.mean_sed, 0.8);
+        assert_approx_eq!(f64, errors.hausdorff, 2.0);
+        assert_approx_eq!(f64, errors.area, 4.0);
+        assert_approx_eq!(f64, errors.frechet, 8.0_f64.sqrt());
+    }
+
+    #[test]
+    fn planar_simplification() {
+        let track = track();
+        let planar: Vec<Datum<(), _, Coordinate2D>> = track
+            .iter()
+            .map(|datum| Datum::new(datum.id, datum.timestamp, datum.coordinate.horizontal(), ()))
+            .collect();
+
+        let errors = ErrorMetrics::new(&track.iter().collect::<Vec<_>>(), &[&track[0], &track[4]]);
+        let planar_errors = ErrorMetrics::new(
+            &planar.iter().collect::<Vec<_>>(),
+            &[&planar[0], &planar[4]],
+        );
+        assert_eq!(errors, planar_errors);
+    }
+
+    #[test]
+    fn empty() {
+        let track = track();
+        let original: Vec<_> = track.iter().collect();
+        assert_eq!(ErrorMetrics::new(&original, &[]), None);
+        assert_eq!(ErrorMetrics::new(&[], &original), None);
+    }
+}
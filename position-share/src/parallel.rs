@@ -0,0 +1,52 @@
+//! Optional parallelism, enabled by the `rayon` feature.
+//!
+//! Without the feature everything here runs sequentially, so the rest of the
+//! crate can be written once for both cases.
+
+/// A marker for types which may be shared between threads during a search.
+///
+/// With the `rayon` feature enabled this is equivalent to [`Sync`], as
+/// geometric novelty strategies and payloads are shared between the threads
+/// searching each segment. Otherwise it is implemented for every type.
+#[cfg(feature = "rayon")]
+pub trait MaybeSync: Sync {}
+
+#[cfg(feature = "rayon")]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+/// A marker for types which may be shared between threads during a search.
+///
+/// With the `rayon` feature enabled this is equivalent to [`Sync`], as
+/// geometric novelty strategies and payloads are shared between the threads
+/// searching each segment. Otherwise it is implemented for every type.
+#[cfg(not(feature = "rayon"))]
+pub trait MaybeSync {}
+
+#[cfg(not(feature = "rayon"))]
+impl<T: ?Sized> MaybeSync for T {}
+
+/// Segments shorter than this are searched sequentially, as the cost of
+/// handing work to another thread outweighs the benefit.
+pub const MIN_PARALLEL_LEN: usize = 4096;
+
+/// Runs two closures, potentially in parallel, and returns both results.
+#[cfg(feature = "rayon")]
+pub fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    rayon::join(a, b)
+}
+
+/// Runs two closures, potentially in parallel, and returns both results.
+#[cfg(not(feature = "rayon"))]
+pub fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA,
+    B: FnOnce() -> RB,
+{
+    (a(), b())
+}
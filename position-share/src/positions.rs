@@ -1,47 +1,223 @@
-use std::collections::BTreeSet;
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use chrono::{DateTime, Utc};
-use search_strategy::SearchStrategy;
+use endpoint_novelty::EndpointNovelty;
+use geometric_novelty::GeometricNovelty;
+use progressive::Progressive;
+use search_strategy::{Explanation, Search, SearchContext, SearchStrategy, Selection};
 use uuid::Uuid;
 
+pub mod endpoint_novelty;
 pub mod geometric_novelty;
+pub mod progressive;
 pub mod search_strategy;
 
-use crate::{coordinate::Coordinate, transmission_history::TransmissionHistory};
+use crate::{
+    coordinate::{Coordinate, NonFiniteCoordinate, Point},
+    id_generator::IdGenerator,
+    parallel::MaybeSync,
+    timestamp::Timestamp,
+    transmission_history::TransmissionHistory,
+};
 
 type NodeId = Uuid;
 
-/// A time-series collection of 3D coordinates.
+/// A time-series collection of 2D or 3D coordinates.
+///
+/// Each coordinate may carry an arbitrary payload `P` (heading, speed, sensor
+/// readings, etc.) which is shared alongside it. By default there is no
+/// payload.
+///
+/// Coordinates may be of any [`Point`] type, but default to the 3D
+/// [`Coordinate`].
 ///
 /// Supports efficient filtering and searching by time.
-#[derive(Debug, Clone, Default)]
-pub struct Positions {
+#[derive(Debug, Clone)]
+pub struct Positions<P = (), T = DateTime<Utc>, C = Coordinate> {
     transmission_history: TransmissionHistory,
-    data: BTreeSet<Datum>,
+    data: BTreeSet<Datum<P, T, C>>,
+    id_generator: IdGenerator,
+    collection: CollectionId,
+    // Incremented whenever a data point is added
+    version: u64,
+    // The version at which a data point was last added anywhere but the end
+    edited: u64,
+}
+
+/// Identifies a version of the contents of a collection, so that a
+/// [`SearchContext`] can tell which collection it last searched, and whether
+/// it has changed since.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Revision {
+    pub collection: usize,
+    pub version: u64,
+    /// The last version in which a data point was added anywhere but the
+    /// end.
+    pub edited: u64,
+}
+
+/// A unique ID for each collection.
+///
+/// Data points in different collections may share IDs (such as those from
+/// sequential ID generators), so a clone is a different collection, with a new
+/// ID.
+#[derive(Debug)]
+struct CollectionId(usize);
+
+impl CollectionId {
+    fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
 }
 
-impl Positions {
+impl Clone for CollectionId {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+/// A collection which assigns random IDs to new data points.
+#[cfg(feature = "std")]
+impl<P, T: Timestamp, C: Point> Default for Positions<P, T, C> {
+    fn default() -> Self {
+        Self::with_id_generator(IdGenerator::default())
+    }
+}
+
+impl<T: Timestamp, C: Point> Positions<(), T, C> {
     /// Adds a new position to the collection.
     ///
     /// This method inserts a new data point into the collection with the
     /// specified timestamp and coordinate. The method returns the ID of the
     /// newly added data point.
-    pub fn add(&mut self, timestamp: DateTime<Utc>, position: Coordinate) -> Uuid {
-        let id = Uuid::new_v4();
-        self.data.insert(Datum {
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any ordinate of the coordinate is NaN or infinite.
+    pub fn add(&mut self, timestamp: T, position: C) -> Result<Uuid, NonFiniteCoordinate<C>> {
+        self.add_with_payload(timestamp, position, ())
+    }
+}
+
+impl<P, T: Timestamp, C: Point> Positions<P, T, C> {
+    /// Creates an empty collection, which assigns IDs to new data points using
+    /// the given generator.
+    ///
+    /// See [`IdGenerator`].
+    #[must_use]
+    pub fn with_id_generator(id_generator: IdGenerator) -> Self {
+        Self {
+            transmission_history: TransmissionHistory::default(),
+            data: BTreeSet::new(),
+            id_generator,
+            collection: CollectionId::new(),
+            version: 0,
+            edited: 0,
+        }
+    }
+
+    /// Adds a new position, along with its payload, to the collection.
+    ///
+    /// The method returns the ID of the newly added data point.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any ordinate of the coordinate is NaN or infinite.
+    pub fn add_with_payload(
+        &mut self,
+        timestamp: T,
+        position: C,
+        payload: P,
+    ) -> Result<Uuid, NonFiniteCoordinate<C>> {
+        if !position.is_finite() {
+            return Err(NonFiniteCoordinate(position));
+        }
+        let id = self.id_generator.next_id();
+        self.record(Datum {
             id,
             timestamp,
             coordinate: position,
+            payload,
         });
-        id
+        Ok(id)
+    }
+
+    /// Inserts an existing data point into the collection.
+    ///
+    /// This is typically used by a recipient to store data points received
+    /// from another node, preserving their original IDs.
+    ///
+    /// Returns `false` if the collection already contained the data point.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any ordinate of the coordinate is NaN or infinite.
+    pub fn insert(&mut self, datum: Datum<P, T, C>) -> Result<bool, NonFiniteCoordinate<C>> {
+        if !datum.coordinate.is_finite() {
+            return Err(NonFiniteCoordinate(datum.coordinate));
+        }
+        Ok(self.record(datum))
+    }
+
+    /// Inserts a data point, keeping track of the collection's revision.
+    fn record(&mut self, datum: Datum<P, T, C>) -> bool {
+        let appended = self.data.last().map_or(true, |last| *last < datum);
+        if !self.data.insert(datum) {
+            return false;
+        }
+        self.version += 1;
+        if !appended {
+            self.edited = self.version;
+        }
+        true
+    }
+
+    /// Returns the number of positions in the collection.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns `true` if the collection contains no positions.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns an iterator over the positions, in time order.
+    pub fn iter(&self) -> impl Iterator<Item = &Datum<P, T, C>> {
+        self.data.iter()
+    }
+
+    /// Returns the current version of the collection's contents.
+    pub(crate) const fn revision(&self) -> Revision {
+        Revision {
+            collection: self.collection.0,
+            version: self.version,
+            edited: self.edited,
+        }
+    }
+
+    /// Returns the record of which positions have been shared with which
+    /// recipients.
+    #[must_use]
+    pub const fn transmission_history(&self) -> &TransmissionHistory {
+        &self.transmission_history
+    }
+
+    /// Returns a mutable reference to the record of which positions have been
+    /// shared with which recipients.
+    ///
+    /// This should be updated as data points are sent (and acknowledged), so
+    /// that they are not needlessly sent again.
+    pub fn transmission_history_mut(&mut self) -> &mut TransmissionHistory {
+        &mut self.transmission_history
     }
 
     /// Filters positions by a time range.
-    pub fn filter_by_time(
-        &self,
-        start: DateTime<Utc>,
-        end: DateTime<Utc>,
-    ) -> impl Iterator<Item = &Datum> {
+    pub fn filter_by_time(&self, start: T, end: T) -> impl Iterator<Item = &Datum<P, T, C>> {
         self.data
             .iter()
             .filter(move |datum| start <= datum.timestamp && datum.timestamp <= end)
@@ -58,10 +234,10 @@ impl Positions {
     #[must_use]
     pub fn most_novel_coordinates(
         &self,
-        strategy: &impl SearchStrategy,
+        strategy: &impl SearchStrategy<P, T, C>,
         recipient: &NodeId,
         n_max: usize,
-    ) -> Vec<&Datum> {
+    ) -> Vec<&Datum<P, T, C>> {
         strategy.search(
             &self.transmission_history,
             &self.data.iter().collect::<Vec<_>>(),
@@ -69,38 +245,266 @@ impl Positions {
             recipient,
         )
     }
+
+    /// Returns the most novel coordinates for a given recipient, reusing the
+    /// buffers in `context`.
+    ///
+    /// This makes the same selection as [`Positions::most_novel_coordinates`],
+    /// but doesn't allocate once the context's buffers have grown to fit. This
+    /// is worthwhile when searching frequently, such as every time a message
+    /// can be sent.
+    ///
+    /// See [`SearchContext`].
+    pub fn most_novel_coordinates_with_context<'c, S, E>(
+        &self,
+        context: &'c mut SearchContext<T>,
+        strategy: &Search<S, E, T>,
+        recipient: &NodeId,
+        n_max: usize,
+    ) -> Selection<'c, '_, P, T, C>
+    where
+        S: GeometricNovelty<P, T, C> + MaybeSync + 'static,
+        E: EndpointNovelty<P, T, C>,
+        P: MaybeSync,
+        T: MaybeSync,
+    {
+        strategy.search_with_context(context, self, n_max, recipient)
+    }
+
+    /// Returns the fewest coordinates needed to describe the track to within
+    /// the strategy's tolerance (see [`Search::with_tolerance`]), in time
+    /// order.
+    ///
+    /// Every coordinate left out is within the tolerance of the chord between
+    /// the coordinates kept either side of it, as measured by the strategy's
+    /// geometric novelty, and no smaller selection achieves this. It's found
+    /// as the shortest path through the 'shortcut graph' of segments within
+    /// the tolerance (after Imai and Iri), so unlike
+    /// [`Positions::most_novel_coordinates`] it may leave out a coordinate
+    /// which is more novel than one it keeps. Only the strategy's geometric
+    /// novelty and tolerance are used.
+    ///
+    /// The first and last coordinates are always kept. Coordinates which the
+    /// recipient already has are part of the track it knows, so are kept
+    /// without being returned, as they needn't be sent again. Without a
+    /// tolerance, only coordinates with no novelty at all (such as those
+    /// exactly on the line between their neighbours) are left out.
+    ///
+    /// Finding the selection takes O(n²) segment searches, so O(n³) time with
+    /// [`rdp`](crate::rdp) (about a second for 1000 data points). This is
+    /// intended for offline use. Strategies which index the time-series, such
+    /// as [`HullRdp`](crate::HullRdp), search each segment more quickly.
+    ///
+    /// # Example
+    #[cfg_attr(feature = "std", doc = "```")]
+    #[cfg_attr(not(feature = "std"), doc = "```ignore")]
+    /// use chrono::{Duration, Utc};
+    /// use position_share::{rdp, Coordinate, NodeId, Positions, Search};
+    ///
+    /// let mut positions = Positions::default();
+    /// let start = Utc::now();
+    /// for (i, (x, y)) in [(0.0, 0.0), (10.0, 5.0), (20.0, 8.0), (30.0, 5.0), (40.0, 0.0)]
+    ///     .into_iter()
+    ///     .enumerate()
+    /// {
+    ///     let timestamp = start + Duration::seconds(i64::try_from(i).unwrap());
+    ///     positions.add(timestamp, Coordinate::new(x, y, 0.0)).unwrap();
+    /// }
+    ///
+    /// // The sides of the hill are within 1 m of straight, but its top isn't
+    /// let tolerance = 1.0;
+    /// let search_strategy = Search::new(rdp, None).with_tolerance(tolerance);
+    /// let simplified = positions.simplify(&search_strategy, &NodeId::new_v4());
+    /// let x: Vec<_> = simplified.iter().map(|datum| datum.coordinate.x).collect();
+    /// assert_eq!(x, [0.0, 20.0, 40.0]);
+    ///
+    /// // Every coordinate left out is within the tolerance of the chord
+    /// // between the selected coordinates either side of it
+    /// let data: Vec<_> = positions.iter().collect();
+    /// let selected: Vec<_> = (0..data.len())
+    ///     .filter(|&i| simplified.iter().any(|datum| datum.id == data[i].id))
+    ///     .collect();
+    /// for pair in selected.windows(2) {
+    ///     if let Some((_, distance, _)) = rdp(&data[pair[0]..=pair[1]]) {
+    ///         assert!(distance <= tolerance);
+    ///     }
+    /// }
+    /// ```
+    #[must_use]
+    pub fn simplify<S, E>(
+        &self,
+        strategy: &Search<S, E, T>,
+        recipient: &NodeId,
+    ) -> Vec<&Datum<P, T, C>>
+    where
+        S: GeometricNovelty<P, T, C> + MaybeSync,
+        P: MaybeSync,
+        T: MaybeSync,
+    {
+        strategy.simplify(
+            &self.transmission_history,
+            &self.data.iter().collect::<Vec<_>>(),
+            recipient,
+        )
+    }
+
+    /// Returns an iterator over the coordinates in progressive refinement
+    /// order, for streaming the whole track to a recipient.
+    ///
+    /// Any prefix of the iterator is a good approximation of the track, so if
+    /// the link drops part-way through the recipient is still left with a
+    /// useful track. Coordinates which the recipient already has aren't
+    /// returned. See [`Progressive`].
+    ///
+    /// # Example
+    #[cfg_attr(feature = "std", doc = "```")]
+    #[cfg_attr(not(feature = "std"), doc = "```ignore")]
+    /// use chrono::{Duration, Utc};
+    /// use position_share::{codec, rdp, Coordinate, NodeId, Positions};
+    ///
+    /// let mut positions = Positions::default();
+    /// let start = Utc::now();
+    /// for i in 0..100 {
+    ///     let x = f64::from(i);
+    ///     positions
+    ///         .add(start + Duration::seconds(i.into()), Coordinate::new(x, (x / 10.0).sin(), 0.0))
+    ///         .unwrap();
+    /// }
+    ///
+    /// // Stream the track in batches of 8, most important first
+    /// let mut progressive = positions.progressive(rdp, &NodeId::new_v4());
+    /// let mut messages = Vec::new();
+    /// loop {
+    ///     let batch: Vec<_> = progressive.by_ref().take(8).collect();
+    ///     if batch.is_empty() {
+    ///         break;
+    ///     }
+    ///     messages.push(codec::encode_batch(&batch));
+    /// }
+    ///
+    /// // If only the first two messages arrive, the recipient still has the
+    /// // endpoints and the rough shape of the track
+    /// let mut received: Positions = Positions::default();
+    /// for message in &messages[..2] {
+    ///     for datum in codec::decode_batch(message).unwrap() {
+    ///         received.insert(datum).unwrap();
+    ///     }
+    /// }
+    /// assert_eq!(received.len(), 16);
+    /// assert_eq!(
+    ///     received.iter().last().map(|datum| datum.id),
+    ///     positions.iter().last().map(|datum| datum.id)
+    /// );
+    /// ```
+    pub fn progressive<S>(&self, strategy: S, recipient: &NodeId) -> Progressive<'_, S, P, T, C>
+    where
+        S: GeometricNovelty<P, T, C> + MaybeSync,
+        P: MaybeSync,
+        T: MaybeSync,
+    {
+        Progressive::new(
+            strategy,
+            &self.transmission_history,
+            self.data.iter().collect(),
+            recipient,
+        )
+    }
+
+    /// Returns the most novel coordinates for a given recipient, explaining
+    /// why each was selected.
+    ///
+    /// This makes the same selection as [`Positions::most_novel_coordinates`],
+    /// but each datum is accompanied by its novelty score. Up to `n_rejected`
+    /// of the most novel candidates which were *not* selected are also
+    /// returned, which is useful for logging and debugging selection
+    /// decisions.
+    ///
+    /// See [`Search::explain`].
+    #[must_use]
+    pub fn explain_most_novel_coordinates<S, E>(
+        &self,
+        strategy: &Search<S, E, T>,
+        recipient: &NodeId,
+        n_max: usize,
+        n_rejected: usize,
+    ) -> Explanation<'_, P, T, C>
+    where
+        S: GeometricNovelty<P, T, C> + MaybeSync,
+        E: EndpointNovelty<P, T, C>,
+        P: MaybeSync,
+        T: MaybeSync,
+    {
+        strategy.explain(
+            &self.transmission_history,
+            &self.data.iter().collect::<Vec<_>>(),
+            n_max,
+            recipient,
+            n_rejected,
+        )
+    }
 }
 
 /// A single data point in the time-series.
-#[derive(Debug, Clone, PartialEq)]
-pub struct Datum {
+///
+/// Data points are identified by their timestamp and ID. The coordinate and
+/// payload do not take part in comparisons.
+///
+/// The timestamp may be of any [`Timestamp`] type, but defaults to
+/// [`DateTime<Utc>`]. Likewise, the coordinate may be of any [`Point`] type,
+/// but defaults to the 3D [`Coordinate`].
+#[derive(Debug, Clone)]
+pub struct Datum<P = (), T = DateTime<Utc>, C = Coordinate> {
+    /// The unique ID of the data point.
     pub id: Uuid,
-    pub timestamp: DateTime<Utc>,
-    pub coordinate: Coordinate,
+    /// The time at which the position was recorded.
+    pub timestamp: T,
+    /// The recorded position.
+    pub coordinate: C,
+    /// Additional data shared alongside the position.
+    pub payload: P,
 }
 
-impl Ord for Datum {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+impl<P, T, C> Datum<P, T, C> {
+    /// Creates a new data point.
+    #[must_use]
+    pub const fn new(id: Uuid, timestamp: T, coordinate: C, payload: P) -> Self {
+        Self {
+            id,
+            timestamp,
+            coordinate,
+            payload,
+        }
+    }
+}
+
+impl<P, T: Ord, C> Ord for Datum<P, T, C> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.timestamp
             .cmp(&other.timestamp)
             .then_with(|| self.id.cmp(&other.id))
     }
 }
 
-impl PartialOrd for Datum {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+impl<P, T: Ord, C> PartialOrd for Datum<P, T, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Eq for Datum {}
+impl<P, T: PartialEq, C> PartialEq for Datum<P, T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.timestamp == other.timestamp && self.id == other.id
+    }
+}
+
+impl<P, T: Eq, C> Eq for Datum<P, T, C> {}
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use geometric_novelty::rdp;
-    use search_strategy::Search;
 
     use super::*;
+    use crate::{Coordinate2D, HullRdp, Probability};
 
     #[test]
     fn test_most_novel_coordinates() {
@@ -117,11 +521,21 @@ mod tests {
         //     0     1     2     3     4     x
 
         let mut positions = Positions::default();
-        let id0 = positions.add(Utc::now(), Coordinate::new(0.0, 0.0, 0.0));
-        let _id1 = positions.add(Utc::now(), Coordinate::new(1.0, 1.0, 0.0));
-        let id2 = positions.add(Utc::now(), Coordinate::new(2.0, 2.0, 0.0));
-        let _id3 = positions.add(Utc::now(), Coordinate::new(3.0, 1.0, 0.0));
-        let id4 = positions.add(Utc::now(), Coordinate::new(4.0, 0.0, 0.0));
+        let id0 = positions
+            .add(Utc::now(), Coordinate::new(0.0, 0.0, 0.0))
+            .unwrap();
+        let _id1 = positions
+            .add(Utc::now(), Coordinate::new(1.0, 1.0, 0.0))
+            .unwrap();
+        let id2 = positions
+            .add(Utc::now(), Coordinate::new(2.0, 2.0, 0.0))
+            .unwrap();
+        let _id3 = positions
+            .add(Utc::now(), Coordinate::new(3.0, 1.0, 0.0))
+            .unwrap();
+        let id4 = positions
+            .add(Utc::now(), Coordinate::new(4.0, 0.0, 0.0))
+            .unwrap();
 
         let search_strategy = Search::new(rdp, None);
 
@@ -139,4 +553,176 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn non_finite_coordinates() {
+        let mut positions = Positions::default();
+        let nan = Coordinate::new(f64::NAN, 0.0, 0.0);
+        let infinite = Coordinate::new(0.0, f64::INFINITY, 0.0);
+
+        assert_eq!(
+            positions.add(Utc::now(), infinite),
+            Err(NonFiniteCoordinate(infinite))
+        );
+        assert!(positions
+            .insert(Datum::new(Uuid::new_v4(), Utc::now(), nan, ()))
+            .is_err());
+        assert!(positions.is_empty());
+    }
+
+    #[test]
+    fn integer_timestamps() {
+        // The same track, timestamped in milliseconds and with chrono.
+        let coordinates = [(0.0, 0.0), (1.0, 3.0), (2.0, 0.5), (3.0, 1.0), (4.0, 0.0)];
+        let mut milliseconds: Positions<(), u64> =
+            Positions::with_id_generator(IdGenerator::sequential(1));
+        let mut date_times = Positions::with_id_generator(IdGenerator::sequential(1));
+        for ((x, y), seconds) in coordinates.into_iter().zip(0..) {
+            let coordinate = Coordinate::new(x, y, 0.0);
+            milliseconds.add(seconds * 1000, coordinate).unwrap();
+            date_times
+                .add(
+                    DateTime::UNIX_EPOCH + chrono::Duration::seconds(seconds.try_into().unwrap()),
+                    coordinate,
+                )
+                .unwrap();
+        }
+
+        let recipient = NodeId::from_u64_pair(0, 1);
+        let milliseconds: Vec<Uuid> = milliseconds
+            .most_novel_coordinates(&Search::new(rdp, None), &recipient, 3)
+            .iter()
+            .map(|datum| datum.id)
+            .collect();
+        let date_times: Vec<Uuid> = date_times
+            .most_novel_coordinates(&Search::new(rdp, None), &recipient, 3)
+            .iter()
+            .map(|datum| datum.id)
+            .collect();
+        assert_eq!(milliseconds, date_times);
+    }
+
+    #[test]
+    fn planar_coordinates() {
+        // The same track, in 2D and in 3D with a dummy z ordinate.
+        let coordinates = [(0.0, 0.0), (1.0, 3.0), (2.0, 0.5), (3.0, 1.0), (4.0, 0.0)];
+        let mut planar: Positions<(), u64, Coordinate2D> =
+            Positions::with_id_generator(IdGenerator::sequential(1));
+        let mut spatial: Positions<(), u64> =
+            Positions::with_id_generator(IdGenerator::sequential(1));
+        for ((x, y), seconds) in coordinates.into_iter().zip(0..) {
+            planar.add(seconds, Coordinate2D::new(x, y)).unwrap();
+            spatial.add(seconds, Coordinate::new(x, y, 0.0)).unwrap();
+        }
+
+        let recipient = NodeId::from_u64_pair(0, 1);
+        let planar: Vec<(Uuid, f64)> = planar
+            .explain_most_novel_coordinates(&Search::new(rdp, None), &recipient, 4, 0)
+            .selected
+            .iter()
+            .map(|candidate| (candidate.datum.id, candidate.novelty.score()))
+            .collect();
+        let spatial: Vec<(Uuid, f64)> = spatial
+            .explain_most_novel_coordinates(&Search::new(rdp, None), &recipient, 4, 0)
+            .selected
+            .iter()
+            .map(|candidate| (candidate.datum.id, candidate.novelty.score()))
+            .collect();
+        assert_eq!(planar, spatial);
+    }
+
+    #[test]
+    fn simplify_leaves_out_coordinates_without_novelty() {
+        let mut positions = Positions::default();
+        let start = DateTime::<Utc>::UNIX_EPOCH;
+        for i in 0..10 {
+            let x = f64::from(i);
+            positions
+                .add(
+                    start + chrono::Duration::seconds(i.into()),
+                    Coordinate::new(x, 2.0 * x, 0.0),
+                )
+                .unwrap();
+        }
+        let data: Vec<_> = positions.iter().map(|datum| datum.id).collect();
+        let recipient = NodeId::from_u64_pair(0, 1);
+
+        // A straight line is described by its ends alone, with or without a
+        // tolerance
+        for search in [
+            Search::new(rdp, None),
+            Search::new(rdp, None).with_tolerance(0.5),
+        ] {
+            let simplified: Vec<_> = positions
+                .simplify(&search, &recipient)
+                .iter()
+                .map(|datum| datum.id)
+                .collect();
+            assert_eq!(simplified, [data[0], data[9]]);
+        }
+
+        // Data points the recipient has are kept, but not returned
+        positions
+            .transmission_history_mut()
+            .record(recipient, data[0], Probability::ONE_HUNDRED);
+        let simplified: Vec<_> = positions
+            .simplify(&Search::new(rdp, None), &recipient)
+            .iter()
+            .map(|datum| datum.id)
+            .collect();
+        assert_eq!(simplified, [data[9]]);
+    }
+
+    #[test]
+    fn ties_favour_the_most_recent() {
+        // Coordinate arrangements, with IDs in the reverse order of the
+        // timestamps:
+        //
+        //   3                                             *
+        //                                                / \
+        //   1     *-----*   ties within a                /   \  ties between
+        //        /       \  segment             0 *--*--*  *  *--*--*  segments
+        //   0   *         *                        0  1  2  3  4  5  6
+        //       0  1     3  4
+        fn select(
+            strategy: impl GeometricNovelty + MaybeSync,
+            track: &[(f64, f64)],
+            n_max: usize,
+        ) -> Vec<usize> {
+            let mut positions = Positions::default();
+            let start = DateTime::<Utc>::UNIX_EPOCH;
+            for (&(x, y), i) in track.iter().zip(0_u8..) {
+                let id = Uuid::from_u128(u128::from(100 - i));
+                let timestamp = start + chrono::Duration::seconds(i.into());
+                positions
+                    .insert(Datum::new(id, timestamp, Coordinate::new(x, y, 0.0), ()))
+                    .unwrap();
+            }
+            let ids: Vec<_> = positions.iter().map(|datum| datum.id).collect();
+
+            let search = Search::new(strategy, None);
+            let mut selected: Vec<_> = positions
+                .most_novel_coordinates(&search, &NodeId::from_u64_pair(0, 1), n_max)
+                .iter()
+                .map(|datum| ids.iter().position(|id| *id == datum.id).unwrap())
+                .collect();
+            selected.sort_unstable();
+            selected
+        }
+
+        let within = [(0.0, 0.0), (1.0, 1.0), (3.0, 1.0), (4.0, 0.0)];
+        let between = [
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (2.0, 0.0),
+            (3.0, 3.0),
+            (4.0, 0.0),
+            (5.0, 0.0),
+            (6.0, 0.0),
+        ];
+        assert_eq!(select(rdp, &within, 3), [0, 2, 3]);
+        assert_eq!(select(HullRdp, &within, 3), [0, 2, 3]);
+        assert_eq!(select(rdp, &between, 4), [0, 3, 4, 6]);
+        assert_eq!(select(HullRdp, &between, 4), [0, 3, 4, 6]);
+    }
 }
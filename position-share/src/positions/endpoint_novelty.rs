@@ -0,0 +1,364 @@
+//! 'Endpoint novelty' is a measure of how important the first and last
+//! coordinates of the time-series are.
+//!
+//! The endpoints can't be scored by their distance from a chord in the same
+//! way as interior points, so a separate heuristic is required. This module
+//! provides a framework for plugging in different heuristics, along with a
+//! number of implementations:
+//!
+//! - [`Separation`] scores both endpoints by the distance between them. This
+//!   is the default.
+//! - [`AlwaysIncludeLatest`] ensures the most recent position is always
+//!   selected.
+//! - [`AlwaysIncludeEndpoints`] ensures both the first and the most recent
+//!   positions are always selected.
+//! - [`DistanceFromLastTransmitted`] scores both endpoints by their distance
+//!   from the most recent position the recipient is known to have.
+//! - [`TimeSinceLastTransmitted`] scores both endpoints by the time elapsed
+//!   since the most recent position the recipient is known to have.
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    coordinate::{Coordinate, Displacement, Point},
+    math,
+    positions::Datum,
+    probability::Probability,
+    timestamp::Timestamp,
+    transmission_history::TransmissionHistory,
+    NodeId,
+};
+
+/// A trait for calculating the novelty of the first and last coordinates in
+/// the time-series.
+pub trait EndpointNovelty<P = (), T = DateTime<Utc>, C = Coordinate> {
+    /// Returns the novelty scores of the (start, end) coordinates.
+    ///
+    /// A search only calls this with a non-empty time-series, but the
+    /// implementations in this module return no novelty for an empty one.
+    fn endpoint_novelty(
+        &self,
+        transmission_history: &TransmissionHistory,
+        positions: &[&Datum<P, T, C>],
+        recipient: &NodeId,
+    ) -> (f64, f64);
+
+    /// Returns whether the (start, end) coordinates must be selected (unless
+    /// the recipient already has them), however novel the rest of the
+    /// time-series is.
+    ///
+    /// Endpoints which must be selected don't count towards the novelty of
+    /// the selection when applying the search threshold.
+    fn always_include(&self) -> (bool, bool) {
+        (false, false)
+    }
+}
+
+/// Scores both endpoints by the distance between them.
+///
+/// This works well for transits, but a loiter track (where the start and end
+/// are close together) will score both endpoints poorly.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Separation;
+
+impl<P, T, C: Point> EndpointNovelty<P, T, C> for Separation {
+    fn endpoint_novelty(
+        &self,
+        _transmission_history: &TransmissionHistory,
+        positions: &[&Datum<P, T, C>],
+        _recipient: &NodeId,
+    ) -> (f64, f64) {
+        let Some((start, end)) = endpoints(positions) else {
+            return (0.0, 0.0);
+        };
+        let distance = (start.coordinate - end.coordinate).magnitude();
+
+        (distance, distance)
+    }
+}
+
+/// Ensures the most recent position is always selected (unless the recipient
+/// already has it).
+///
+/// Both endpoints are scored by their [`Separation`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlwaysIncludeLatest;
+
+impl<P, T, C: Point> EndpointNovelty<P, T, C> for AlwaysIncludeLatest {
+    fn endpoint_novelty(
+        &self,
+        transmission_history: &TransmissionHistory,
+        positions: &[&Datum<P, T, C>],
+        recipient: &NodeId,
+    ) -> (f64, f64) {
+        Separation.endpoint_novelty(transmission_history, positions, recipient)
+    }
+
+    fn always_include(&self) -> (bool, bool) {
+        (false, true)
+    }
+}
+
+/// Ensures both the first and the last positions are always selected (unless
+/// the recipient already has them, or `n_max` is less than 2).
+///
+/// This matches the classic Ramer-Douglas-Peucker algorithm, in which the
+/// endpoints of the line are always retained. Both endpoints are scored by
+/// their [`Separation`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlwaysIncludeEndpoints;
+
+impl<P, T, C: Point> EndpointNovelty<P, T, C> for AlwaysIncludeEndpoints {
+    fn endpoint_novelty(
+        &self,
+        transmission_history: &TransmissionHistory,
+        positions: &[&Datum<P, T, C>],
+        recipient: &NodeId,
+    ) -> (f64, f64) {
+        Separation.endpoint_novelty(transmission_history, positions, recipient)
+    }
+
+    fn always_include(&self) -> (bool, bool) {
+        (true, true)
+    }
+}
+
+/// Scores both endpoints by their distance from the most recent position which
+/// the recipient is known to have.
+///
+/// If the recipient isn't known to have any positions, this is equivalent to
+/// [`Separation`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DistanceFromLastTransmitted;
+
+impl<P, T, C: Point> EndpointNovelty<P, T, C> for DistanceFromLastTransmitted {
+    fn endpoint_novelty(
+        &self,
+        transmission_history: &TransmissionHistory,
+        positions: &[&Datum<P, T, C>],
+        recipient: &NodeId,
+    ) -> (f64, f64) {
+        let Some((start, end)) = endpoints(positions) else {
+            return (0.0, 0.0);
+        };
+        let Some(reference) = last_transmitted(transmission_history, positions, recipient) else {
+            return Separation.endpoint_novelty(transmission_history, positions, recipient);
+        };
+
+        (
+            (start.coordinate - reference.coordinate).magnitude(),
+            (end.coordinate - reference.coordinate).magnitude(),
+        )
+    }
+}
+
+/// Scores both endpoints by the time elapsed since the most recent position
+/// which the recipient is known to have.
+///
+/// The elapsed time is converted into the same units as the geometric novelty
+/// by scoring `distance` for every `per` elapsed, where `per` is a
+/// [`Timestamp::Duration`]. A natural choice is the distance the vehicle
+/// typically travels in that time (such as 2 m per second).
+///
+/// If the recipient isn't known to have any positions, the elapsed time is
+/// measured between the endpoints.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeSinceLastTransmitted<T: Timestamp = DateTime<Utc>> {
+    distance: f64,
+    per: T::Duration,
+}
+
+impl<T: Timestamp> TimeSinceLastTransmitted<T> {
+    /// Creates a new [`TimeSinceLastTransmitted`], which scores `distance`
+    /// for every `per` elapsed.
+    #[must_use]
+    pub const fn new(distance: f64, per: T::Duration) -> Self {
+        Self { distance, per }
+    }
+}
+
+impl<P, T: Timestamp, C> EndpointNovelty<P, T, C> for TimeSinceLastTransmitted<T> {
+    fn endpoint_novelty(
+        &self,
+        transmission_history: &TransmissionHistory,
+        positions: &[&Datum<P, T, C>],
+        recipient: &NodeId,
+    ) -> (f64, f64) {
+        let Some((start, end)) = endpoints(positions) else {
+            return (0.0, 0.0);
+        };
+        let scale = self.distance / T::duration_seconds(self.per);
+        let elapsed = |from: &Datum<P, T, C>, to: &Datum<P, T, C>| {
+            math::abs(to.timestamp.seconds_since(&from.timestamp)) * scale
+        };
+
+        last_transmitted(transmission_history, positions, recipient).map_or_else(
+            || {
+                let novelty = elapsed(start, end);
+                (novelty, novelty)
+            },
+            |reference| (elapsed(reference, start), elapsed(reference, end)),
+        )
+    }
+}
+
+/// The first and last data points of a time-series.
+type Endpoints<'a, P, T, C> = (&'a Datum<P, T, C>, &'a Datum<P, T, C>);
+
+/// Returns the first and last data points, unless there aren't any.
+fn endpoints<'a, P, T, C>(positions: &[&'a Datum<P, T, C>]) -> Option<Endpoints<'a, P, T, C>> {
+    Some((positions.first()?, positions.last()?))
+}
+
+/// Returns the most recent data point which the recipient is known to have.
+fn last_transmitted<'a, P, T, C>(
+    transmission_history: &TransmissionHistory,
+    positions: &[&'a Datum<P, T, C>],
+    recipient: &NodeId,
+) -> Option<&'a Datum<P, T, C>> {
+    positions.iter().rev().copied().find(|datum| {
+        transmission_history.probability_recipient_has_datum(recipient, &datum.id)
+            == Probability::ONE_HUNDRED
+    })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::{rdp, Coordinate, Positions, Search};
+
+    #[test]
+    fn loiter_track_includes_latest() {
+        // A loiter track, which ends where it started
+        //
+        //     y
+        //     ^
+        //   2 |     * (1,2) * (2,2)
+        //     |
+        //     * (0,0.1) latest
+        //   0 * (0,0)             * (3,0)
+        //     +-----|-----|-----|---->
+        let start = Utc::now();
+        let mut positions = Positions::default();
+        positions
+            .add(start, Coordinate::new(0.0, 0.0, 0.0))
+            .unwrap();
+        positions
+            .add(start + Duration::seconds(1), Coordinate::new(1.0, 2.0, 0.0))
+            .unwrap();
+        positions
+            .add(start + Duration::seconds(2), Coordinate::new(2.0, 2.0, 0.0))
+            .unwrap();
+        positions
+            .add(start + Duration::seconds(3), Coordinate::new(3.0, 0.0, 0.0))
+            .unwrap();
+        let latest = positions
+            .add(start + Duration::seconds(4), Coordinate::new(0.0, 0.1, 0.0))
+            .unwrap();
+        let recipient = NodeId::new_v4();
+
+        let default = positions.most_novel_coordinates(&Search::new(rdp, None), &recipient, 2);
+        assert!(default.iter().all(|datum| datum.id != latest));
+
+        let search = Search::new(rdp, None).with_endpoint_novelty(AlwaysIncludeLatest);
+        let most_novel = positions.most_novel_coordinates(&search, &recipient, 2);
+        assert!(most_novel.iter().any(|datum| datum.id == latest));
+    }
+
+    #[test]
+    fn time_since_last_transmitted() {
+        let mut positions: Positions<(), u64> = Positions::default();
+        let ids: Vec<_> = [0, 10_000, 30_000]
+            .into_iter()
+            .map(|timestamp| {
+                positions
+                    .add(timestamp, Coordinate::new(0.0, 0.0, 0.0))
+                    .unwrap()
+            })
+            .collect();
+        let recipient = NodeId::new_v4();
+        let time_since =
+            TimeSinceLastTransmitted::<u64>::new(2.0, core::time::Duration::from_secs(1));
+
+        // 2 m per second between the endpoints
+        let data: Vec<_> = positions.iter().collect();
+        let mut history = TransmissionHistory::default();
+        assert_eq!(
+            time_since.endpoint_novelty(&history, &data, &recipient),
+            (60.0, 60.0)
+        );
+
+        // Then since the middle position, which the recipient has
+        history.record(recipient, ids[1], Probability::ONE_HUNDRED);
+        assert_eq!(
+            time_since.endpoint_novelty(&history, &data, &recipient),
+            (20.0, 40.0)
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let history = TransmissionHistory::default();
+        let recipient = NodeId::new_v4();
+        let empty: &[&Datum] = &[];
+
+        assert_eq!(
+            Separation.endpoint_novelty(&history, empty, &recipient),
+            (0.0, 0.0)
+        );
+        assert_eq!(
+            DistanceFromLastTransmitted.endpoint_novelty(&history, empty, &recipient),
+            (0.0, 0.0)
+        );
+        assert_eq!(
+            TimeSinceLastTransmitted::new(2.0, Duration::seconds(1))
+                .endpoint_novelty(&history, empty, &recipient),
+            (0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn threshold_with_each_policy() {
+        fn select<E: EndpointNovelty>(
+            positions: &Positions,
+            endpoint_novelty: E,
+            recipient: &NodeId,
+        ) -> Vec<uuid::Uuid> {
+            let search = Search::new(rdp, Some(0.4)).with_endpoint_novelty(endpoint_novelty);
+            positions
+                .most_novel_coordinates(&search, recipient, 5)
+                .iter()
+                .map(|datum| datum.id)
+                .collect()
+        }
+
+        // A zigzag, where every peak is well described by the others
+        let start = Utc::now();
+        let mut positions = Positions::default();
+        let ids: Vec<_> = (0..7)
+            .map(|i| {
+                let x = f64::from(i);
+                positions
+                    .add(
+                        start + Duration::seconds(i.into()),
+                        Coordinate::new(x, f64::from(i % 2) * 3.0, 0.0),
+                    )
+                    .unwrap()
+            })
+            .collect();
+        let recipient = NodeId::new_v4();
+
+        // Endpoints which are always included don't hold the search to their
+        // own novelty, so it carries on into the interior as usual
+        let separation = select(&positions, Separation, &recipient);
+        let latest = select(&positions, AlwaysIncludeLatest, &recipient);
+        let both = select(&positions, AlwaysIncludeEndpoints, &recipient);
+        for selected in [&separation, &latest, &both] {
+            assert_eq!(selected.len(), 5);
+        }
+        assert!(latest.contains(&ids[6]));
+        assert!(both.contains(&ids[0]) && both.contains(&ids[6]));
+    }
+}
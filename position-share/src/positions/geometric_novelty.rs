@@ -5,96 +5,372 @@
 //! crate provides a framework for plugging in different algorithms.
 //!
 //! An implementation of the [Ramer-Douglas-Peucker algorithm](https://en.wikipedia.org/wiki/Ramer%E2%80%93Douglas%E2%80%93Peucker_algorithm) is provided.
+//! [`RdpWithPayload`] extends it with a user-defined measure of how much a
+//! datum's payload deviates from the rest of the segment, and [`HullRdp`]
+//! indexes the time-series so that long tracks aren't searched in quadratic
+//! time. This is guaranteed (O(n log² n)) in 2D, but only a heuristic in 3D.
+//!
+//! With the `rayon` feature enabled, the interior of long segments is scored
+//! in parallel.
+
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BinaryHeap},
+};
+use core::any::Any;
 
-use std::collections::BinaryHeap;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
 
-use crate::{positions::Datum, Coordinate};
+use crate::{
+    coordinate::{Displacement, Point},
+    parallel::MaybeSync,
+    positions::Datum,
+    Coordinate,
+};
+
+mod hull;
+pub use hull::HullRdp;
 
 /// A helper struct for sorting segments of the time-series by the most novel
 /// coordinate in the segment.
 ///
 /// This struct is a wrapper placed in a [`BinaryHeap`] in order to create a
-/// max-heap.
-#[derive(Debug, PartialEq)]
-struct Comparator<'a, 'b> {
-    pub segment: &'a [&'b Datum],
-    pub datum: &'b Datum,
-    pub distance: f64,
+/// max-heap. Segments and data points are referred to by their index in the
+/// time-series, so that the heap can be reused between searches.
+#[derive(Debug)]
+struct Comparator<T> {
+    pub start: usize,
+    pub end: usize,
     pub index: usize,
+    pub distance: f64,
+    pub depth: usize,
+    pub timestamp: T,
+    pub id: Uuid,
 }
 
-impl Ord for Comparator<'_, '_> {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+/// Segments are ordered by the novelty of their most novel coordinate. Ties
+/// are broken deterministically, in favour of the most recent coordinate (and
+/// then by ID).
+impl<T: Ord> Ord for Comparator<T> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.distance
-            .partial_cmp(&other.distance)
-            .unwrap_or_else(|| self.datum.id.cmp(&other.datum.id))
+            .total_cmp(&other.distance)
+            .then_with(|| self.timestamp.cmp(&other.timestamp))
+            .then_with(|| self.id.cmp(&other.id))
     }
 }
 
-impl PartialOrd for Comparator<'_, '_> {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+impl<T: Ord> PartialOrd for Comparator<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Eq for Comparator<'_, '_> {}
+impl<T: Ord> PartialEq for Comparator<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<T: Ord> Eq for Comparator<T> {}
 
 /// A max-heap used to store segments of the time-series sorted by the most
 /// geometrically novel coordinate in the segment.
-#[derive(Debug, Default)]
-pub struct MaxHeap<'a, 'b>(BinaryHeap<Comparator<'a, 'b>>);
+///
+/// Each segment is identified by the (inclusive) indices of its first and last
+/// data points, and its most novel coordinate by its index.
+#[derive(Debug)]
+pub struct MaxHeap<T>(BinaryHeap<Comparator<T>>);
 
-impl<'a, 'b> MaxHeap<'a, 'b> {
-    pub fn push(
+impl<T: Ord> Default for MaxHeap<T> {
+    fn default() -> Self {
+        Self(BinaryHeap::default())
+    }
+}
+
+impl<T: Ord + Copy> MaxHeap<T> {
+    pub fn push<P, C>(
         &mut self,
-        segment: &'a [&'b Datum],
-        datum: &'b Datum,
+        (start, end): (usize, usize),
+        datum: &Datum<P, T, C>,
         distance: f64,
         index: usize,
+        depth: usize,
     ) {
         self.0.push(Comparator {
-            segment,
-            datum,
-            distance,
+            start,
+            end,
             index,
+            distance,
+            depth,
+            timestamp: datum.timestamp,
+            id: datum.id,
         });
     }
 
-    pub fn pop(&mut self) -> Option<(&'a [&'b Datum], &'b Datum, f64, usize)> {
+    pub fn pop(&mut self) -> Option<((usize, usize), f64, usize, usize)> {
         self.0.pop().map(
             |Comparator {
-                 segment,
-                 datum,
+                 start,
+                 end,
                  distance,
                  index,
-             }| (segment, datum, distance, index),
+                 depth,
+                 ..
+             }| ((start, end), distance, index, depth),
         )
     }
+
+    /// Removes all segments, keeping the allocated memory for reuse.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// A cache of the most novel coordinate in each segment of the time-series
+/// which has been searched.
+///
+/// Between two searches typically only a few data points have been appended,
+/// so most segments are unchanged. The geometric novelty of a segment only
+/// depends on the data within it, so a segment is identified by the IDs of its
+/// first and last data points and its length. Appending data (or inserting it
+/// mid-track) changes the segments spanning it, which are then searched again,
+/// while the rest are looked up.
+///
+/// Segments which weren't used by the previous search are forgotten, so the
+/// cache doesn't grow without bound.
+#[derive(Debug, Default)]
+pub struct SegmentCache {
+    segments: BTreeMap<(Uuid, Uuid, usize), CachedSegment>,
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct CachedSegment {
+    /// The (distance, index) of the most novel coordinate.
+    most_novel: Option<(f64, usize)>,
+    /// The search in which the segment was last used.
+    generation: u64,
+}
+
+impl SegmentCache {
+    /// Returns the cached (distance, index) of the most novel coordinate in
+    /// the segment, or `None` if it hasn't been searched.
+    #[allow(clippy::option_option)] // segments without an interior are cached too
+    pub fn get<P, T, C>(&mut self, segment: &[&Datum<P, T, C>]) -> Option<Option<(f64, usize)>> {
+        let generation = self.generation;
+        let cached = self.segments.get_mut(&Self::key(segment)?)?;
+        cached.generation = generation;
+        Some(cached.most_novel)
+    }
+
+    /// Records the (distance, index) of the most novel coordinate in the
+    /// segment.
+    pub fn insert<P, T, C>(
+        &mut self,
+        segment: &[&Datum<P, T, C>],
+        most_novel: Option<(f64, usize)>,
+    ) {
+        if let Some(key) = Self::key(segment) {
+            let generation = self.generation;
+            self.segments.insert(
+                key,
+                CachedSegment {
+                    most_novel,
+                    generation,
+                },
+            );
+        }
+    }
+
+    /// Starts a new search, forgetting the segments which weren't used by the
+    /// previous one.
+    pub fn start(&mut self) {
+        let generation = self.generation;
+        self.segments
+            .retain(|_, cached| cached.generation == generation);
+        self.generation = generation.wrapping_add(1);
+    }
+
+    /// Forgets every segment.
+    pub fn clear(&mut self) {
+        self.segments.clear();
+    }
+
+    fn key<P, T, C>(segment: &[&Datum<P, T, C>]) -> Option<(Uuid, Uuid, usize)> {
+        Some((segment.first()?.id, segment.last()?.id, segment.len()))
+    }
 }
 
 /// A trait for calculating the most novel coordinate in a segment of the
 /// time-series.
-pub trait GeometricNovelty {
+///
+/// The trait is generic over the payload type `P` of the data points, so that
+/// implementations may take the payload into account when scoring novelty, as
+/// well as over their timestamp and coordinate types.
+pub trait GeometricNovelty<P = (), T = DateTime<Utc>, C = Coordinate> {
     /// Calculates the most novel coordinate in a segment of the time-series.
     ///
     /// The first and last should be excluded. Only the interior points should
     /// be considered as candidates for the most novel coordinate.
-    fn most_novel_coordinate<'a>(&self, segment: &[&'a Datum]) -> Option<(&'a Datum, f64, usize)>;
+    fn most_novel_coordinate<'a>(
+        &self,
+        segment: &[&'a Datum<P, T, C>],
+    ) -> Option<(&'a Datum<P, T, C>, f64, usize)>;
+
+    /// Indexes the whole time-series before a search.
+    ///
+    /// A search calls this once, then looks up each segment in the index
+    /// rather than calling [`GeometricNovelty::most_novel_coordinate`], which
+    /// lets implementations such as [`HullRdp`] avoid scanning every segment.
+    /// A [`SearchContext`](crate::SearchContext) keeps the index between
+    /// searches until the time-series changes. By default there's no index.
+    fn index(&self, _history: &[&Datum<P, T, C>]) -> Option<Box<dyn SegmentIndex>> {
+        None
+    }
+
+    /// Updates an index created by [`GeometricNovelty::index`] after data
+    /// points have been appended to the time-series it indexed, which is now
+    /// `history`.
+    ///
+    /// Returns `false` if the index can't be extended, in which case it's
+    /// rebuilt instead. By default indices are always rebuilt.
+    fn extend_index(&self, _index: &mut dyn SegmentIndex, _history: &[&Datum<P, T, C>]) -> bool {
+        false
+    }
+
+    /// Identifies the strategy's parameters, so that a
+    /// [`SearchContext`](crate::SearchContext) only reuses what it cached for
+    /// the same strategy.
+    ///
+    /// Two strategies of the same type with the same key must find the same
+    /// most novel coordinate in every segment. Returns `None` if the strategy
+    /// can't be identified, in which case nothing is kept between searches.
+    ///
+    /// By default, a strategy without any state (such as [`rdp`] or
+    /// [`HullRdp`]) is identified by its type alone, and any other strategy
+    /// can't be identified.
+    fn cache_key(&self) -> Option<u64> {
+        (core::mem::size_of_val(self) == 0).then_some(0)
+    }
 }
 
-impl<F> GeometricNovelty for F
+/// An index of the time-series, created by [`GeometricNovelty::index`], which
+/// finds the most novel coordinate in any of its segments.
+pub trait SegmentIndex: Send + MaybeSync {
+    /// Returns the novelty and index (in the time-series) of the most novel
+    /// coordinate strictly between `start` and `end`, or `None` if there
+    /// aren't any.
+    fn most_novel_coordinate(&self, start: usize, end: usize) -> Option<(f64, usize)>;
+
+    /// Returns the index as [`Any`], so that
+    /// [`GeometricNovelty::extend_index`] can recover its type.
+    ///
+    /// By default this returns `None`.
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
+}
+
+impl<F, P, T, C> GeometricNovelty<P, T, C> for F
 where
-    F: for<'a> Fn(&[&'a Datum]) -> Option<(&'a Datum, f64, usize)>,
+    F: for<'a> Fn(&[&'a Datum<P, T, C>]) -> Option<(&'a Datum<P, T, C>, f64, usize)>,
 {
-    fn most_novel_coordinate<'a>(&self, segment: &[&'a Datum]) -> Option<(&'a Datum, f64, usize)> {
+    fn most_novel_coordinate<'a>(
+        &self,
+        segment: &[&'a Datum<P, T, C>],
+    ) -> Option<(&'a Datum<P, T, C>, f64, usize)> {
         self(segment)
     }
 }
 
-/// A 3D version of the [Ramer-Douglas-Peucker algorithm](https://en.wikipedia.org/wiki/Ramer%E2%80%93Douglas%E2%80%93Peucker_algorithm) for calculating geometric novelty.
+/// A 2D or 3D version of the [Ramer-Douglas-Peucker algorithm](https://en.wikipedia.org/wiki/Ramer%E2%80%93Douglas%E2%80%93Peucker_algorithm) for calculating geometric novelty.
 #[must_use]
+pub fn rdp<'a, P: MaybeSync, T: MaybeSync, C: Point>(
+    segment: &[&'a Datum<P, T, C>],
+) -> Option<(&'a Datum<P, T, C>, f64, usize)> {
+    most_novel_by(segment, |start, end, datum| {
+        distance_from_line(&start.coordinate, &end.coordinate, &datum.coordinate)
+    })
+}
+
+/// An extension of [`rdp`] which also accounts for changes in the payload.
+///
+/// The novelty of an interior datum is its perpendicular distance from the
+/// line joining the segment endpoints, plus a user-supplied 'payload delta'.
+/// The payload delta is a function of the payloads of the segment start, the
+/// candidate datum, and the segment end, allowing sudden changes in (for
+/// example) heading or depth-rate to be prioritised even when the geometry is
+/// unremarkable.
+///
+/// The payload delta should be expressed in the same units as the
+/// geometric distance, and should be non-negative.
+///
+/// # Example
+#[cfg_attr(feature = "std", doc = "```")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// use chrono::Utc;
+/// use position_share::{Coordinate, NodeId, Positions, RdpWithPayload, Search};
+///
+/// // The payload is the vessel heading, in degrees
+/// let mut positions = Positions::default();
+/// positions.add_with_payload(Utc::now(), Coordinate::new(0.0, 0.0, 0.0), 90.0).unwrap();
+/// positions.add_with_payload(Utc::now(), Coordinate::new(1.0, 0.0, 0.0), 180.0).unwrap();
+/// positions.add_with_payload(Utc::now(), Coordinate::new(2.0, 0.0, 0.0), 90.0).unwrap();
+///
+/// // Every degree of deviation from the start heading is worth 10cm of novelty
+/// let novelty = RdpWithPayload::new(|start: &f64, heading: &f64, _end: &f64| {
+///     0.1 * (heading - start).abs()
+/// });
+///
+/// let most_novel = positions.most_novel_coordinates(
+///     &Search::new(novelty, None),
+///     &NodeId::new_v4(),
+///     3,
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RdpWithPayload<F> {
+    payload_delta: F,
+}
+
+impl<F> RdpWithPayload<F> {
+    /// Creates a new [`RdpWithPayload`] from a payload delta function.
+    ///
+    /// The function is called with the payloads of the segment start, the
+    /// candidate datum, and the segment end, and returns the additional
+    /// novelty of the candidate.
+    pub const fn new(payload_delta: F) -> Self {
+        Self { payload_delta }
+    }
+}
+
+impl<P, T, C, F> GeometricNovelty<P, T, C> for RdpWithPayload<F>
+where
+    P: MaybeSync,
+    T: MaybeSync,
+    C: Point,
+    F: Fn(&P, &P, &P) -> f64 + MaybeSync,
+{
+    fn most_novel_coordinate<'a>(
+        &self,
+        segment: &[&'a Datum<P, T, C>],
+    ) -> Option<(&'a Datum<P, T, C>, f64, usize)> {
+        most_novel_by(segment, |start, end, datum| {
+            distance_from_line(&start.coordinate, &end.coordinate, &datum.coordinate)
+                + (self.payload_delta)(&start.payload, &datum.payload, &end.payload)
+        })
+    }
+}
+
+/// Finds the interior datum of a segment with the highest novelty, as
+/// calculated by `novelty(start, end, datum)`.
 #[allow(clippy::missing_panics_doc)]
-pub fn rdp<'a>(segment: &[&'a Datum]) -> Option<(&'a Datum, f64, usize)> {
+fn most_novel_by<'a, P: MaybeSync, T: MaybeSync, C: Point>(
+    segment: &[&'a Datum<P, T, C>],
+    novelty: impl Fn(&Datum<P, T, C>, &Datum<P, T, C>, &Datum<P, T, C>) -> f64 + MaybeSync,
+) -> Option<(&'a Datum<P, T, C>, f64, usize)> {
     // Algorithm:
     // 1. if there are less than 3 data points, return None
     // 2. find the most novel datum in the segment, excluding the first and last
@@ -111,41 +387,53 @@ pub fn rdp<'a>(segment: &[&'a Datum]) -> Option<(&'a Datum, f64, usize)> {
     #[allow(clippy::unwrap_used)]
     let end = segment.last().unwrap();
 
-    segment[1..segment.len() - 1]
-        .iter()
-        .zip(1..)
-        .map(|(datum, i)| {
-            let distance =
-                distance_from_line(&start.coordinate, &end.coordinate, &datum.coordinate);
-            (*datum, distance, i)
-        })
-        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+    let interior = &segment[1..segment.len() - 1];
+    let score =
+        |(i, datum): (usize, &&'a Datum<P, T, C>)| (*datum, novelty(start, end, datum), i + 1);
+    // Ties are broken in favour of the most recent datum, as in `Novelty`. As
+    // indices are unique, this is a total order, so the result doesn't depend
+    // on evaluation order.
+    let compare = |a: &(&Datum<P, T, C>, f64, usize), b: &(&Datum<P, T, C>, f64, usize)| {
+        a.1.total_cmp(&b.1).then_with(|| a.2.cmp(&b.2))
+    };
+
+    #[cfg(feature = "rayon")]
+    if segment.len() >= crate::parallel::MIN_PARALLEL_LEN {
+        use rayon::prelude::*;
+        return interior.par_iter().enumerate().map(score).max_by(compare);
+    }
+
+    interior.iter().enumerate().map(score).max_by(compare)
 }
 
 /// Calculates the perpendicular distance from a coordinate to a line defined by
 /// two coordinates.
-fn distance_from_line(start: &Coordinate, end: &Coordinate, coordinate: &Coordinate) -> f64 {
+///
+/// If the two coordinates coincide (as at the ends of a loop, or while
+/// loitering) there is no line, so the distance from the coordinate to them is
+/// returned instead.
+pub fn distance_from_line<C: Point>(start: &C, end: &C, coordinate: &C) -> f64 {
     // Vector from start to end
-    let line_vector = end - start;
+    let line_vector = *end - *start;
 
     // Vector from start to the coordinate
-    let point_vector = coordinate - start;
-
-    // Calculate the cross product
-    let cross_product = &line_vector.cross_product(&point_vector);
+    let point_vector = *coordinate - *start;
 
     // Calculate the magnitude of the cross product
-    let cross_product_magnitude = cross_product.magnitude();
+    let cross_product_magnitude = line_vector.cross_product_magnitude(&point_vector);
 
     // Calculate the magnitude of the line vector
     let line_magnitude = line_vector.magnitude();
+    if line_magnitude == 0.0 {
+        return point_vector.magnitude();
+    }
 
     // The perpendicular distance is the magnitude of the cross product divided by
     // the magnitude of the line vector
     cross_product_magnitude / line_magnitude
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use float_cmp::assert_approx_eq;
 
@@ -166,4 +454,108 @@ mod tests {
         let coordinate = Coordinate::new(2.0, 2.0, 0.0);
         assert_approx_eq!(f64, distance_from_line(&start, &end, &coordinate), 2.0);
     }
+
+    #[test]
+    fn test_distance_from_zero_length_line() {
+        let start = Coordinate::new(1.0, 1.0, 0.0);
+        let coordinate = Coordinate::new(4.0, 5.0, 0.0);
+        assert_approx_eq!(f64, distance_from_line(&start, &start, &coordinate), 5.0);
+        assert_approx_eq!(f64, distance_from_line(&start, &start, &start), 0.0);
+    }
+
+    #[test]
+    fn test_distance_from_line_2d() {
+        let start = crate::Coordinate2D::new(0.0, 0.0);
+        let end = crate::Coordinate2D::new(4.0, 0.0);
+        let coordinate = crate::Coordinate2D::new(2.0, -2.0);
+        assert_approx_eq!(f64, distance_from_line(&start, &end, &coordinate), 2.0);
+        assert_approx_eq!(
+            f64,
+            distance_from_line(&start, &start, &coordinate),
+            8.0_f64.sqrt()
+        );
+    }
+
+    fn track(coordinates: &[(f64, f64)]) -> Vec<Datum> {
+        coordinates
+            .iter()
+            .zip(0..)
+            .map(|(&(x, y), seconds)| Datum {
+                id: uuid::Uuid::new_v4(),
+                timestamp: chrono::DateTime::UNIX_EPOCH + chrono::Duration::seconds(seconds),
+                coordinate: Coordinate::new(x, y, 0.0),
+                payload: (),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_coincident_endpoints() {
+        // A closed loop, which returns to where it started
+        let data = track(&[(0.0, 0.0), (3.0, 0.0), (3.0, 4.0), (0.0, 4.0), (0.0, 0.0)]);
+        let segment: Vec<_> = data.iter().collect();
+
+        let (datum, score, index) = rdp(&segment).unwrap();
+
+        // The furthest point from the start of the loop
+        assert_eq!(index, 2);
+        assert_eq!(datum.id, data[2].id);
+        assert_approx_eq!(f64, score, 5.0);
+    }
+
+    #[test]
+    fn test_loiter() {
+        // A track which never moves
+        let data = track(&[(2.0, 2.0); 5]);
+        let segment: Vec<_> = data.iter().collect();
+
+        let (datum, score, index) = rdp(&segment).unwrap();
+
+        assert!(score.is_finite());
+        assert_approx_eq!(f64, score, 0.0);
+        assert_eq!(index, 3);
+        assert_eq!(datum.id, data[3].id);
+    }
+
+    #[test]
+    fn test_long_segment() {
+        // Long enough to be scored in parallel with the `rayon` feature, with
+        // many equally novel points.
+        let coordinates: Vec<_> = (0..3 * crate::parallel::MIN_PARALLEL_LEN)
+            .map(|i| (f64::from(u32::try_from(i).unwrap()), f64::from(i % 7 == 3)))
+            .collect();
+        let data = track(&coordinates);
+        let segment: Vec<_> = data.iter().collect();
+
+        let (datum, score, index) = rdp(&segment).unwrap();
+
+        let most_recent = (0..coordinates.len() - 1).rev().find(|i| i % 7 == 3);
+        assert_eq!(Some(index), most_recent);
+        assert_eq!(datum.id, data[index].id);
+        assert_approx_eq!(f64, score, 1.0);
+    }
+
+    #[test]
+    fn test_rdp_with_payload() {
+        // A straight line, where the payload spikes at an otherwise
+        // unremarkable point
+        let data: Vec<_> = [(0.0, 0.0), (1.0, 0.1), (2.0, 5.0), (3.0, 0.0)]
+            .into_iter()
+            .zip(0..)
+            .map(|((x, payload), seconds)| Datum {
+                id: uuid::Uuid::new_v4(),
+                timestamp: chrono::DateTime::UNIX_EPOCH + chrono::Duration::seconds(seconds),
+                coordinate: Coordinate::new(x, 0.0, 0.0),
+                payload,
+            })
+            .collect();
+        let segment: Vec<_> = data.iter().collect();
+
+        let novelty = RdpWithPayload::new(|_: &f64, payload: &f64, _: &f64| *payload);
+        let (datum, score, index) = novelty.most_novel_coordinate(&segment).unwrap();
+
+        assert_eq!(index, 2);
+        assert_eq!(datum.id, data[2].id);
+        assert_approx_eq!(f64, score, 5.0);
+    }
 }
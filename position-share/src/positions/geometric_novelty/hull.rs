@@ -0,0 +1,718 @@
+//! A version of [`rdp`] which indexes the time-series, so that searching many
+//! segments of a long track doesn't take quadratic time.
+//!
+//! [`rdp`] scans every interior point of each segment it searches, so a search
+//! which splits segments unevenly (as on a spiral, where the most novel point
+//! is always near one end) takes O(n²) time. [`HullRdp`] instead indexes the
+//! time-series with a tree, in which each node summarises a contiguous
+//! sub-path, and searches each segment using the few nodes which cover its
+//! interior. The tree is extended as positions are appended, so a
+//! [`SearchContext`](crate::SearchContext) doesn't need to rebuild it between
+//! searches.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{any::Any, ops::Range};
+
+use super::{distance_from_line, rdp, GeometricNovelty, SegmentIndex};
+use crate::{
+    coordinate::{Displacement, Point},
+    parallel::MaybeSync,
+    positions::Datum,
+    Coordinate, Coordinate2D, Vector2D,
+};
+
+/// Sub-paths at most this long are leaves of the tree.
+const LEAF_LEN: usize = 16;
+
+/// A version of [`rdp`] with a better worst case on long tracks.
+///
+/// The geometric novelty is the same as [`rdp`]'s (up to rounding, and which
+/// of several equally novel points is chosen), but each search first indexes
+/// the whole time-series:
+///
+/// - For [`Coordinate2D`]s, each node of the index stores the convex hull of
+///   its sub-path, a static form of Hershberger and Snoeyink's path hulls. The
+///   point furthest from a line is a vertex of the hull, found by binary
+///   search, so each segment is searched in O(log² n) time after O(n log n)
+///   preprocessing.
+/// - For [`Coordinate`]s, the point furthest from a line isn't the extreme
+///   point of the hull in any fixed direction, so there's no such guarantee.
+///   Instead each node stores a bounding sphere, and nodes which can't contain
+///   a point further from the line than the best found so far are skipped.
+///   This is only a heuristic: the result is exact, and in practice it avoids
+///   the quadratic time taken on spirals and other tracks which split
+///   unevenly, but a segment with many points almost equally far from its
+///   chord (such as a track running parallel to it) still visits most of the
+///   tree. The worst case is O(n) per segment, as for [`rdp`].
+///
+/// Building the index costs more than a single scan, so for short tracks, or
+/// searches which stop after a few segments, [`rdp`] is faster. A
+/// [`SearchContext`](crate::SearchContext) keeps the index between searches,
+/// and extends it as positions are appended.
+///
+/// # Example
+#[cfg_attr(feature = "std", doc = "```")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// use chrono::{DateTime, Duration};
+/// use position_share::{Coordinate2D, HullRdp, IdGenerator, NodeId, Positions, Search};
+///
+/// // An expanding search spiral
+/// let mut positions: Positions<(), _, Coordinate2D> =
+///     Positions::with_id_generator(IdGenerator::sequential(0));
+/// for i in 0..1000 {
+///     let angle = f64::from(i) / 10.0;
+///     positions
+///         .add(
+///             DateTime::UNIX_EPOCH + Duration::seconds(i.into()),
+///             Coordinate2D::new(angle * angle.cos(), angle * angle.sin()),
+///         )
+///         .unwrap();
+/// }
+///
+/// let most_novel = positions.most_novel_coordinates(
+///     &Search::new(HullRdp, None),
+///     &NodeId::new_v4(),
+///     1000,
+/// );
+/// assert_eq!(most_novel.len(), 1000);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HullRdp;
+
+impl<P: MaybeSync, T: MaybeSync> GeometricNovelty<P, T, Coordinate2D> for HullRdp {
+    fn most_novel_coordinate<'a>(
+        &self,
+        segment: &[&'a Datum<P, T, Coordinate2D>],
+    ) -> Option<(&'a Datum<P, T, Coordinate2D>, f64, usize)> {
+        rdp(segment)
+    }
+
+    fn index(&self, history: &[&Datum<P, T, Coordinate2D>]) -> Option<Box<dyn SegmentIndex>> {
+        Some(Box::new(PathHulls::new(
+            history.iter().map(|datum| datum.coordinate).collect(),
+        )))
+    }
+
+    fn extend_index(
+        &self,
+        index: &mut dyn SegmentIndex,
+        history: &[&Datum<P, T, Coordinate2D>],
+    ) -> bool {
+        let Some(hulls) = index
+            .as_any_mut()
+            .and_then(|index| index.downcast_mut::<PathHulls>())
+        else {
+            return false;
+        };
+        let Some(appended) = history.get(hulls.points.len()..) else {
+            return false;
+        };
+        hulls.extend(appended.iter().map(|datum| datum.coordinate));
+        true
+    }
+}
+
+impl<P: MaybeSync, T: MaybeSync> GeometricNovelty<P, T, Coordinate> for HullRdp {
+    fn most_novel_coordinate<'a>(
+        &self,
+        segment: &[&'a Datum<P, T, Coordinate>],
+    ) -> Option<(&'a Datum<P, T, Coordinate>, f64, usize)> {
+        rdp(segment)
+    }
+
+    fn index(&self, history: &[&Datum<P, T, Coordinate>]) -> Option<Box<dyn SegmentIndex>> {
+        Some(Box::new(SpherePruning::new(
+            history.iter().map(|datum| datum.coordinate).collect(),
+        )))
+    }
+
+    fn extend_index(
+        &self,
+        index: &mut dyn SegmentIndex,
+        history: &[&Datum<P, T, Coordinate>],
+    ) -> bool {
+        let Some(spheres) = index
+            .as_any_mut()
+            .and_then(|index| index.downcast_mut::<SpherePruning<Coordinate>>())
+        else {
+            return false;
+        };
+        let Some(appended) = history.get(spheres.points.len()..) else {
+            return false;
+        };
+        spheres.extend(appended.iter().map(|datum| datum.coordinate));
+        true
+    }
+}
+
+/// A node of the tree, summarising a sub-path of the time-series.
+struct Node<S> {
+    range: Range<usize>,
+    /// The indices of the node's children, unless it's a leaf.
+    children: Option<[usize; 2]>,
+    summary: S,
+}
+
+/// A forest of perfect binary trees over a time-series, which grows as points
+/// are appended.
+///
+/// The leaves are sub-paths of [`LEAF_LEN`] points, and, like the digits of a
+/// binary counter, whenever two trees are the same size they are merged under
+/// a new root. So there are at most O(log n) trees, and any range is covered by
+/// O(log n) nodes, as with a single balanced tree.
+struct Tree<S> {
+    nodes: Vec<Node<S>>,
+    /// The root of each tree, in time order. Only the last tree may be a leaf
+    /// shorter than [`LEAF_LEN`].
+    roots: Vec<usize>,
+}
+
+impl<S> Tree<S> {
+    const fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Returns the number of points in the time-series.
+    fn len(&self) -> usize {
+        self.roots
+            .last()
+            .map_or(0, |&root| self.nodes[root].range.end)
+    }
+
+    /// Removes the last leaf if it's shorter than [`LEAF_LEN`], so that it can
+    /// be rebuilt along with the points appended after it, and returns its
+    /// summary.
+    fn pop_partial(&mut self) -> Option<S> {
+        let &root = self.roots.last()?;
+        let node = &self.nodes[root];
+        if node.children.is_some() || node.range.len() == LEAF_LEN {
+            return None;
+        }
+        // The partial leaf is always the last node built
+        self.roots.pop();
+        self.nodes.pop().map(|node| node.summary)
+    }
+
+    /// Extends the forest over a time-series which has grown to `len` points,
+    /// calling `summarise` with the (non-empty) range of each new node and the
+    /// summaries of its children (unless it's a leaf).
+    fn extend(
+        &mut self,
+        len: usize,
+        summarise: &mut impl FnMut(Range<usize>, Option<[&S; 2]>) -> S,
+    ) {
+        self.pop_partial();
+        let mut start = self.len();
+        while start < len {
+            let end = len.min(start + LEAF_LEN);
+            let summary = summarise(start..end, None);
+            self.push(start..end, None, summary);
+
+            while let [.., left, right] = self.roots[..] {
+                let [left_node, right_node] = [&self.nodes[left], &self.nodes[right]];
+                if left_node.range.len() != right_node.range.len() {
+                    break;
+                }
+                let range = left_node.range.start..right_node.range.end;
+                let summary = summarise(
+                    range.clone(),
+                    Some([&left_node.summary, &right_node.summary]),
+                );
+                self.roots.truncate(self.roots.len() - 2);
+                self.push(range, Some([left, right]), summary);
+            }
+            start = end;
+        }
+    }
+
+    /// Adds a node as the root of a new tree.
+    fn push(&mut self, range: Range<usize>, children: Option<[usize; 2]>, summary: S) {
+        self.nodes.push(Node {
+            range,
+            children,
+            summary,
+        });
+        self.roots.push(self.nodes.len() - 1);
+    }
+
+    /// Calls `visit` with each maximal node within `range`, and each leaf which
+    /// partially overlaps it, along with the overlap.
+    fn cover(&self, range: &Range<usize>, visit: &mut impl FnMut(&Node<S>, Range<usize>)) {
+        for &root in &self.roots {
+            self.cover_from(&self.nodes[root], range, visit);
+        }
+    }
+
+    fn cover_from(
+        &self,
+        node: &Node<S>,
+        range: &Range<usize>,
+        visit: &mut impl FnMut(&Node<S>, Range<usize>),
+    ) {
+        let overlap = range.start.max(node.range.start)..range.end.min(node.range.end);
+        if overlap.is_empty() {
+            return;
+        }
+        match node.children {
+            Some(children) if overlap != node.range => {
+                for child in children {
+                    self.cover_from(&self.nodes[child], range, visit);
+                }
+            }
+            _ => visit(node, overlap),
+        }
+    }
+}
+
+/// Records a point as the most novel if it's more novel than the best found so
+/// far. As with [`rdp`], ties are broken in favour of the most recent.
+fn offer(most_novel: &mut Option<(f64, usize)>, distance: f64, index: usize) {
+    let better = most_novel.map_or(true, |(best, best_index)| {
+        distance
+            .total_cmp(&best)
+            .then_with(|| index.cmp(&best_index))
+            .is_gt()
+    });
+    if better {
+        *most_novel = Some((distance, index));
+    }
+}
+
+/// The convex hull of the sub-path of each node of a [`Tree`].
+///
+/// Each hull is stored as its upper and lower chains, ordered by x. The hull
+/// of a node is the hull of its children's hulls, so the tree is built in
+/// O(n log n) time, or amortised O(log n) time per point appended.
+struct PathHulls {
+    points: Vec<Coordinate2D>,
+    /// The vertices of every chain, as indices into `points`.
+    vertices: Vec<usize>,
+    tree: Tree<Hull>,
+}
+
+/// The upper and lower chains of a hull, as ranges of [`PathHulls::vertices`].
+type Hull = [Range<usize>; 2];
+
+impl PathHulls {
+    fn new(points: Vec<Coordinate2D>) -> Self {
+        let mut hulls = Self {
+            points: Vec::with_capacity(points.len()),
+            vertices: Vec::new(),
+            tree: Tree::new(),
+        };
+        hulls.extend(points);
+        hulls
+    }
+
+    /// Appends points to the path, and extends the tree over them.
+    fn extend(&mut self, points: impl IntoIterator<Item = Coordinate2D>) {
+        // The vertices of the last leaf's chains are last, so they can be
+        // discarded when it's rebuilt
+        if let Some([upper, _]) = self.tree.pop_partial() {
+            self.vertices.truncate(upper.start);
+        }
+        self.points.extend(points);
+
+        let Self {
+            points,
+            vertices,
+            tree,
+        } = self;
+        let by_x = |a: &usize, b: &usize| {
+            let (a, b) = (points[*a], points[*b]);
+            a.x.total_cmp(&b.x).then_with(|| a.y.total_cmp(&b.y))
+        };
+        let mut candidates = Vec::new();
+        tree.extend(points.len(), &mut |range, children: Option<[&Hull; 2]>| {
+            [Chain::Upper, Chain::Lower].map(|chain| {
+                candidates.clear();
+                if let Some([left, right]) = children {
+                    merge(
+                        &vertices[left[chain as usize].clone()],
+                        &vertices[right[chain as usize].clone()],
+                        by_x,
+                        &mut candidates,
+                    );
+                } else {
+                    candidates.extend(range.clone());
+                    candidates.sort_unstable_by(by_x);
+                }
+                let start = vertices.len();
+                for &candidate in &candidates {
+                    while vertices.len() >= start + 2 {
+                        let [a, b] = [vertices[vertices.len() - 2], vertices[vertices.len() - 1]];
+                        if chain.is_convex(points[a], points[b], points[candidate]) {
+                            break;
+                        }
+                        vertices.pop();
+                    }
+                    vertices.push(candidate);
+                }
+                start..vertices.len()
+            })
+        });
+    }
+
+    /// Returns the vertex of a chain which is furthest in the direction of
+    /// `normal`.
+    fn extreme(&self, chain: &[usize], normal: Vector2D) -> usize {
+        // The chain turns steadily in one direction, so the projection of its
+        // vertices onto the normal either rises and then falls, or falls and
+        // then rises. The maximum is at the turning point or at an end.
+        let projection = |i: usize| normal.dot_product(&(self.points[chain[i]] - self.points[0]));
+        let (mut low, mut high) = (0, chain.len() - 1);
+        while low < high {
+            let middle = (low + high) / 2;
+            if projection(middle + 1) > projection(middle) {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        [0, low, chain.len() - 1]
+            .into_iter()
+            .max_by(|&a, &b| projection(a).total_cmp(&projection(b)))
+            .map_or(chain[0], |i| chain[i])
+    }
+}
+
+impl SegmentIndex for PathHulls {
+    fn most_novel_coordinate(&self, start: usize, end: usize) -> Option<(f64, usize)> {
+        if end >= self.points.len() || end < start + 2 {
+            return None;
+        }
+        let (line_start, line_end) = (self.points[start], self.points[end]);
+        let line = line_end - line_start;
+        let normal = Vector2D::new(-line.y, line.x);
+
+        let mut most_novel = None;
+        let mut score = |index: usize| {
+            let distance = distance_from_line(&line_start, &line_end, &self.points[index]);
+            offer(&mut most_novel, distance, index);
+        };
+        self.tree.cover(&(start + 1..end), &mut |node, overlap| {
+            let [upper, lower] = node.summary.clone().map(|chain| &self.vertices[chain]);
+            if overlap != node.range {
+                overlap.for_each(&mut score);
+            } else if line.magnitude() == 0.0 {
+                // The furthest point from the start is also a vertex of the
+                // hull, but it isn't extreme in any one direction.
+                upper.iter().chain(lower).copied().for_each(&mut score);
+            } else {
+                for chain in [upper, lower] {
+                    score(self.extreme(chain, normal));
+                    score(self.extreme(chain, normal * -1.0));
+                }
+            }
+        });
+        most_novel
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+/// One of the chains of a convex hull.
+#[derive(Clone, Copy)]
+enum Chain {
+    Upper = 0,
+    Lower = 1,
+}
+
+impl Chain {
+    /// Returns `true` if the vertices `a`, `b`, `c` (in order of x) turn the
+    /// right way to all be on the chain.
+    #[allow(clippy::suboptimal_flops)]
+    fn is_convex(self, a: Coordinate2D, b: Coordinate2D, c: Coordinate2D) -> bool {
+        let (ab, ac) = (b - a, c - a);
+        let cross = ab.x * ac.y - ab.y * ac.x;
+        match self {
+            Self::Upper => cross < 0.0,
+            Self::Lower => cross > 0.0,
+        }
+    }
+}
+
+/// Merges two sorted lists into `merged`.
+fn merge(
+    left: &[usize],
+    right: &[usize],
+    compare: impl Fn(&usize, &usize) -> core::cmp::Ordering,
+    merged: &mut Vec<usize>,
+) {
+    let (mut left, mut right) = (left.iter().peekable(), right.iter().peekable());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if compare(a, b).is_le() {
+            merged.extend(left.next());
+        } else {
+            merged.extend(right.next());
+        }
+    }
+    merged.extend(left.chain(right));
+}
+
+/// A sphere bounding the sub-path of each node of a [`Tree`], used to skip
+/// sub-paths which can't contain the most novel point of a segment.
+///
+/// This is a heuristic, with no better worst case than scanning every point:
+/// when the points of a segment are all almost equally far from its chord, few
+/// nodes can be skipped.
+struct SpherePruning<C> {
+    points: Vec<C>,
+    tree: Tree<Sphere<C>>,
+}
+
+struct Sphere<C> {
+    centre: C,
+    radius: f64,
+}
+
+impl<C: Point> SpherePruning<C> {
+    fn new(points: Vec<C>) -> Self {
+        let mut spheres = Self {
+            points: Vec::with_capacity(points.len()),
+            tree: Tree::new(),
+        };
+        spheres.extend(points);
+        spheres
+    }
+
+    /// Appends points to the path, and extends the tree over them.
+    fn extend(&mut self, points: impl IntoIterator<Item = C>) {
+        self.points.extend(points);
+        let points = &self.points;
+        self.tree.extend(points.len(), &mut |range, _| {
+            let sub_path = &points[range];
+            // The centroid, which is usually close to the centre of the
+            // smallest bounding sphere
+            let centre = sub_path
+                .iter()
+                .zip(1..)
+                .skip(1)
+                .fold(sub_path[0], |centre, (&point, n)| {
+                    centre + (point - centre) * (1.0 / f64::from(n))
+                });
+            let radius = sub_path
+                .iter()
+                .map(|&point| (point - centre).magnitude())
+                .fold(0.0, f64::max);
+            Sphere { centre, radius }
+        });
+    }
+
+    /// Searches the part of a node's sub-path within `range`, skipping any part
+    /// of it which can't contain a point more novel than the best found so far.
+    fn search(
+        &self,
+        node: &Node<Sphere<C>>,
+        range: &Range<usize>,
+        distance: &impl Fn(&C) -> f64,
+        most_novel: &mut Option<(f64, usize)>,
+    ) {
+        let bound = |node: &Node<Sphere<C>>| distance(&node.summary.centre) + node.summary.radius;
+        let overlap = range.start.max(node.range.start)..range.end.min(node.range.end);
+        if overlap.is_empty() || most_novel.is_some_and(|(best, _)| bound(node) < best) {
+            return;
+        }
+        match node.children {
+            Some(children) => {
+                // Searching the more promising child first lets more of the
+                // other be skipped
+                let [first, second] = children.map(|child| &self.tree.nodes[child]);
+                let children = if bound(second) > bound(first) {
+                    [second, first]
+                } else {
+                    [first, second]
+                };
+                for child in children {
+                    self.search(child, range, distance, most_novel);
+                }
+            }
+            None => {
+                for index in overlap {
+                    offer(most_novel, distance(&self.points[index]), index);
+                }
+            }
+        }
+    }
+}
+
+impl<C: Point + Send + 'static> SegmentIndex for SpherePruning<C> {
+    fn most_novel_coordinate(&self, start: usize, end: usize) -> Option<(f64, usize)> {
+        if end >= self.points.len() || end < start + 2 {
+            return None;
+        }
+        let (line_start, line_end) = (self.points[start], self.points[end]);
+        let distance = |point: &C| distance_from_line(&line_start, &line_end, point);
+
+        let mut most_novel = None;
+        for &root in &self.tree.roots {
+            self.search(
+                &self.tree.nodes[root],
+                &(start + 1..end),
+                &distance,
+                &mut most_novel,
+            );
+        }
+        most_novel
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use float_cmp::assert_approx_eq;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{IdGenerator, NodeId, Positions, Search};
+
+    fn data<C>(coordinates: &[C]) -> Vec<Datum<(), u64, C>>
+    where
+        C: Copy,
+    {
+        coordinates
+            .iter()
+            .zip(0..)
+            .map(|(&coordinate, timestamp)| Datum {
+                id: uuid::Uuid::new_v4(),
+                timestamp,
+                coordinate,
+                payload: (),
+            })
+            .collect()
+    }
+
+    /// Checks the index finds a point as novel as `rdp` does in every segment.
+    fn assert_matches_rdp<C: Point>(coordinates: &[C], index: &dyn SegmentIndex) {
+        let data = data(coordinates);
+        let history: Vec<_> = data.iter().collect();
+        for start in 0..history.len() {
+            for end in start..history.len() {
+                let expected = rdp(&history[start..=end]);
+                let actual = index.most_novel_coordinate(start, end);
+                match (expected, actual) {
+                    (Some((_, expected, _)), Some((actual, index))) => {
+                        assert!(start < index && index < end);
+                        assert_approx_eq!(f64, actual, expected, epsilon = 1e-9);
+                    }
+                    (None, None) => {}
+                    _ => panic!("{expected:?} != {actual:?} in {start}..={end}"),
+                }
+            }
+        }
+    }
+
+    fn ids<C>(selected: &[&Datum<(), u64, C>]) -> Vec<uuid::Uuid> {
+        selected.iter().map(|datum| datum.id).collect()
+    }
+
+    fn spiral(len: i32) -> impl Iterator<Item = (f64, f64, f64)> {
+        (0..len).map(|i| {
+            let angle = f64::from(i) / 10.0;
+            (angle * angle.cos(), angle * angle.sin(), -angle)
+        })
+    }
+
+    #[test]
+    fn loop_and_loiter() {
+        // Segments with coincident endpoints, and with no novel points at all
+        let mut coordinates = vec![(0.0, 0.0), (3.0, 0.0), (3.0, 4.0), (0.0, 4.0), (0.0, 0.0)];
+        coordinates.extend([(2.0, 2.0); 40]);
+        let planar: Vec<_> = coordinates
+            .iter()
+            .map(|&(x, y)| Coordinate2D::new(x, y))
+            .collect();
+        let spatial: Vec<_> = coordinates
+            .iter()
+            .map(|&(x, y)| Coordinate::new(x, y, 0.0))
+            .collect();
+
+        assert_matches_rdp(&planar, &PathHulls::new(planar.clone()));
+        assert_matches_rdp(&spatial, &SpherePruning::new(spatial.clone()));
+    }
+
+    #[test]
+    fn search_spiral() {
+        let mut planar = Positions::with_id_generator(IdGenerator::sequential(0));
+        let mut spatial = Positions::with_id_generator(IdGenerator::sequential(0));
+        for ((x, y, z), timestamp) in spiral(2000).zip(0_u64..) {
+            planar.add(timestamp, Coordinate2D::new(x, y)).unwrap();
+            spatial.add(timestamp, Coordinate::new(x, y, z)).unwrap();
+        }
+        let recipient = NodeId::new_v4();
+
+        // Without ties, the same point is found in each segment, so the whole
+        // search is the same
+        assert_eq!(
+            ids(&planar.most_novel_coordinates(&Search::new(HullRdp, None), &recipient, 500)),
+            ids(&planar.most_novel_coordinates(&Search::new(rdp, None), &recipient, 500)),
+        );
+        assert_eq!(
+            ids(&spatial.most_novel_coordinates(&Search::new(HullRdp, None), &recipient, 500)),
+            ids(&spatial.most_novel_coordinates(&Search::new(rdp, None), &recipient, 500)),
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn planar_matches_rdp(
+            coordinates in prop::collection::vec((-100.0..100.0, -100.0..100.0), 0..60)
+        ) {
+            let coordinates: Vec<_> = coordinates
+                .into_iter()
+                .map(|(x, y)| Coordinate2D::new(x, y))
+                .collect();
+            assert_matches_rdp(&coordinates, &PathHulls::new(coordinates.clone()));
+        }
+
+        #[test]
+        fn spatial_matches_rdp(
+            coordinates in prop::collection::vec((-100.0..100.0, -100.0..100.0, -10.0..10.0), 0..60)
+        ) {
+            let coordinates: Vec<_> = coordinates
+                .into_iter()
+                .map(|(x, y, z)| Coordinate::new(x, y, z))
+                .collect();
+            assert_matches_rdp(&coordinates, &SpherePruning::new(coordinates.clone()));
+        }
+
+        #[test]
+        fn extended_index_matches_rdp(
+            coordinates in prop::collection::vec((-100.0..100.0, -100.0..100.0, -10.0..10.0), 0..60),
+            mut appended in prop::collection::vec(0..60usize, 0..4),
+        ) {
+            appended.push(coordinates.len());
+            appended.sort_unstable();
+            let planar: Vec<_> = coordinates
+                .iter()
+                .map(|&(x, y, _)| Coordinate2D::new(x, y))
+                .collect();
+            let spatial: Vec<_> = coordinates
+                .iter()
+                .map(|&(x, y, z)| Coordinate::new(x, y, z))
+                .collect();
+
+            let mut hulls = PathHulls::new(Vec::new());
+            let mut spheres = SpherePruning::new(Vec::new());
+            let mut start = 0;
+            for end in appended.into_iter().map(|end| end.min(coordinates.len())) {
+                hulls.extend(planar[start..end].iter().copied());
+                spheres.extend(spatial[start..end].iter().copied());
+                start = end;
+            }
+
+            assert_matches_rdp(&planar, &hulls);
+            assert_matches_rdp(&spatial, &spheres);
+            // The vertices of the rebuilt leaves aren't left behind
+            prop_assert_eq!(hulls.vertices, PathHulls::new(planar).vertices);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Progressive (coarse-to-fine) ordering of a whole time-series.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use chrono::{DateTime, Utc};
+
+use super::{
+    geometric_novelty::{GeometricNovelty, MaxHeap, SegmentIndex},
+    search_strategy::most_novel_in,
+    Datum,
+};
+use crate::{
+    coordinate::{Coordinate, Point},
+    parallel::MaybeSync,
+    probability::Probability,
+    timestamp::Timestamp,
+    transmission_history::TransmissionHistory,
+    NodeId,
+};
+
+/// An iterator over the data points of a time-series in progressive
+/// refinement order, created by [`Positions::progressive`](super::Positions::progressive).
+///
+/// The most recent data point comes first, then the first, and then each data
+/// point in turn is the one described worst (according to the geometric
+/// novelty strategy) by the track through those before it. So any prefix of
+/// the iterator is a good approximation of the track, and the more is sent,
+/// the better it gets.
+///
+/// Data points which the recipient certainly has (according to the
+/// transmission history when the iterator was created) are already part of
+/// the track it knows, so the track is refined around them and they are never
+/// yielded.
+///
+/// Data points are found lazily, so taking a short prefix of a long track
+/// only searches as much as it needs to.
+pub struct Progressive<'a, S, P = (), T = DateTime<Utc>, C = Coordinate> {
+    strategy: S,
+    segment_index: Option<Box<dyn SegmentIndex>>,
+    positions: Vec<&'a Datum<P, T, C>>,
+    // The endpoints still to be yielded, in reverse order
+    endpoints: Vec<usize>,
+    segments: MaxHeap<T>,
+}
+
+impl<'a, S, P, T, C> Progressive<'a, S, P, T, C>
+where
+    S: GeometricNovelty<P, T, C> + MaybeSync,
+    P: MaybeSync,
+    T: Timestamp + MaybeSync,
+    C: Point,
+{
+    /// Creates an iterator over `positions` (which must be in time order) for
+    /// `recipient`.
+    pub fn new(
+        strategy: S,
+        transmission_history: &TransmissionHistory,
+        positions: Vec<&'a Datum<P, T, C>>,
+        recipient: &NodeId,
+    ) -> Self {
+        let known = |datum: &Datum<P, T, C>| {
+            transmission_history.probability_recipient_has_datum(recipient, &datum.id)
+                == Probability::ONE_HUNDRED
+        };
+        let last = positions.len().saturating_sub(1);
+        let endpoints = [0, last]
+            .into_iter()
+            .filter(|&index| positions.get(index).is_some_and(|&datum| !known(datum)))
+            .take(positions.len())
+            .collect();
+
+        // The recipient's track already passes through the endpoints (once
+        // they have been sent) and everything it has, so split the time-series
+        // at each of them
+        let mut segments = MaxHeap::default();
+        let segment_index = strategy.index(&positions);
+        let mut start = 0;
+        for end in 1..positions.len() {
+            if end == last || known(positions[end]) {
+                let [most_novel] = most_novel_in(
+                    &strategy,
+                    segment_index.as_deref(),
+                    &mut None,
+                    &positions,
+                    [(start, end)],
+                );
+                if let Some((distance, index)) = most_novel {
+                    segments.push((start, end), positions[index], distance, index, 1);
+                }
+                start = end;
+            }
+        }
+
+        Self {
+            strategy,
+            segment_index,
+            positions,
+            endpoints,
+            segments,
+        }
+    }
+}
+
+impl<'a, S, P, T, C> Iterator for Progressive<'a, S, P, T, C>
+where
+    S: GeometricNovelty<P, T, C> + MaybeSync,
+    P: MaybeSync,
+    T: Timestamp + MaybeSync,
+    C: Point,
+{
+    type Item = &'a Datum<P, T, C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(index) = self.endpoints.pop() {
+            return Some(self.positions[index]);
+        }
+
+        let ((start, end), _, index, depth) = self.segments.pop()?;
+        let segments = [(start, index), (index, end)];
+        let most_novel = most_novel_in(
+            &self.strategy,
+            self.segment_index.as_deref(),
+            &mut None,
+            &self.positions,
+            segments,
+        );
+        for (bounds, most_novel) in segments.into_iter().zip(most_novel) {
+            if let Some((distance, index)) = most_novel {
+                self.segments
+                    .push(bounds, self.positions[index], distance, index, depth + 1);
+            }
+        }
+        Some(self.positions[index])
+    }
+}
+
+impl<S, P, T, C> core::fmt::Debug for Progressive<'_, S, P, T, C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Progressive")
+            .field("len", &self.positions.len())
+            .field("remaining_endpoints", &self.endpoints.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use chrono::Duration;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{positions::geometric_novelty::distance_from_line, rdp, HullRdp, Positions};
+
+    fn positions(coordinates: &[(f64, f64)]) -> Positions {
+        let mut positions = Positions::default();
+        for (seconds, &(x, y)) in (0..).zip(coordinates) {
+            positions
+                .add(
+                    DateTime::UNIX_EPOCH + Duration::seconds(seconds),
+                    Coordinate::new(x, y, 0.0),
+                )
+                .unwrap();
+        }
+        positions
+    }
+
+    #[test]
+    fn endpoints_first() {
+        let positions = positions(&[(0.0, 0.0), (1.0, 1.0), (2.0, 3.0), (3.0, 1.0), (4.0, 0.0)]);
+        let ids: Vec<_> = positions.iter().map(|datum| datum.id).collect();
+
+        let order: Vec<_> = positions
+            .progressive(rdp, &NodeId::new_v4())
+            .map(|datum| datum.id)
+            .collect();
+        // The tie between the sides of the peak goes to the most recent
+        assert_eq!(order, [ids[4], ids[0], ids[2], ids[3], ids[1]]);
+    }
+
+    #[test]
+    fn single_datum() {
+        let positions = positions(&[(0.0, 0.0)]);
+        assert_eq!(positions.progressive(rdp, &NodeId::new_v4()).count(), 1);
+        assert_eq!(
+            Positions::<()>::default()
+                .progressive(rdp, &NodeId::new_v4())
+                .count(),
+            0
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn each_datum_is_the_worst_described(
+            coordinates in prop::collection::vec((-100.0..100.0, -100.0..100.0), 1..40),
+            known in prop::collection::vec(any::<bool>(), 40),
+        ) {
+            let mut positions = positions(&coordinates);
+            let recipient = NodeId::new_v4();
+            let ids: Vec<_> = positions.iter().map(|datum| datum.id).collect();
+            for (&id, _) in ids.iter().zip(&known).filter(|(_, &known)| known) {
+                positions
+                    .transmission_history_mut()
+                    .record(recipient, id, Probability::ONE_HUNDRED);
+            }
+
+            // The recipient's track, as indices of the data points it has
+            let mut track: Vec<usize> = (0..ids.len()).filter(|&i| known[i]).collect();
+            let order: Vec<_> = positions.progressive(rdp, &recipient).collect();
+            let data: Vec<_> = positions.iter().collect();
+            for (n, datum) in order.iter().enumerate() {
+                let index = ids.iter().position(|id| *id == datum.id).unwrap();
+                prop_assert!(!known[index]);
+                prop_assert!(!track.contains(&index));
+
+                // Once both endpoints have been sent, no other data point is
+                // further from the track than this one
+                if n >= 2 || (track.contains(&0) && track.contains(&(ids.len() - 1))) {
+                    let worst = track
+                        .windows(2)
+                        .flat_map(|pair| {
+                            let (start, end) = (data[pair[0]], data[pair[1]]);
+                            data[pair[0] + 1..pair[1]].iter().map(|datum| {
+                                distance_from_line(&start.coordinate, &end.coordinate, &datum.coordinate)
+                            })
+                        })
+                        .fold(0.0, f64::max);
+                    let segment = track.partition_point(|&i| i < index);
+                    let (start, end) = (data[track[segment - 1]], data[track[segment]]);
+                    let distance = distance_from_line(&start.coordinate, &end.coordinate, &datum.coordinate);
+                    prop_assert!(distance >= worst);
+                }
+
+                let position = track.partition_point(|&i| i < index);
+                track.insert(position, index);
+            }
+
+            // Everything the recipient doesn't have is sent eventually
+            prop_assert_eq!(track.len(), ids.len());
+        }
+
+        #[test]
+        fn indexed_strategies_match(
+            coordinates in prop::collection::vec((-100.0..100.0, -100.0..100.0), 0..40),
+        ) {
+            let positions = positions(&coordinates);
+            let recipient = NodeId::new_v4();
+            let rdp: Vec<_> = positions.progressive(rdp, &recipient).map(|datum| datum.id).collect();
+            let hull: Vec<_> = positions.progressive(HullRdp, &recipient).map(|datum| datum.id).collect();
+            prop_assert_eq!(rdp, hull);
+        }
+    }
+}
@@ -14,23 +14,40 @@
 //! See [`rdp`](crate::positions::geometric_novelty::rdp) for an example of a
 //! geometric novelty strategy which can be used with [`Search`].
 
-use std::{cmp::Reverse, collections::BTreeMap};
+use alloc::{boxed::Box, collections::BinaryHeap, vec, vec::Vec};
+use core::{any::TypeId, cmp::Reverse, fmt};
+
+use chrono::{DateTime, Utc};
 
 use super::{
-    geometric_novelty::{GeometricNovelty, MaxHeap},
-    Datum,
+    endpoint_novelty::{EndpointNovelty, Separation},
+    geometric_novelty::{GeometricNovelty, MaxHeap, SegmentCache, SegmentIndex},
+    Datum, Positions, Revision,
+};
+use crate::{
+    coordinate::{Coordinate, Point},
+    parallel::{self, MaybeSync, MIN_PARALLEL_LEN},
+    probability::Probability,
+    timestamp::Timestamp,
+    transmission_history::TransmissionHistory,
+    NodeId,
 };
-use crate::{probability::Probability, transmission_history::TransmissionHistory, NodeId};
+
+mod optimal;
+pub use optimal::{Objective, Optimal};
 
 /// A search strategy for finding the most novel positions in a time-series.
-pub trait SearchStrategy {
+///
+/// The trait is generic over the payload type `P` of the data points, as well
+/// as over their timestamp and coordinate types.
+pub trait SearchStrategy<P = (), T = DateTime<Utc>, C = Coordinate> {
     fn search<'a>(
         &self,
         transmission_history: &TransmissionHistory,
-        positions: &[&'a Datum],
+        positions: &[&'a Datum<P, T, C>],
         n_max: usize,
         recipient: &NodeId,
-    ) -> Vec<&'a Datum>;
+    ) -> Vec<&'a Datum<P, T, C>>;
 }
 
 /// A search strategy which searches recursively through the time-series.
@@ -39,249 +56,1855 @@ pub trait SearchStrategy {
 /// searches the left and right subsegments on either side of it.
 ///
 /// The strategy for determining the most novel coordinate in a segment is
-/// provided by the `GeometricNovelty` trait.
+/// provided by the `GeometricNovelty` trait. The first and last coordinates are
+/// scored separately, by the `EndpointNovelty` trait (see
+/// [`Search::with_endpoint_novelty`]).
 ///
 /// # Example
-/// ```
+#[cfg_attr(feature = "std", doc = "```")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
 /// use chrono::Utc;
 /// use position_share::{rdp, Coordinate, NodeId, Positions, Search};
 ///
 /// let mut positions = Positions::default();
-/// positions.add(Utc::now(), Coordinate::new(0.0, 0.0, 0.0));
-/// positions.add(Utc::now(), Coordinate::new(1.0, 1.0, 0.0));
-/// positions.add(Utc::now(), Coordinate::new(2.0, 2.0, 0.0));
-/// positions.add(Utc::now(), Coordinate::new(3.0, 1.0, 0.0));
-/// positions.add(Utc::now(), Coordinate::new(4.0, 0.0, 0.0));
+/// positions.add(Utc::now(), Coordinate::new(0.0, 0.0, 0.0)).unwrap();
+/// positions.add(Utc::now(), Coordinate::new(1.0, 1.0, 0.0)).unwrap();
+/// positions.add(Utc::now(), Coordinate::new(2.0, 2.0, 0.0)).unwrap();
+/// positions.add(Utc::now(), Coordinate::new(3.0, 1.0, 0.0)).unwrap();
+/// positions.add(Utc::now(), Coordinate::new(4.0, 0.0, 0.0)).unwrap();
 ///
 /// let search_strategy = Search::new(rdp, None);
 /// let recipient = NodeId::new_v4();
 ///
 /// let most_novel = positions.most_novel_coordinates(&search_strategy, &recipient, 3);
 /// ```
-pub struct Search<S>
-where
-    S: GeometricNovelty,
-{
+pub struct Search<S, E = Separation, T: Timestamp = DateTime<Utc>> {
     strategy: S,
+    endpoint_novelty: E,
     threshold: Option<f64>,
+    tolerance: Option<f64>,
+    time_decay: Option<TimeDecay<T>>,
 }
 
-impl<S> SearchStrategy for Search<S>
+impl<S, E, P, T, C> SearchStrategy<P, T, C> for Search<S, E, T>
 where
-    S: GeometricNovelty,
+    S: GeometricNovelty<P, T, C> + MaybeSync,
+    E: EndpointNovelty<P, T, C>,
+    P: MaybeSync,
+    T: Timestamp + MaybeSync,
+    C: Point,
 {
     fn search<'a>(
         &self,
         transmission_history: &TransmissionHistory,
-        positions: &[&'a Datum],
+        positions: &[&'a Datum<P, T, C>],
         n_max: usize,
         recipient: &NodeId,
-    ) -> Vec<&'a Datum> {
-        // First consider the first and last coordinates.
-        let (start_novelty, end_novelty) = start_and_end_point_novelty(positions);
-
-        let mut results = Results::new(n_max);
-        let first_datum = positions.first().unwrap();
-        let last_datum = positions.last().unwrap();
-        results.insert(
-            first_datum,
-            Novelty {
-                distance: start_novelty,
-                probability_not_transmitted: transmission_history
-                    .probability_recipient_has_datum(recipient, &first_datum.id)
-                    .complement(),
-                id: first_datum.id,
-            },
-        );
-        results.insert(
-            last_datum,
-            Novelty {
-                distance: end_novelty,
-                probability_not_transmitted: transmission_history
-                    .probability_recipient_has_datum(recipient, &last_datum.id)
-                    .complement(),
-                id: last_datum.id,
-            },
+    ) -> Vec<&'a Datum<P, T, C>> {
+        let mut context = SearchContext::uncached();
+        context.segment_index = self.strategy.index(positions);
+        self.rank(
+            &mut context,
+            transmission_history,
+            positions,
+            n_max,
+            recipient,
+            0,
         );
 
-        // Find the most novel coordinate in the first segment.
-        let Some((datum, distance, index)) = self.strategy.most_novel_coordinate(positions) else {
-            return vec![];
-        };
-        let mut segment_heap = MaxHeap::default();
-        segment_heap.push(positions, datum, distance, index);
+        let mut selected = Vec::new();
+        context
+            .selected
+            .drain_sorted(|_, index| selected.push(positions[index]));
+        selected
+    }
+}
 
-        // Then search the rest of the coordinates.
-        while let Some((segment, datum, distance, index)) = segment_heap.pop() {
-            let novelty = Novelty {
-                distance,
-                probability_not_transmitted: transmission_history
-                    .probability_recipient_has_datum(recipient, &datum.id)
-                    .complement(),
-                id: datum.id,
+impl<S, E, T: Timestamp> Search<S, E, T> {
+    /// Searches for the most novel positions, explaining why each was
+    /// selected.
+    ///
+    /// This returns the same selection as [`SearchStrategy::search`], but each
+    /// selected datum is accompanied by its [`Novelty`]. Additionally, up to
+    /// `n_rejected` of the most novel candidates which were considered but
+    /// *not* selected are returned, along with the reason for their
+    /// rejection.
+    ///
+    /// With the `rayon` feature enabled, the two subsegments either side of
+    /// each selected datum are searched in parallel (if they are long enough
+    /// to benefit).
+    pub fn explain<'a, P, C: Point>(
+        &self,
+        transmission_history: &TransmissionHistory,
+        positions: &[&'a Datum<P, T, C>],
+        n_max: usize,
+        recipient: &NodeId,
+        n_rejected: usize,
+    ) -> Explanation<'a, P, T, C>
+    where
+        S: GeometricNovelty<P, T, C> + MaybeSync,
+        E: EndpointNovelty<P, T, C>,
+        P: MaybeSync,
+        T: MaybeSync,
+    {
+        let mut context = SearchContext::uncached();
+        context.segment_index = self.strategy.index(positions);
+        self.rank(
+            &mut context,
+            transmission_history,
+            positions,
+            n_max,
+            recipient,
+            n_rejected,
+        );
+
+        let mut explanation = Explanation::default();
+        context.selected.drain_sorted(|novelty, index| {
+            explanation.selected.push(Candidate {
+                datum: positions[index],
+                novelty,
+            });
+        });
+        context.rejected.drain_sorted(|novelty, (index, reason)| {
+            let candidate = Candidate {
+                datum: positions[index],
+                novelty,
             };
+            explanation.rejected.push((candidate, reason));
+        });
+        explanation
+    }
 
-            // stop condition
-            if let (Some(min_novelty), Some(threshold)) = (results.min_novelty(), self.threshold) {
-                if novelty < *min_novelty && distance < threshold * min_novelty.distance {
-                    break;
+    /// Searches for the most novel positions, reusing the buffers in
+    /// `context`.
+    ///
+    /// This makes the same selection as [`SearchStrategy::search`], but once
+    /// the buffers have grown to fit the time-series, repeated searches don't
+    /// allocate.
+    ///
+    /// See [`Positions::most_novel_coordinates_with_context`].
+    pub fn search_with_context<'c, 'a, P, C: Point>(
+        &self,
+        context: &'c mut SearchContext<T>,
+        positions: &'a Positions<P, T, C>,
+        n_max: usize,
+        recipient: &NodeId,
+    ) -> Selection<'c, 'a, P, T, C>
+    where
+        S: GeometricNovelty<P, T, C> + MaybeSync + 'static,
+        E: EndpointNovelty<P, T, C>,
+        P: MaybeSync,
+        T: MaybeSync,
+    {
+        let appended = context.bind::<S>(positions.revision(), self.strategy.cache_key());
+        let mut history = reuse(core::mem::take(&mut context.history));
+        history.extend(positions.iter());
+        if appended {
+            let extended = context
+                .segment_index
+                .as_deref_mut()
+                .is_some_and(|segment_index| self.strategy.extend_index(segment_index, &history));
+            if !extended {
+                context.segment_index = None;
+            }
+        }
+        if context.segment_index.is_none() {
+            context.segment_index = self.strategy.index(&history);
+        }
+        self.rank(
+            context,
+            positions.transmission_history(),
+            &history,
+            n_max,
+            recipient,
+            0,
+        );
+
+        let mut selected = reuse(core::mem::take(&mut context.selection));
+        context
+            .selected
+            .drain_sorted(|_, index| selected.push(history[index]));
+        context.history = release(history);
+
+        Selection {
+            selected,
+            buffer: &mut context.selection,
+        }
+    }
+
+    /// Returns the fewest data points which describe the track to within the
+    /// tolerance, in time order.
+    ///
+    /// See [`Positions::simplify`].
+    pub(super) fn simplify<'a, P, C: Point>(
+        &self,
+        transmission_history: &TransmissionHistory,
+        positions: &[&'a Datum<P, T, C>],
+        recipient: &NodeId,
+    ) -> Vec<&'a Datum<P, T, C>>
+    where
+        S: GeometricNovelty<P, T, C> + MaybeSync,
+        P: MaybeSync,
+        T: MaybeSync,
+    {
+        let tolerance = self.tolerance.unwrap_or(0.0);
+        let segment_index = self.strategy.index(positions);
+        let unknown: Vec<bool> = positions
+            .iter()
+            .map(|datum| {
+                transmission_history.probability_recipient_has_datum(recipient, &datum.id)
+                    < Probability::ONE_HUNDRED
+            })
+            .collect();
+
+        // The fewest data points selected to describe the track up to each
+        // data point, as a vertex, and the previous vertex. The shortest path
+        // is found in order, as every segment runs forwards in time.
+        let mut fewest = vec![(usize::MAX, 0); positions.len()];
+        let Some(first) = fewest.first_mut() else {
+            return Vec::new();
+        };
+        *first = (usize::from(unknown[0]), 0);
+        // Data points the recipient has must be vertices, so no segment may
+        // skip them
+        let mut known = 0;
+        for j in 1..positions.len() {
+            for i in (known..j).rev() {
+                let count = fewest[i].0 + usize::from(unknown[j]);
+                if count >= fewest[j].0 {
+                    continue;
                 }
+                let [most_novel] = most_novel_in(
+                    &self.strategy,
+                    segment_index.as_deref(),
+                    &mut None,
+                    positions,
+                    [(i, j)],
+                );
+                if most_novel.map_or(true, |(distance, _)| distance <= tolerance) {
+                    fewest[j] = (count, i);
+                }
+            }
+            if !unknown[j] {
+                known = j;
             }
+        }
 
-            // Only insert the datum if the recipient has a non-zero probability of not
-            // having received it yet.
-            if novelty.probability_not_transmitted > Probability::ZERO {
-                results.insert(datum, novelty);
+        let mut selected = Vec::with_capacity(fewest[positions.len() - 1].0);
+        let mut j = positions.len() - 1;
+        loop {
+            if unknown[j] {
+                selected.push(positions[j]);
             }
-            // Push the left and right subsegments onto the queue
-            for segment in [&segment[..=index], &segment[index..]] {
-                if let Some((datum, distance, index)) = self.strategy.most_novel_coordinate(segment)
-                {
-                    segment_heap.push(segment, datum, distance, index);
+            if j == 0 {
+                break;
+            }
+            j = fewest[j].1;
+        }
+        selected.reverse();
+        selected
+    }
+
+    /// Ranks the positions, leaving the selected and rejected candidates (as
+    /// indices into `positions`) in the context.
+    ///
+    /// The context's segment index (if any) must be of `positions`.
+    fn rank<P, C: Point>(
+        &self,
+        context: &mut SearchContext<T>,
+        transmission_history: &TransmissionHistory,
+        positions: &[&Datum<P, T, C>],
+        n_max: usize,
+        recipient: &NodeId,
+        n_rejected: usize,
+    ) where
+        S: GeometricNovelty<P, T, C> + MaybeSync,
+        E: EndpointNovelty<P, T, C>,
+        P: MaybeSync,
+        T: MaybeSync,
+    {
+        let SearchContext {
+            segments: segment_heap,
+            segment_cache,
+            segment_index,
+            selected: results,
+            rejected,
+            ..
+        } = context;
+        let segment_index = segment_index.as_deref();
+        segment_heap.clear();
+        if let Some(segment_cache) = segment_cache.as_mut() {
+            segment_cache.start();
+        }
+        results.reset(n_max);
+        rejected.reset(n_rejected);
+
+        // Age is measured relative to the configured reference time, or the most
+        // recent datum if there isn't one.
+        let now = self
+            .time_decay
+            .and_then(|time_decay| time_decay.reference)
+            .or_else(|| positions.last().map(|datum| datum.timestamp));
+        let novelty_of = |datum: &Datum<P, T, C>, distance, depth| Novelty {
+            distance,
+            probability_not_transmitted: transmission_history
+                .probability_recipient_has_datum(recipient, &datum.id)
+                .complement(),
+            recency: match (self.time_decay, now) {
+                (Some(time_decay), Some(now)) => {
+                    time_decay.weight(now.seconds_since(&datum.timestamp))
+                }
+                _ => 1.0,
+            },
+            depth,
+            timestamp: datum.timestamp,
+            id: datum.id,
+        };
+
+        let (Some(&first_datum), Some(&last_datum)) = (positions.first(), positions.last()) else {
+            return;
+        };
+        let last = positions.len() - 1;
+
+        // First consider the first and last coordinates.
+        let (start_novelty, end_novelty) =
+            self.endpoint_novelty
+                .endpoint_novelty(transmission_history, positions, recipient);
+
+        let (include_start, include_end) =
+            EndpointNovelty::<P, T, C>::always_include(&self.endpoint_novelty);
+
+        let endpoints = [
+            (0, novelty_of(first_datum, start_novelty, 0), include_start),
+            (last, novelty_of(last_datum, end_novelty, 0), include_end),
+        ];
+        // A single datum is both the first and the last.
+        let endpoints = if positions.len() == 1 {
+            &[(0, endpoints[0].1, include_start || include_end)][..]
+        } else {
+            &endpoints[..]
+        };
+        for &(index, novelty, always_include) in endpoints {
+            consider(results, rejected, novelty, index, always_include);
+        }
+
+        // Find the most novel coordinate in the first segment (if there are any
+        // coordinates between the endpoints).
+        let most_novel = most_novel_in(
+            &self.strategy,
+            segment_index,
+            segment_cache,
+            positions,
+            [(0, last)],
+        );
+        if let [Some((distance, index))] = most_novel {
+            segment_heap.push((0, last), positions[index], distance, index, 1);
+        }
+
+        // Then search the rest of the coordinates.
+        while let Some(((start, end), distance, index, depth)) = segment_heap.pop() {
+            let novelty = novelty_of(positions[index], distance, depth);
+
+            if let Some(reason) = self.stopping_reason(&novelty, results.min_novelty()) {
+                rejected.insert(novelty, (index, reason));
+                break;
+            }
+
+            consider(results, rejected, novelty, index, false);
+            // Push the left and right subsegments onto the queue. They are
+            // independent, so may be searched in parallel.
+            let segments = [(start, index), (index, end)];
+            let most_novel = most_novel_in(
+                &self.strategy,
+                segment_index,
+                segment_cache,
+                positions,
+                segments,
+            );
+            for (bounds, most_novel) in segments.into_iter().zip(most_novel) {
+                if let Some((distance, index)) = most_novel {
+                    segment_heap.push(bounds, positions[index], distance, index, depth + 1);
                 }
             }
         }
-        results.into_iter().collect()
+    }
+
+    /// Returns the reason to stop searching, if the most novel remaining
+    /// segment isn't novel enough to continue.
+    fn stopping_reason(
+        &self,
+        novelty: &Novelty<T>,
+        min_novelty: Option<&Novelty<T>>,
+    ) -> Option<Rejection> {
+        // Every remaining segment is within tolerance of its chord.
+        if self
+            .tolerance
+            .is_some_and(|tolerance| novelty.distance <= tolerance)
+        {
+            return Some(Rejection::WithinTolerance);
+        }
+        if let (Some(min_novelty), Some(threshold)) = (min_novelty, self.threshold) {
+            if novelty < min_novelty && novelty.distance < threshold * min_novelty.distance {
+                return Some(Rejection::BelowThreshold);
+            }
+        }
+        None
     }
 }
 
-impl<S> Search<S>
+/// Adds a candidate to the results, recording it as rejected if it (or the
+/// candidate it displaces) doesn't make the cut.
+fn consider<T: Ord>(
+    results: &mut Results<T, usize>,
+    rejected: &mut Results<T, (usize, Rejection)>,
+    novelty: Novelty<T>,
+    index: usize,
+    always_include: bool,
+) {
+    // Only insert the datum if the recipient has a non-zero probability of not
+    // having received it yet.
+    if novelty.probability_not_transmitted > Probability::ZERO {
+        let outranked = if always_include {
+            results.insert_always(novelty, index)
+        } else {
+            results.insert(novelty, index)
+        };
+        if let Some((novelty, index)) = outranked {
+            rejected.insert(novelty, (index, Rejection::Outranked));
+        }
+    } else {
+        rejected.insert(novelty, (index, Rejection::AlreadyTransmitted));
+    }
+}
+
+/// Returns the (distance, index) of the most novel coordinate in each segment
+/// of `positions`, given by its first and last index.
+///
+/// Segments which have already been searched are looked up in the cache (if
+/// there is one), and the rest in the strategy's index (if it has one). The
+/// two halves of a long segment are searched in parallel.
+pub(super) fn most_novel_in<S, P, T, C, const N: usize>(
+    strategy: &S,
+    segment_index: Option<&dyn SegmentIndex>,
+    segment_cache: &mut Option<SegmentCache>,
+    positions: &[&Datum<P, T, C>],
+    bounds: [(usize, usize); N],
+) -> [Option<(f64, usize)>; N]
 where
-    S: GeometricNovelty,
+    S: GeometricNovelty<P, T, C> + MaybeSync,
+    P: MaybeSync,
+    T: MaybeSync,
+    C: Point,
 {
+    let segments = bounds.map(|(start, end)| &positions[start..=end]);
+    let cached = segments.map(|segment| {
+        segment_cache
+            .as_mut()
+            .and_then(|segment_cache| segment_cache.get(segment))
+    });
+    let search = |i: usize| {
+        let (start, end) = bounds[i];
+        cached[i].unwrap_or_else(|| {
+            segment_index.map_or_else(
+                || {
+                    strategy
+                        .most_novel_coordinate(segments[i])
+                        .map(|(_, distance, index)| (distance, index))
+                },
+                |segment_index| {
+                    segment_index
+                        .most_novel_coordinate(start, end)
+                        .map(|(distance, index)| (distance, index - start))
+                },
+            )
+        })
+    };
+    let len = segments.iter().map(|segment| segment.len()).sum::<usize>();
+    let most_novel = if N == 2 && len > MIN_PARALLEL_LEN {
+        let mut most_novel = [None; N];
+        (most_novel[0], most_novel[1]) = parallel::join(|| search(0), || search(1));
+        most_novel
+    } else {
+        core::array::from_fn(search)
+    };
+
+    if let Some(segment_cache) = segment_cache {
+        for i in 0..N {
+            if cached[i].is_none() {
+                segment_cache.insert(segments[i], most_novel[i]);
+            }
+        }
+    }
+    // The cache stores indices within each segment, but they are returned as
+    // indices into `positions`
+    core::array::from_fn(|i| most_novel[i].map(|(distance, index)| (distance, bounds[i].0 + index)))
+}
+
+/// Reusable buffers for repeated searches.
+///
+/// Searching allocates a copy of the time-series (as references), a heap of
+/// segments to search, and the selection itself. When searching every few
+/// seconds, keeping these buffers between searches avoids the allocations
+/// entirely once they have grown to fit.
+///
+/// The context also remembers the most novel coordinate in each segment it
+/// searched. Between searches typically only a few data points have been
+/// appended, so only the segments spanning the new data (usually those at the
+/// end of the track) are searched again. Likewise, the strategy's segment index
+/// (see [`GeometricNovelty::index`]) is kept, and extended as data points are
+/// appended.
+/// The transmission history isn't cached, so changes in probability are always
+/// taken into account.
+///
+/// The cache belongs to a single collection and geometric novelty strategy,
+/// and is cleared automatically when the context is used with another.
+/// Strategies are told apart by their type and
+/// [`GeometricNovelty::cache_key`], and nothing is cached for a strategy
+/// without a key.
+///
+/// # Example
+#[cfg_attr(feature = "std", doc = "```")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// use chrono::{Duration, Utc};
+/// use position_share::{rdp, Coordinate, NodeId, Positions, Search, SearchContext};
+///
+/// let mut positions = Positions::default();
+/// let mut context = SearchContext::new();
+/// let search_strategy = Search::new(rdp, None);
+/// let recipient = NodeId::new_v4();
+///
+/// let start = Utc::now();
+/// for i in 0..10 {
+///     let x = f64::from(i);
+///     positions.add(start + Duration::seconds(i.into()), Coordinate::new(x, x.sin(), 0.0)).unwrap();
+///
+///     let selection = positions.most_novel_coordinates_with_context(
+///         &mut context,
+///         &search_strategy,
+///         &recipient,
+///         3,
+///     );
+///     assert!(selection.len() <= 3);
+/// }
+/// ```
+pub struct SearchContext<T = DateTime<Utc>> {
+    segments: MaxHeap<T>,
+    segment_cache: Option<SegmentCache>,
+    segment_index: Option<Box<dyn SegmentIndex>>,
+    // The collection and strategy (if it has a key) which were last searched
+    source: Option<(Revision, Option<(TypeId, u64)>)>,
+    selected: Results<T, usize>,
+    rejected: Results<T, (usize, Rejection)>,
+    // Buffers of references are kept as `usize`s between searches (see
+    // `reuse`).
+    history: Vec<usize>,
+    selection: Vec<usize>,
+}
+
+impl<T: Ord> SearchContext<T> {
+    /// Creates an empty context. Its buffers grow as required.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            segment_cache: Some(SegmentCache::default()),
+            ..Self::uncached()
+        }
+    }
+
+    /// Forgets the most novel coordinate in each segment searched so far, and
+    /// the strategy's segment index.
+    ///
+    /// This happens automatically when searching a different collection or
+    /// with a different geometric novelty strategy, so is only needed to free
+    /// memory.
+    pub fn clear_cache(&mut self) {
+        if let Some(segment_cache) = &mut self.segment_cache {
+            segment_cache.clear();
+        }
+        self.segment_index = None;
+    }
+
+    /// Prepares to search a revision of a collection with strategy `S` (whose
+    /// cache key is `key`), discarding whatever was cached for anything else.
+    ///
+    /// Returns `true` if data points have only been appended since the segment
+    /// index was built, so it may be extended rather than rebuilt.
+    fn bind<S: 'static>(&mut self, revision: Revision, key: Option<u64>) -> bool {
+        let strategy = key.map(|key| (TypeId::of::<S>(), key));
+        let mut appended = false;
+        match self.source {
+            Some((source, source_strategy))
+                if source.collection == revision.collection
+                    && strategy.is_some()
+                    && source_strategy == strategy =>
+            {
+                // The cached segments are keyed by their contents, so remain
+                // valid as the collection changes, but the index doesn't.
+                if source.version != revision.version {
+                    appended = revision.edited <= source.version;
+                    if !appended {
+                        self.segment_index = None;
+                    }
+                }
+            }
+            _ => self.clear_cache(),
+        }
+        self.source = Some((revision, strategy));
+        appended
+    }
+
+    /// Creates a context for a single search, which doesn't cache segments.
+    fn uncached() -> Self {
+        Self {
+            segments: MaxHeap::default(),
+            segment_cache: None,
+            segment_index: None,
+            source: None,
+            selected: Results::new(0),
+            rejected: Results::new(0),
+            history: Vec::new(),
+            selection: Vec::new(),
+        }
+    }
+}
+
+impl<T: Ord> Default for SearchContext<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SearchContext<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SearchContext")
+            .field("segments", &self.segments)
+            .field("segment_cache", &self.segment_cache)
+            .field("indexed", &self.segment_index.is_some())
+            .field("source", &self.source)
+            .field("selected", &self.selected)
+            .field("rejected", &self.rejected)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The data points selected by [`Search::search_with_context`].
+///
+/// Ordering: most novel to least novel
+///
+/// The buffer holding the selection is returned to the [`SearchContext`] when
+/// this is dropped.
+#[derive(Debug)]
+pub struct Selection<'c, 'a, P = (), T = DateTime<Utc>, C = Coordinate> {
+    selected: Vec<&'a Datum<P, T, C>>,
+    buffer: &'c mut Vec<usize>,
+}
+
+impl<'a, P, T, C> core::ops::Deref for Selection<'_, 'a, P, T, C> {
+    type Target = [&'a Datum<P, T, C>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.selected
+    }
+}
+
+impl<P, T, C> Drop for Selection<'_, '_, P, T, C> {
+    fn drop(&mut self) {
+        *self.buffer = release(core::mem::take(&mut self.selected));
+    }
+}
+
+/// Reuses the allocation of a buffer of `usize`s for an empty buffer of
+/// references.
+///
+/// A buffer of references can't be kept in a [`SearchContext`] between
+/// searches, as it would borrow the time-series (preventing new positions from
+/// being added), so its allocation is kept as a buffer of `usize`s instead.
+fn reuse<'a, X>(buffer: Vec<usize>) -> Vec<&'a X> {
+    recycle(buffer)
+}
+
+/// Returns the allocation of a buffer of references, for use with [`reuse`].
+fn release<X>(buffer: Vec<&X>) -> Vec<usize> {
+    recycle(buffer)
+}
+
+/// Clears `buffer`, and returns its allocation as an empty buffer of another
+/// type with the same size and alignment.
+fn recycle<A, B>(mut buffer: Vec<A>) -> Vec<B> {
+    const {
+        assert!(core::mem::size_of::<A>() == core::mem::size_of::<B>());
+        assert!(core::mem::align_of::<A>() == core::mem::align_of::<B>());
+    }
+    buffer.clear();
+    // Collecting an iterator over a `Vec` into a `Vec` with the same layout
+    // reuses its allocation, and there are no elements to convert
+    buffer.into_iter().map(|_| unreachable!()).collect()
+}
+
+/// An explanation of the selection made by [`Search::explain`].
+#[derive(Debug)]
+pub struct Explanation<'a, P = (), T = DateTime<Utc>, C = Coordinate> {
+    /// The selected data points.
+    ///
+    /// Ordering: most novel to least novel
+    pub selected: Vec<Candidate<'a, P, T, C>>,
+
+    /// The most novel data points which were not selected, and the reason
+    /// they were rejected.
+    ///
+    /// Ordering: most novel to least novel
+    pub rejected: Vec<(Candidate<'a, P, T, C>, Rejection)>,
+}
+
+impl<P, T, C> Default for Explanation<'_, P, T, C> {
+    fn default() -> Self {
+        Self {
+            selected: Vec::new(),
+            rejected: Vec::new(),
+        }
+    }
+}
+
+/// A data point considered by the search, along with its novelty.
+#[derive(Debug)]
+pub struct Candidate<'a, P = (), T = DateTime<Utc>, C = Coordinate> {
+    pub datum: &'a Datum<P, T, C>,
+    pub novelty: Novelty<T>,
+}
+
+/// The reason a candidate was not selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// The recipient is known to already have the data point.
+    AlreadyTransmitted,
+    /// There were at least `n_max` more novel data points.
+    Outranked,
+    /// The novelty was below the search threshold, so the search stopped.
+    BelowThreshold,
+    /// Every remaining segment was within the search tolerance of its chord,
+    /// so the search stopped.
+    WithinTolerance,
+}
+
+impl<S, T: Timestamp> Search<S, Separation, T> {
     /// Create a new search strategy.
     ///
     /// If `threshold` is provided, the search stops when the geometric novelty
     /// of a subsegment is less than `threshold` times the geometric novelty of
-    /// its parent segment.
+    /// its parent segment. For a stopping criterion in metres, see
+    /// [`Search::with_tolerance`].
     pub const fn new(strategy: S, threshold: Option<f64>) -> Self {
         Self {
             strategy,
+            endpoint_novelty: Separation,
             threshold,
+            tolerance: None,
+            time_decay: None,
         }
     }
 }
 
-/// Returns the geometric novelty scores for the start and end coordinates.
+impl<S, E, T: Timestamp> Search<S, E, T> {
+    /// Use a different heuristic for scoring the first and last coordinates.
+    ///
+    /// By default, both are scored by the distance between them (see
+    /// [`Separation`]).
+    ///
+    /// # Example
+    /// ```
+    /// use position_share::{endpoint_novelty::AlwaysIncludeLatest, rdp, Search};
+    ///
+    /// let search_strategy = Search::new(rdp, None).with_endpoint_novelty(AlwaysIncludeLatest);
+    /// # let _: &dyn position_share::SearchStrategy = &search_strategy;
+    /// ```
+    pub fn with_endpoint_novelty<E2>(self, endpoint_novelty: E2) -> Search<S, E2, T> {
+        let Self {
+            strategy,
+            endpoint_novelty: _,
+            threshold,
+            tolerance,
+            time_decay,
+        } = self;
+        Search {
+            strategy,
+            endpoint_novelty,
+            threshold,
+            tolerance,
+            time_decay,
+        }
+    }
+
+    /// Stop searching once every remaining segment is within `tolerance` (in
+    /// metres) of its chord.
+    ///
+    /// Unlike the `threshold` passed to [`Search::new`], which is relative to
+    /// the novelty of the selection so far, this is the maximum distance of
+    /// any unselected coordinate from the track through the selected ones (as
+    /// measured by the geometric novelty strategy). To select every coordinate
+    /// needed to meet the tolerance, with no cap on their number, see
+    /// [`Positions::simplify`](super::Positions::simplify).
+    ///
+    /// # Example
+    /// ```
+    /// use position_share::{rdp, Search};
+    ///
+    /// // Stop once every remaining segment is within 2 m of its chord
+    /// let search_strategy = Search::new(rdp, None).with_tolerance(2.0);
+    /// # let _: &dyn position_share::SearchStrategy = &search_strategy;
+    /// ```
+    #[must_use]
+    pub const fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = Some(tolerance);
+        self
+    }
+
+    /// Weight the novelty of each datum by its age.
+    ///
+    /// Older data is considered less novel, so that under bandwidth pressure
+    /// the most recent positions are preferred over historical detours.
+    #[must_use]
+    pub const fn with_time_decay(mut self, time_decay: TimeDecay<T>) -> Self {
+        self.time_decay = Some(time_decay);
+        self
+    }
+}
+
+/// A time-decay weighting applied to the novelty score.
+///
+/// The novelty of a datum is halved for every `half_life` that has elapsed
+/// between its timestamp and the reference time ('now'). By default the
+/// reference time is the timestamp of the most recent datum in the
+/// time-series. The half-life is a [`Timestamp::Duration`] of the timestamp
+/// type.
+///
+/// # Example
+#[cfg_attr(feature = "std", doc = "```")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// use chrono::{Duration, Utc};
+/// use position_share::{rdp, Coordinate, NodeId, Positions, Search, TimeDecay};
+///
+/// let mut positions = Positions::default();
+/// positions.add(Utc::now() - Duration::hours(3), Coordinate::new(0.0, 0.0, 0.0)).unwrap();
+/// positions.add(Utc::now() - Duration::hours(2), Coordinate::new(1.0, 5.0, 0.0)).unwrap();
+/// positions.add(Utc::now(), Coordinate::new(2.0, 0.0, 0.0)).unwrap();
+///
+/// let search_strategy = Search::new(rdp, None)
+///     .with_time_decay(TimeDecay::exponential(Duration::minutes(30)).relative_to(Utc::now()));
 ///
-/// The novelty score is the distance between them
-fn start_and_end_point_novelty(positions: &[&Datum]) -> (f64, f64) {
-    let start = positions.first().unwrap();
-    let end = positions.last().unwrap();
-    let distance = (start.coordinate - end.coordinate).magnitude();
+/// let most_novel = positions.most_novel_coordinates(&search_strategy, &NodeId::new_v4(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeDecay<T: Timestamp = DateTime<Utc>> {
+    half_life: T::Duration,
+    reference: Option<T>,
+}
+
+impl<T: Timestamp> TimeDecay<T> {
+    /// Creates an exponential time-decay weighting with the given half-life.
+    #[must_use]
+    pub const fn exponential(half_life: T::Duration) -> Self {
+        Self {
+            half_life,
+            reference: None,
+        }
+    }
 
-    (distance, distance)
+    /// Measure the age of each datum relative to `now`, rather than the most
+    /// recent datum.
+    #[must_use]
+    pub const fn relative_to(mut self, now: T) -> Self {
+        self.reference = Some(now);
+        self
+    }
+
+    /// Returns the weighting for a datum of the given age (in seconds),
+    /// between 0.0 and 1.0.
+    ///
+    /// Data from the 'future' (with a negative age) are not penalised.
+    fn weight(&self, age: f64) -> f64 {
+        if age <= 0.0 {
+            return 1.0;
+        }
+        crate::math::exp2(-age / T::duration_seconds(self.half_life))
+    }
 }
 
+/// A collection of the `n_max` most novel values.
+///
+/// Values which must be included (such as endpoints required by the
+/// [`EndpointNovelty`] heuristic) are kept separately, so they are only ever
+/// outranked by each other.
 #[derive(Debug)]
-struct Results<'a> {
+struct Results<T, V> {
     n_max: usize,
-    data: BTreeMap<Reverse<Novelty>, &'a Datum>,
+    // A min-heap, so the least novel value is evicted first
+    data: BinaryHeap<Reverse<Ranked<T, V>>>,
+    always_included: Vec<Ranked<T, V>>,
 }
 
-impl<'a> Results<'a> {
+impl<T: Ord, V> Results<T, V> {
     /// Creates a new `Results` struct with a maximum of `n_max` results.
-    const fn new(n_max: usize) -> Self {
+    fn new(n_max: usize) -> Self {
         Self {
             n_max,
-            data: BTreeMap::new(),
+            data: BinaryHeap::new(),
+            always_included: Vec::new(),
         }
     }
 
-    /// Inserts a new datum into the results, keeping only the `n_max` most
+    /// Removes all of the results, and sets a new maximum, keeping the
+    /// allocated memory for reuse.
+    fn reset(&mut self, n_max: usize) {
+        self.n_max = n_max;
+        self.data.clear();
+        self.always_included.clear();
+    }
+
+    /// Inserts a new value into the results, keeping only the `n_max` most
     /// novel results.
-    fn insert(&mut self, datum: &'a Datum, novelty: Novelty) {
+    ///
+    /// Returns the value which didn't make the cut, if any. This may either be
+    /// the inserted value, or the least novel of the existing values.
+    fn insert(&mut self, novelty: Novelty<T>, value: V) -> Option<(Novelty<T>, V)> {
         // There are less results than the maximum, so insert it with no further checks.
-        if self.data.len() < self.n_max {
-            self.data.insert(Reverse(novelty), datum);
-        // The results are full, so only insert the datum if it is more novel
+        if self.data.len() + self.always_included.len() < self.n_max {
+            self.data.push(Reverse(Ranked(novelty, value)));
+            return None;
+        }
+        // The results are full, so only insert the value if it is more novel
         // than the least novel result.
-        } else if let Some(min_novelty) = self.min_novelty() {
+        if let Some(min_novelty) = self.min_novelty() {
             if novelty > *min_novelty {
-                self.data.pop_last();
-                self.data.insert(Reverse(novelty), datum);
+                let evicted = self.data.pop();
+                self.data.push(Reverse(Ranked(novelty, value)));
+                return evicted.map(|Reverse(Ranked(novelty, value))| (novelty, value));
             }
         }
-        // Only reachable if n_max is 0, meaning we don't want any results.
+        // Either the value is not novel enough, every result must be included,
+        // or n_max is 0, meaning we don't want any results.
+        Some((novelty, value))
     }
 
-    /// Returns the novelty score of the least novel coordinate in the results
-    /// or 0.0 if the results are empty.
-    fn min_novelty(&self) -> Option<&Novelty> {
-        self.data
-            .keys()
-            .next_back()
-            .map(|reverse_novelty| &reverse_novelty.0)
+    /// Inserts a value which must be included, however novel the other
+    /// results are.
+    ///
+    /// Returns the value which didn't make the cut, if any. This is the least
+    /// novel of the other results, unless every result must be included, in
+    /// which case only the `n_max` most novel of those are kept.
+    fn insert_always(&mut self, novelty: Novelty<T>, value: V) -> Option<(Novelty<T>, V)> {
+        if self.always_included.len() < self.n_max {
+            self.always_included.push(Ranked(novelty, value));
+            if self.data.len() + self.always_included.len() > self.n_max {
+                return self
+                    .data
+                    .pop()
+                    .map(|Reverse(Ranked(novelty, value))| (novelty, value));
+            }
+            return None;
+        }
+        let least = self
+            .always_included
+            .iter_mut()
+            .min()
+            .filter(|least| novelty > least.0);
+        match least {
+            Some(least) => {
+                let Ranked(novelty, value) = core::mem::replace(least, Ranked(novelty, value));
+                Some((novelty, value))
+            }
+            None => Some((novelty, value)),
+        }
     }
-}
 
-impl<'a> IntoIterator for Results<'a> {
-    type IntoIter = std::collections::btree_map::IntoValues<Reverse<Novelty>, &'a Datum>;
-    type Item = &'a Datum;
+    /// Returns the novelty score of the least novel result, not counting
+    /// those which must be included, if there are any.
+    fn min_novelty(&self) -> Option<&Novelty<T>> {
+        self.data.peek().map(|Reverse(Ranked(novelty, _))| novelty)
+    }
 
-    /// Returns an iterator over the results.
+    /// Removes all of the results, passing each to `f`.
     ///
-    /// Ordering: most novel to least novel
-    fn into_iter(self) -> Self::IntoIter {
-        self.data.into_values()
+    /// Ordering: most novel to least novel, including those which must be
+    /// included
+    fn drain_sorted(&mut self, mut f: impl FnMut(Novelty<T>, V)) {
+        self.always_included.sort_unstable_by(|a, b| b.cmp(a));
+        let mut sorted = core::mem::take(&mut self.data).into_sorted_vec();
+        {
+            // Both are sorted, so merge them
+            let mut always_included = self.always_included.drain(..).peekable();
+            #[allow(clippy::iter_with_drain)] // keeps the allocation for reuse
+            let mut rest = sorted.drain(..).map(|Reverse(ranked)| ranked).peekable();
+            loop {
+                let next = match (always_included.peek(), rest.peek()) {
+                    (Some(a), Some(b)) if a < b => rest.next(),
+                    (Some(_), _) => always_included.next(),
+                    (None, _) => rest.next(),
+                };
+                let Some(Ranked(novelty, value)) = next else {
+                    break;
+                };
+                f(novelty, value);
+            }
+        }
+        self.data = BinaryHeap::from(sorted);
+    }
+}
+
+/// A value, ranked by its novelty alone.
+#[derive(Debug)]
+struct Ranked<T, V>(Novelty<T>, V);
+
+impl<T: Ord, V> Ord for Ranked<T, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<T: Ord, V> PartialOrd for Ranked<T, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord, V> PartialEq for Ranked<T, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
     }
 }
 
-use std::cmp::Ordering;
+impl<T: Ord, V> Eq for Ranked<T, V> {}
+
+use core::cmp::Ordering;
 
 use uuid::Uuid;
 
-#[derive(Debug, PartialEq)]
-pub struct Novelty {
+/// The novelty of a data point, and its constituent parts.
+///
+/// Of two equally novel data points, the most recent is preferred; IDs only
+/// break ties between data points recorded at the same time. The same rule
+/// picks the most novel point within a segment, and the segment searched
+/// next, so a search never depends on the order in which ties are found.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Novelty<T = DateTime<Utc>> {
+    /// The geometric novelty of the data point.
     pub distance: f64,
+    /// The probability that the recipient does not already have the data
+    /// point.
     pub probability_not_transmitted: Probability,
+    /// A weighting between 0.0 and 1.0 which favours more recent data.
+    pub recency: f64,
+    /// The recursion depth at which the data point was found.
+    ///
+    /// The start and end points are at depth 0.
+    pub depth: usize,
+    /// The time at which the data point was recorded.
+    pub timestamp: T,
+    /// The ID of the data point.
     pub id: Uuid,
 }
 
-impl Novelty {
+impl<T> Novelty<T> {
+    /// The combined novelty score, used to rank data points.
     #[must_use]
     pub fn score(&self) -> f64 {
-        self.distance * self.probability_not_transmitted
+        self.distance * self.probability_not_transmitted * self.recency
     }
 }
 
-impl Ord for Novelty {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+/// Data points are ranked by their score. Ties are broken deterministically,
+/// in favour of the data point the recipient is least likely to have, then the
+/// most recent, and finally by ID.
+impl<T: Ord> Ord for Novelty<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
         self.score()
-            .partial_cmp(&other.score())
-            .unwrap_or(Ordering::Equal)
+            .total_cmp(&other.score())
             .then_with(|| {
                 self.probability_not_transmitted
                     .cmp(&other.probability_not_transmitted)
             })
+            .then_with(|| self.timestamp.cmp(&other.timestamp))
             .then_with(|| self.id.cmp(&other.id))
     }
 }
 
-impl PartialOrd for Novelty {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+impl<T: Ord> PartialOrd for Novelty<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Eq for Novelty {}
+impl<T: Ord> Eq for Novelty<T> {}
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
+    use chrono::{Duration, Utc};
+    use float_cmp::assert_approx_eq;
+
+    use crate::{positions::Positions, rdp, Coordinate};
+
     #[test]
     fn compare() {
         let a = Novelty {
             distance: 2.0,
             probability_not_transmitted: Probability::ONE_HUNDRED,
+            recency: 1.0,
+            depth: 1,
+            timestamp: Utc::now(),
             id: Uuid::new_v4(),
         };
         let b = Novelty {
             distance: 1.0,
             probability_not_transmitted: Probability::ONE_HUNDRED,
+            recency: 1.0,
+            depth: 1,
+            timestamp: Utc::now(),
             id: Uuid::new_v4(),
         };
         assert!(a > b);
     }
+
+    #[test]
+    fn explain() {
+        // Coordinate arrangement:
+        //
+        //     y
+        //     ^
+        //   2 |           * (2,2)
+        //     |
+        //   1 |     * (1,1)     * (3,1)
+        //     |
+        //   0 * (0,0)                 * (4,0)
+        //     +-----|-----|-----|-----|---->
+        //     0     1     2     3     4     x
+        let mut positions = Positions::default();
+        let ids: Vec<_> = [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 1.0), (4.0, 0.0)]
+            .into_iter()
+            .map(|(x, y)| {
+                positions
+                    .add(Utc::now(), Coordinate::new(x, y, 0.0))
+                    .unwrap()
+            })
+            .collect();
+
+        let explanation = positions.explain_most_novel_coordinates(
+            &Search::new(rdp, None),
+            &NodeId::new_v4(),
+            3,
+            5,
+        );
+
+        // The endpoints are selected first, followed by the peak
+        let selected: Vec<_> = explanation
+            .selected
+            .iter()
+            .map(|candidate| (candidate.datum.id, candidate.novelty.depth))
+            .collect();
+        assert_eq!(selected.len(), 3);
+        assert!(selected[..2].contains(&(ids[0], 0)));
+        assert!(selected[..2].contains(&(ids[4], 0)));
+        assert_eq!(selected[2], (ids[2], 1));
+
+        // The remaining points lie on the simplified path, so aren't novel at all
+        assert_eq!(explanation.rejected.len(), 2);
+        for (candidate, reason) in &explanation.rejected {
+            assert!([ids[1], ids[3]].contains(&candidate.datum.id));
+            assert_eq!(candidate.novelty.depth, 2);
+            assert_eq!(*reason, Rejection::Outranked);
+        }
+    }
+
+    #[test]
+    fn tolerance() {
+        // A detour of 3 m, from which the track returns almost straight
+        let mut positions = Positions::default();
+        let start = Utc::now();
+        let ids: Vec<_> = [(0.0, 0.0), (5.0, 3.0), (7.5, 2.5), (10.0, 0.0), (20.0, 0.0)]
+            .into_iter()
+            .zip(0..)
+            .map(|((x, y), seconds)| {
+                positions
+                    .add(
+                        start + Duration::seconds(seconds),
+                        Coordinate::new(x, y, 0.0),
+                    )
+                    .unwrap()
+            })
+            .collect();
+
+        let explanation = positions.explain_most_novel_coordinates(
+            &Search::new(rdp, None).with_tolerance(2.0),
+            &NodeId::new_v4(),
+            usize::MAX,
+            5,
+        );
+
+        let mut selected: Vec<_> = explanation
+            .selected
+            .iter()
+            .map(|candidate| candidate.datum.id)
+            .collect();
+        selected.sort_unstable();
+        let mut expected = vec![ids[0], ids[1], ids[4]];
+        expected.sort_unstable();
+        assert_eq!(selected, expected);
+
+        // The rest of the track is within 2 m of the chord from the detour, so
+        // the search stops
+        assert_eq!(explanation.rejected.len(), 1);
+        let (candidate, reason) = &explanation.rejected[0];
+        assert_eq!(candidate.datum.id, ids[3]);
+        assert_eq!(*reason, Rejection::WithinTolerance);
+    }
+
+    #[test]
+    fn time_decay_weight() {
+        let time_decay = TimeDecay::<u64>::exponential(core::time::Duration::from_secs(600));
+        assert!((time_decay.weight(0.0) - 1.0).abs() < f64::EPSILON);
+        assert!((time_decay.weight(1200.0) - 0.25).abs() < f64::EPSILON);
+        assert!((time_decay.weight(-300.0) - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn latest_fix_wins_under_bandwidth_pressure() {
+        // A large detour three hours ago, and a small deviation just now.
+        //
+        //     y
+        //     ^
+        //  20 |     * (1,20) 3 hours ago
+        //     |
+        //   1 |                      * (9,1) now
+        //   0 *--------------------------* (10,0)
+        //     +-----|-----|-----|-----|---->
+        let start = Utc::now() - Duration::hours(4);
+        let mut positions = Positions::default();
+        positions
+            .add(start, Coordinate::new(0.0, 0.0, 0.0))
+            .unwrap();
+        let detour = positions
+            .add(start + Duration::hours(1), Coordinate::new(1.0, 20.0, 0.0))
+            .unwrap();
+        positions
+            .add(start + Duration::hours(2), Coordinate::new(2.0, 0.0, 0.0))
+            .unwrap();
+        let turn = positions
+            .add(start + Duration::hours(4), Coordinate::new(9.0, 1.0, 0.0))
+            .unwrap();
+        let latest = positions
+            .add(
+                start + Duration::hours(4) + Duration::minutes(1),
+                Coordinate::new(10.0, 0.0, 0.0),
+            )
+            .unwrap();
+        let recipient = NodeId::new_v4();
+
+        // Without time-decay the historical detour is the most novel point.
+        let undecayed = positions.most_novel_coordinates(&Search::new(rdp, None), &recipient, 1);
+        assert_eq!(undecayed.len(), 1);
+        assert_eq!(undecayed[0].id, detour);
+
+        // With time-decay the latest fix wins when only one point can be sent...
+        let search =
+            Search::new(rdp, None).with_time_decay(TimeDecay::exponential(Duration::minutes(30)));
+        let decayed = positions.most_novel_coordinates(&search, &recipient, 1);
+        assert_eq!(decayed[0].id, latest);
+
+        // ...and the recent turn is preferred over the historical detour.
+        let decayed = positions.most_novel_coordinates(&search, &recipient, 2);
+        assert!(decayed.iter().any(|datum| datum.id == turn));
+        assert!(decayed.iter().all(|datum| datum.id != detour));
+    }
+
+    #[test]
+    fn loiter() {
+        // A track which departs, loiters in one place, and returns to where it
+        // started, so that the endpoints (and many other points) coincide.
+        let start = Utc::now() - Duration::minutes(10);
+        let mut positions = Positions::default();
+        for (minutes, (x, y)) in (0..).zip([
+            (0.0, 0.0),
+            (5.0, 0.0),
+            (5.0, 0.0),
+            (5.0, 0.0),
+            (5.0, 0.0),
+            (0.0, 0.0),
+        ]) {
+            positions
+                .add(
+                    start + Duration::minutes(minutes),
+                    Coordinate::new(x, y, 0.0),
+                )
+                .unwrap();
+        }
+
+        let explanation = positions.explain_most_novel_coordinates(
+            &Search::new(rdp, None),
+            &NodeId::new_v4(),
+            6,
+            6,
+        );
+
+        let candidates = explanation
+            .selected
+            .iter()
+            .chain(explanation.rejected.iter().map(|(candidate, _)| candidate));
+        for candidate in candidates {
+            assert!(candidate.novelty.score().is_finite());
+        }
+        // The coincident endpoints have no separation, so the most novel
+        // point is where the track turns back.
+        let most_novel = &explanation.selected[0];
+        assert_approx_eq!(f64, most_novel.novelty.score(), 5.0);
+        assert_approx_eq!(f64, most_novel.datum.coordinate.x, 5.0);
+    }
+
+    #[test]
+    fn always_included_are_ordered_by_novelty() {
+        use crate::endpoint_novelty::AlwaysIncludeLatest;
+
+        // A loiter track, whose latest position must be selected even though
+        // its endpoints are close together, so score poorly
+        let start = Utc::now();
+        let mut positions = Positions::default();
+        for (i, (x, y)) in (0..).zip([(0.0, 0.0), (1.0, 2.0), (2.0, 2.0), (3.0, 0.0), (0.0, 0.1)]) {
+            positions
+                .add(start + Duration::seconds(i), Coordinate::new(x, y, 0.0))
+                .unwrap();
+        }
+        let latest = positions.iter().last().unwrap().id;
+        let search = Search::new(rdp, None).with_endpoint_novelty(AlwaysIncludeLatest);
+
+        let explanation =
+            positions.explain_most_novel_coordinates(&search, &NodeId::new_v4(), 3, 0);
+
+        let selected = &explanation.selected;
+        assert_eq!(selected.len(), 3);
+        assert_eq!(selected[2].datum.id, latest);
+        for pair in selected.windows(2) {
+            assert!(pair[0].novelty >= pair[1].novelty);
+        }
+    }
+
+    #[test]
+    fn appending_only_searches_new_segments() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct CountingRdp(AtomicUsize);
+
+        impl GeometricNovelty for CountingRdp {
+            fn most_novel_coordinate<'a>(
+                &self,
+                segment: &[&'a Datum],
+            ) -> Option<(&'a Datum, f64, usize)> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                rdp(segment)
+            }
+
+            fn cache_key(&self) -> Option<u64> {
+                Some(0)
+            }
+        }
+
+        let search = Search::new(CountingRdp(AtomicUsize::new(0)), None);
+        let mut context = SearchContext::new();
+        let recipient = NodeId::new_v4();
+
+        let mut positions = Positions::default();
+        let start = Utc::now();
+        let add = |positions: &mut Positions, i: i32| {
+            let x = f64::from(i);
+            positions
+                .add(
+                    start + chrono::Duration::seconds(i.into()),
+                    Coordinate::new(x, (x / 3.0).sin() * 10.0, 0.0),
+                )
+                .unwrap();
+        };
+        for i in 0..200 {
+            add(&mut positions, i);
+        }
+        let _ =
+            positions.most_novel_coordinates_with_context(&mut context, &search, &recipient, 50);
+        let first = search.strategy.0.swap(0, Ordering::Relaxed);
+
+        add(&mut positions, 200);
+        let selected: Vec<_> = positions
+            .most_novel_coordinates_with_context(&mut context, &search, &recipient, 50)
+            .iter()
+            .map(|datum| datum.id)
+            .collect();
+        let second = search.strategy.0.load(Ordering::Relaxed);
+
+        // Only the segments along the right-hand edge of the decomposition
+        // include the new point
+        assert!(second * 4 < first, "{second} of {first} segments searched");
+        let expected: Vec<_> = positions
+            .most_novel_coordinates(&search, &recipient, 50)
+            .iter()
+            .map(|datum| datum.id)
+            .collect();
+        assert_eq!(selected, expected);
+    }
+
+    #[test]
+    fn contexts_are_bound_to_their_collection() {
+        use crate::IdGenerator;
+
+        // Both collections have the same IDs, and the same number of data
+        // points, but different tracks
+        let track = |amplitude: f64| {
+            let mut positions = Positions::with_id_generator(IdGenerator::sequential(0));
+            let start = Utc::now();
+            for i in 0..20 {
+                let x = f64::from(i);
+                positions
+                    .add(
+                        start + Duration::seconds(i.into()),
+                        Coordinate::new(x, (x * amplitude).sin() * 10.0, 0.0),
+                    )
+                    .unwrap();
+            }
+            positions
+        };
+        let search = Search::new(rdp, None);
+        let mut context = SearchContext::new();
+        let recipient = NodeId::new_v4();
+
+        // A clone is a separate collection too
+        let first = track(1.0);
+        for positions in [first.clone(), track(0.3), first] {
+            let selected: Vec<_> = positions
+                .most_novel_coordinates_with_context(&mut context, &search, &recipient, 5)
+                .iter()
+                .map(|datum| datum.coordinate)
+                .collect();
+            let expected: Vec<_> = positions
+                .most_novel_coordinates(&search, &recipient, 5)
+                .iter()
+                .map(|datum| datum.coordinate)
+                .collect();
+            assert_eq!(selected, expected);
+        }
+    }
+
+    #[test]
+    fn contexts_are_bound_to_their_strategy() {
+        use crate::RdpWithPayload;
+
+        type Strategy = for<'a> fn(&[&'a Datum<f64>]) -> Option<(&'a Datum<f64>, f64, usize)>;
+
+        /// Weights the payload by a parameter.
+        struct PayloadWeight(f64);
+
+        impl GeometricNovelty<f64> for PayloadWeight {
+            fn most_novel_coordinate<'a>(
+                &self,
+                segment: &[&'a Datum<f64>],
+            ) -> Option<(&'a Datum<f64>, f64, usize)> {
+                RdpWithPayload::new(|_: &f64, payload: &f64, _: &f64| self.0 * payload)
+                    .most_novel_coordinate(segment)
+            }
+
+            fn cache_key(&self) -> Option<u64> {
+                Some(self.0.to_bits())
+            }
+        }
+
+        /// Searches with `first` and then `second`, returning whether the
+        /// payload stood out in the second search.
+        fn switch<A, B>(
+            context: &mut SearchContext,
+            positions: &Positions<f64>,
+            first: A,
+            second: B,
+        ) -> bool
+        where
+            A: GeometricNovelty<f64> + MaybeSync + 'static,
+            B: GeometricNovelty<f64> + MaybeSync + 'static,
+        {
+            let recipient = NodeId::new_v4();
+            let first = Search::new(first, None);
+            let _ = positions.most_novel_coordinates_with_context(context, &first, &recipient, 3);
+            positions
+                .most_novel_coordinates_with_context(
+                    context,
+                    &Search::new(second, None),
+                    &recipient,
+                    3,
+                )
+                .iter()
+                .any(|datum| datum.payload > 50.0)
+        }
+
+        // A straight line, where only the payload of the middle point stands
+        // out
+        let mut positions = Positions::default();
+        let start = Utc::now();
+        for i in 0..9 {
+            let x = f64::from(i);
+            let payload = if i == 4 { 100.0 } else { 0.0 };
+            positions
+                .add_with_payload(
+                    start + Duration::seconds(i.into()),
+                    Coordinate::new(x, x, 0.0),
+                    payload,
+                )
+                .unwrap();
+        }
+        let mut context = SearchContext::new();
+
+        // Strategies of different types
+        let with_payload = RdpWithPayload::new(|_: &f64, payload: &f64, _: &f64| *payload);
+        assert!(switch(&mut context, &positions, rdp, with_payload));
+
+        // Function pointers, which have no key
+        let payload_only: Strategy = |segment| {
+            let (index, datum) = segment[1..segment.len() - 1]
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.payload.total_cmp(&b.1.payload))?;
+            Some((*datum, datum.payload, index + 1))
+        };
+        assert!(switch(
+            &mut context,
+            &positions,
+            rdp as Strategy,
+            payload_only
+        ));
+
+        // Strategies of the same type, with different keys
+        assert!(switch(
+            &mut context,
+            &positions,
+            PayloadWeight(0.0),
+            PayloadWeight(1.0)
+        ));
+    }
+
+    #[test]
+    fn segment_index_is_extended_as_data_is_appended() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::{HullRdp, SegmentIndex};
+
+        #[derive(Default)]
+        struct CountingHullRdp {
+            built: AtomicUsize,
+            extended: AtomicUsize,
+        }
+
+        impl GeometricNovelty for CountingHullRdp {
+            fn most_novel_coordinate<'a>(
+                &self,
+                segment: &[&'a Datum],
+            ) -> Option<(&'a Datum, f64, usize)> {
+                HullRdp.most_novel_coordinate(segment)
+            }
+
+            fn index(&self, history: &[&Datum]) -> Option<Box<dyn SegmentIndex>> {
+                self.built.fetch_add(1, Ordering::Relaxed);
+                HullRdp.index(history)
+            }
+
+            fn extend_index(&self, index: &mut dyn SegmentIndex, history: &[&Datum]) -> bool {
+                self.extended.fetch_add(1, Ordering::Relaxed);
+                HullRdp.extend_index(index, history)
+            }
+
+            fn cache_key(&self) -> Option<u64> {
+                Some(0)
+            }
+        }
+
+        let search = Search::new(CountingHullRdp::default(), None);
+        let mut context = SearchContext::new();
+        let recipient = NodeId::new_v4();
+        let mut positions = Positions::default();
+        let start = Utc::now();
+        let add = |positions: &mut Positions, milliseconds: i32| {
+            let x = f64::from(milliseconds) / 1000.0;
+            positions
+                .add(
+                    start + Duration::milliseconds(milliseconds.into()),
+                    Coordinate::new(x, x.sin(), 0.0),
+                )
+                .unwrap();
+        };
+        let mut search_and_count = |positions: &Positions| {
+            let selected: Vec<_> = positions
+                .most_novel_coordinates_with_context(&mut context, &search, &recipient, 10)
+                .iter()
+                .map(|datum| datum.id)
+                .collect();
+            let expected: Vec<_> = positions
+                .most_novel_coordinates(&Search::new(rdp, None), &recipient, 10)
+                .iter()
+                .map(|datum| datum.id)
+                .collect();
+            assert_eq!(selected, expected);
+            (
+                search.strategy.built.load(Ordering::Relaxed),
+                search.strategy.extended.load(Ordering::Relaxed),
+            )
+        };
+        for i in 0..30 {
+            add(&mut positions, i * 1000);
+        }
+
+        // Unchanged data is indexed once
+        for _ in 0..3 {
+            assert_eq!(search_and_count(&positions), (1, 0));
+        }
+
+        // Appended data extends the index
+        for i in 30..40 {
+            add(&mut positions, i * 1000);
+        }
+        assert_eq!(search_and_count(&positions), (1, 1));
+
+        // But data added anywhere else means rebuilding it
+        add(&mut positions, 10_500);
+        assert_eq!(search_and_count(&positions), (2, 1));
+    }
+
+    mod properties {
+        use std::collections::HashSet;
+
+        use chrono::{DateTime, Duration};
+        use proptest::prelude::*;
+        use uuid::Uuid;
+
+        use super::*;
+        use crate::endpoint_novelty::AlwaysIncludeEndpoints;
+
+        /// A track of up to 40 positions, each with the probability that the
+        /// recipient already has it.
+        fn track() -> impl Strategy<Value = Vec<((f64, f64, f64), u32)>> {
+            let coordinate = (-100.0..100.0, -100.0..100.0, -10.0..10.0);
+            let probability = prop_oneof![Just(0), Just(u32::MAX), any::<u32>()];
+            prop::collection::vec((coordinate, probability), 0..40)
+        }
+
+        fn positions(track: &[((f64, f64, f64), u32)], recipient: NodeId) -> Positions {
+            let start = DateTime::UNIX_EPOCH;
+            let mut positions = Positions::default();
+            for (seconds, &((x, y, z), probability)) in (0..).zip(track) {
+                let id = positions
+                    .add(start + Duration::seconds(seconds), Coordinate::new(x, y, z))
+                    .unwrap();
+                positions.transmission_history_mut().record(
+                    recipient,
+                    id,
+                    Probability::new(probability),
+                );
+            }
+            positions
+        }
+
+        fn ids(data: &[&Datum]) -> HashSet<Uuid> {
+            data.iter().map(|datum| datum.id).collect()
+        }
+
+        fn transmitted(positions: &Positions, recipient: &NodeId, datum: &Datum) -> bool {
+            positions
+                .transmission_history()
+                .probability_recipient_has_datum(recipient, &datum.id)
+                == Probability::ONE_HUNDRED
+        }
+
+        proptest! {
+            #[test]
+            fn selection_is_bounded_and_unique(track in track(), n_max in 0..50usize) {
+                let recipient = NodeId::new_v4();
+                let positions = positions(&track, recipient);
+
+                let selected = positions.most_novel_coordinates(&Search::new(rdp, None), &recipient, n_max);
+
+                prop_assert!(selected.len() <= n_max);
+                prop_assert_eq!(ids(&selected).len(), selected.len());
+            }
+
+            #[test]
+            fn transmitted_data_is_never_selected(track in track(), n_max in 0..50usize) {
+                let recipient = NodeId::new_v4();
+                let positions = positions(&track, recipient);
+
+                for selected in [
+                    positions.most_novel_coordinates(&Search::new(rdp, None), &recipient, n_max),
+                    positions.most_novel_coordinates(
+                        &Search::new(rdp, None).with_endpoint_novelty(AlwaysIncludeEndpoints),
+                        &recipient,
+                        n_max,
+                    ),
+                ] {
+                    prop_assert!(selected.iter().all(|datum| !transmitted(&positions, &recipient, datum)));
+                }
+            }
+
+            #[test]
+            fn simplification_is_within_tolerance(track in track(), tolerance in 0.0..50.0) {
+                let recipient = NodeId::new_v4();
+                let positions = positions(&track, recipient);
+                let search = Search::new(rdp, None).with_tolerance(tolerance);
+
+                let simplified = positions.simplify(&search, &recipient);
+
+                prop_assert!(simplified.windows(2).all(|pair| pair[0] < pair[1]));
+                prop_assert!(simplified.iter().all(|datum| !transmitted(&positions, &recipient, datum)));
+                // The recipient's track includes the data it already has
+                let simplified = ids(&simplified);
+                let data: Vec<_> = positions.iter().collect();
+                let kept: Vec<_> = (0..data.len())
+                    .filter(|&i| {
+                        simplified.contains(&data[i].id) || transmitted(&positions, &recipient, data[i])
+                    })
+                    .collect();
+                if !data.is_empty() {
+                    prop_assert_eq!(kept.first(), Some(&0));
+                    prop_assert_eq!(kept.last(), Some(&(data.len() - 1)));
+                }
+                for pair in kept.windows(2) {
+                    if let Some((_, distance, _)) = rdp(&data[pair[0]..=pair[1]]) {
+                        prop_assert!(distance <= tolerance);
+                    }
+                }
+            }
+
+            #[test]
+            fn simplification_is_minimal(
+                coordinates in prop::collection::vec((-100.0..100.0, -100.0..100.0, -10.0..10.0), 0..10),
+                tolerance in 0.0..50.0,
+            ) {
+                // The recipient has none of the data
+                let track: Vec<_> = coordinates.into_iter().map(|coordinate| (coordinate, 0)).collect();
+                let positions = positions(&track, NodeId::new_v4());
+                let search = Search::new(rdp, None).with_tolerance(tolerance);
+                let data: Vec<_> = positions.iter().collect();
+
+                // Try every selection of interior data points
+                let within_tolerance = |kept: &[usize]| {
+                    kept.windows(2).all(|pair| {
+                        rdp(&data[pair[0]..=pair[1]]).map_or(true, |(_, distance, _)| distance <= tolerance)
+                    })
+                };
+                let interior = data.len().saturating_sub(2);
+                let fewest = (0_u32..1 << interior)
+                    .map(|mask| {
+                        (0..data.len())
+                            .filter(|&i| i == 0 || i == data.len() - 1 || mask & 1 << (i - 1) != 0)
+                            .collect::<Vec<_>>()
+                    })
+                    .filter(|kept| within_tolerance(kept))
+                    .map(|kept| kept.len())
+                    .min()
+                    .unwrap_or(0);
+
+                let simplified = positions.simplify(&search, &NodeId::new_v4());
+                prop_assert_eq!(simplified.len(), fewest);
+                let greedy = positions.most_novel_coordinates(&search, &NodeId::new_v4(), usize::MAX);
+                prop_assert!(simplified.len() <= greedy.len());
+            }
+
+            #[test]
+            fn endpoints_are_selected(track in track(), n_max in 2..50usize) {
+                let recipient = NodeId::new_v4();
+                let positions = positions(&track, recipient);
+                let search = Search::new(rdp, None).with_endpoint_novelty(AlwaysIncludeEndpoints);
+
+                let selected = ids(&positions.most_novel_coordinates(&search, &recipient, n_max));
+
+                for datum in [positions.iter().next(), positions.iter().last()].into_iter().flatten() {
+                    if !transmitted(&positions, &recipient, datum) {
+                        prop_assert!(selected.contains(&datum.id));
+                    }
+                }
+            }
+
+            #[test]
+            fn selection_grows_with_n_max(track in track(), n_max in 0..50usize) {
+                let recipient = NodeId::new_v4();
+                let positions = positions(&track, recipient);
+                let search = Search::new(rdp, None);
+
+                let smaller = ids(&positions.most_novel_coordinates(&search, &recipient, n_max));
+                let larger = ids(&positions.most_novel_coordinates(&search, &recipient, n_max + 1));
+
+                prop_assert!(smaller.is_subset(&larger));
+            }
+
+            #[test]
+            fn reused_context_matches_explain(
+                tracks in prop::collection::vec(track(), 1..4),
+                n_max in 0..50usize,
+            ) {
+                let recipient = NodeId::new_v4();
+                let search = Search::new(rdp, Some(0.4));
+                let mut context = SearchContext::new();
+
+                for track in tracks {
+                    let positions = positions(&track, recipient);
+                    let selected: Vec<Uuid> = positions
+                        .most_novel_coordinates_with_context(&mut context, &search, &recipient, n_max)
+                        .iter()
+                        .map(|datum| datum.id)
+                        .collect();
+                    let explained: Vec<Uuid> = positions
+                        .explain_most_novel_coordinates(&search, &recipient, n_max, 0)
+                        .selected
+                        .iter()
+                        .map(|candidate| candidate.datum.id)
+                        .collect();
+
+                    prop_assert_eq!(selected, explained);
+                }
+            }
+
+            #[test]
+            fn cached_segments_match_explain(
+                track in track(),
+                acknowledged in prop::collection::vec(any::<prop::sample::Index>(), 0..40),
+                n_max in 0..50usize,
+            ) {
+                let recipient = NodeId::new_v4();
+                let search = Search::new(rdp, Some(0.4));
+                let mut context = SearchContext::new();
+                let mut positions = Positions::default();
+                let mut ids = Vec::new();
+                let start = DateTime::UNIX_EPOCH;
+
+                for ((seconds, &((x, y, z), _)), acknowledged) in
+                    (0..).zip(&track).zip(acknowledged.iter().map(Some).chain(core::iter::repeat(None)))
+                {
+                    ids.push(
+                        positions
+                            .add(start + Duration::seconds(seconds), Coordinate::new(x, y, z))
+                            .unwrap(),
+                    );
+                    // Probabilities change between searches too
+                    if let Some(acknowledged) = acknowledged {
+                        positions.transmission_history_mut().record(
+                            recipient,
+                            *acknowledged.get(&ids),
+                            Probability::ONE_HUNDRED,
+                        );
+                    }
+
+                    let selected: Vec<Uuid> = positions
+                        .most_novel_coordinates_with_context(&mut context, &search, &recipient, n_max)
+                        .iter()
+                        .map(|datum| datum.id)
+                        .collect();
+                    let explained: Vec<Uuid> = positions
+                        .explain_most_novel_coordinates(&search, &recipient, n_max, 0)
+                        .selected
+                        .iter()
+                        .map(|candidate| candidate.datum.id)
+                        .collect();
+
+                    prop_assert_eq!(selected, explained);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,507 @@
+//! An optimal (rather than greedy) selection, for when there's time to find
+//! it.
+
+use alloc::{vec, vec::Vec};
+
+use super::{Search, SearchStrategy};
+use crate::{
+    coordinate::Point,
+    endpoint_novelty::AlwaysIncludeEndpoints,
+    parallel::MaybeSync,
+    positions::{
+        geometric_novelty::{distance_from_line, rdp},
+        Datum,
+    },
+    probability::Probability,
+    timestamp::Timestamp,
+    transmission_history::TransmissionHistory,
+    NodeId,
+};
+
+/// What an [`Optimal`] selection minimises.
+///
+/// The error of each data point which isn't selected is its distance from
+/// the line joining the selected data points either side of it (as with
+/// [`rdp`](crate::rdp)).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Objective {
+    /// The greatest error of any data point.
+    #[default]
+    MaxError,
+    /// The sum of the errors of every data point.
+    TotalError,
+}
+
+impl Objective {
+    /// Combines the error of part of the track with the error of the next
+    /// segment.
+    fn combine(self, error: f64, segment: f64) -> f64 {
+        match self {
+            Self::MaxError => error.max(segment),
+            Self::TotalError => error + segment,
+        }
+    }
+
+    /// Returns the error a segment must be less than to improve on `error`
+    /// when combined with `error_via`.
+    fn margin(self, error_via: f64, error: f64) -> f64 {
+        match self {
+            Self::MaxError if error_via < error => error,
+            Self::MaxError => f64::NEG_INFINITY,
+            Self::TotalError => error - error_via,
+        }
+    }
+
+    /// Returns the error of the interior data points of a segment, or `None`
+    /// if it's more than `limit`.
+    ///
+    /// The error is accumulated one data point at a time, so a segment is
+    /// only measured until it exceeds the limit.
+    fn segment_error<P, T, C: Point>(self, segment: &[&Datum<P, T, C>], limit: f64) -> Option<f64> {
+        let (Some(start), Some(end)) = (segment.first(), segment.last()) else {
+            return Some(0.0);
+        };
+        let mut error = 0.0;
+        for datum in &segment[1..segment.len() - 1] {
+            let distance =
+                distance_from_line(&start.coordinate, &end.coordinate, &datum.coordinate);
+            error = self.combine(error, distance);
+            if error > limit {
+                return None;
+            }
+        }
+        Some(error)
+    }
+}
+
+/// A search strategy which selects the data points which together describe
+/// the track best.
+///
+/// [`Search`](super::Search) greedily selects the most novel data point one at
+/// a time, which isn't optimal for a fixed number of points. This strategy
+/// instead finds the selection of at most `n_max` data points which minimises
+/// the [`Objective`], by dynamic programming over the 'shortcut graph' of
+/// segments between every pair of data points (after Imai and Iri).
+///
+/// The first and last data points are always selected (unless `n_max` is
+/// less than 2, when only the last is). Data points which the recipient
+/// certainly has are part of the track it already knows, so are kept in the
+/// track without counting towards `n_max`, and are never selected.
+///
+/// The selection is returned in time order. Finding it takes O(n³ + n² ×
+/// `n_max`) time at worst and O(n × `n_max`) memory, so this is intended for
+/// offline use, such as uploading a whole mission after recovery, rather than
+/// for searching long tracks in real time. Segments are only measured until
+/// they can't improve the selection, so a search of 1000 data points
+/// typically takes around a second. Longer tracks are searched optimally too,
+/// however long it takes, unless a greedy fallback is chosen with
+/// [`Optimal::with_greedy_fallback`].
+///
+/// # Example
+#[cfg_attr(feature = "std", doc = "```")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// use chrono::{Duration, Utc};
+/// use position_share::{Coordinate, NodeId, Objective, Optimal, Positions};
+///
+/// let mut positions = Positions::default();
+/// let start = Utc::now();
+/// for i in 0..50 {
+///     let x = f64::from(i);
+///     positions
+///         .add(start + Duration::seconds(i.into()), Coordinate::new(x, (x / 5.0).sin(), 0.0))
+///         .unwrap();
+/// }
+///
+/// let optimal = Optimal::new(Objective::MaxError);
+/// let selected = positions.most_novel_coordinates(&optimal, &NodeId::new_v4(), 10);
+/// assert_eq!(selected.len(), 10);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Optimal {
+    objective: Objective,
+    max_len: Option<usize>,
+}
+
+impl Optimal {
+    /// Creates a new optimal search strategy, which minimises `objective`.
+    #[must_use]
+    pub const fn new(objective: Objective) -> Self {
+        Self {
+            objective,
+            max_len: None,
+        }
+    }
+
+    /// Searches tracks of more than `max_len` data points greedily instead,
+    /// as by a [`Search`] using [`rdp`] and [`AlwaysIncludeEndpoints`], so
+    /// that long tracks don't take too long.
+    ///
+    /// # Example
+    /// ```
+    /// use position_share::{Objective, Optimal};
+    ///
+    /// let optimal = Optimal::new(Objective::MaxError).with_greedy_fallback(1000);
+    /// # let _: &dyn position_share::SearchStrategy = &optimal;
+    /// ```
+    #[must_use]
+    pub const fn with_greedy_fallback(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+}
+
+impl<P, T, C> SearchStrategy<P, T, C> for Optimal
+where
+    P: MaybeSync,
+    T: Timestamp + MaybeSync,
+    C: Point,
+{
+    fn search<'a>(
+        &self,
+        transmission_history: &TransmissionHistory,
+        positions: &[&'a Datum<P, T, C>],
+        n_max: usize,
+        recipient: &NodeId,
+    ) -> Vec<&'a Datum<P, T, C>> {
+        if self
+            .max_len
+            .is_some_and(|max_len| positions.len() > max_len)
+        {
+            let greedy = Search::new(rdp, None).with_endpoint_novelty(AlwaysIncludeEndpoints);
+            let mut selected = greedy.search(transmission_history, positions, n_max, recipient);
+            selected.sort();
+            return selected;
+        }
+
+        // The number of data points each counts towards `n_max`
+        let cost: Vec<usize> = positions
+            .iter()
+            .map(|datum| {
+                usize::from(
+                    transmission_history.probability_recipient_has_datum(recipient, &datum.id)
+                        < Probability::ONE_HUNDRED,
+                )
+            })
+            .collect();
+        let (Some(&first), Some(&last)) = (cost.first(), cost.last()) else {
+            return Vec::new();
+        };
+        let end = positions.len() - 1;
+        if end == 0 || n_max < first + last {
+            // There's no room for both ends, so prefer the most recent (if the
+            // recipient doesn't already have it)
+            return positions[end..]
+                .iter()
+                .copied()
+                .filter(|_| last == 1 && n_max >= 1)
+                .collect();
+        }
+        let n_max = n_max.min(cost.iter().sum());
+
+        // The least error of the track up to each data point, as a vertex,
+        // having selected each number of data points, and the previous vertex
+        let mut error = vec![f64::INFINITY; (n_max + 1) * positions.len()];
+        let mut previous = vec![0; (n_max + 1) * positions.len()];
+        let at = |n: usize, j: usize| n * positions.len() + j;
+        error[at(first, 0)] = 0.0;
+
+        // Data points the recipient has must be vertices, so no segment may
+        // skip them
+        let mut known = 0;
+        for j in 1..positions.len() {
+            // Selecting this data point may not fit at all (if both ends are
+            // already known, and `n_max` is 0)
+            let Some(n_before) = n_max.checked_sub(cost[j]) else {
+                continue;
+            };
+            // The shortest segments are measured first, as they're quickest
+            // to measure and the most likely to improve the selection
+            for i in (known..j).rev() {
+                // Up to `i`, at most `i + 1` data points can have been
+                // selected
+                let counts = first..=n_before.min(i + 1);
+                // The segment can only improve the selection if its error is
+                // within the margin of at least one count
+                let limit = counts
+                    .clone()
+                    .filter(|&n| error[at(n, i)].is_finite())
+                    .map(|n| {
+                        self.objective
+                            .margin(error[at(n, i)], error[at(n + cost[j], j)])
+                    })
+                    .fold(f64::NEG_INFINITY, f64::max);
+                if limit < 0.0 {
+                    continue;
+                }
+                let Some(segment) = self.objective.segment_error(&positions[i..=j], limit) else {
+                    continue;
+                };
+                for n in counts {
+                    let error_via_i = self.objective.combine(error[at(n, i)], segment);
+                    if error_via_i < error[at(n + cost[j], j)] {
+                        error[at(n + cost[j], j)] = error_via_i;
+                        previous[at(n + cost[j], j)] = i;
+                    }
+                }
+            }
+            if cost[j] == 0 {
+                known = j;
+            }
+        }
+
+        // The fewest data points with the least error
+        let Some(mut n) = (0..=n_max)
+            .filter(|&n| error[at(n, end)].is_finite())
+            .min_by(|&a, &b| error[at(a, end)].total_cmp(&error[at(b, end)]))
+        else {
+            return Vec::new();
+        };
+        let mut selected = Vec::with_capacity(n);
+        let mut j = end;
+        loop {
+            if cost[j] == 1 {
+                selected.push(positions[j]);
+            }
+            if j == 0 {
+                break;
+            }
+            let i = previous[at(n, j)];
+            n -= cost[j];
+            j = i;
+        }
+        selected.reverse();
+        selected
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use chrono::{DateTime, Duration};
+    use float_cmp::assert_approx_eq;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{endpoint_novelty::AlwaysIncludeEndpoints, rdp, Coordinate, Positions, Search};
+
+    fn positions(coordinates: &[(f64, f64)]) -> Positions {
+        let mut positions = Positions::default();
+        for (seconds, &(x, y)) in (0..).zip(coordinates) {
+            positions
+                .add(
+                    DateTime::UNIX_EPOCH + Duration::seconds(seconds),
+                    Coordinate::new(x, y, 0.0),
+                )
+                .unwrap();
+        }
+        positions
+    }
+
+    /// Returns the (max, total) error of the track through the selected data
+    /// points, which must include both ends.
+    fn error(positions: &Positions, selected: &[&Datum]) -> (f64, f64) {
+        let data: Vec<_> = positions.iter().collect();
+        let vertices: Vec<_> = (0..data.len())
+            .filter(|&i| selected.iter().any(|datum| datum.id == data[i].id))
+            .collect();
+        assert_eq!(vertices.first(), Some(&0));
+        assert_eq!(vertices.last(), Some(&(data.len() - 1)));
+        vertices
+            .windows(2)
+            .map(|pair| {
+                let segment = &data[pair[0]..=pair[1]];
+                [Objective::MaxError, Objective::TotalError]
+                    .map(|objective| objective.segment_error(segment, f64::INFINITY).unwrap())
+            })
+            .fold((0.0, 0.0), |(max, total), [segment_max, segment_total]| {
+                (f64::max(max, segment_max), total + segment_total)
+            })
+    }
+
+    #[test]
+    fn better_than_greedy() {
+        // Greedily taking the furthest points first (the troughs) leaves the
+        // descent badly described, while the bottom of the descent alone
+        // describes the whole track as closely as any four points can.
+        let positions = positions(&[
+            (0.0, 3.0),
+            (1.0, 3.0),
+            (2.0, 1.0),
+            (3.0, 0.0),
+            (4.0, 3.0),
+            (5.0, 0.0),
+            (6.0, 3.0),
+        ]);
+        let recipient = NodeId::new_v4();
+
+        let optimal = positions.most_novel_coordinates(&Optimal::default(), &recipient, 4);
+        let greedy = positions.most_novel_coordinates(
+            &Search::new(rdp, None).with_endpoint_novelty(AlwaysIncludeEndpoints),
+            &recipient,
+            4,
+        );
+
+        assert_eq!(optimal.len(), 3);
+        assert_approx_eq!(f64, error(&positions, &optimal).0, 2.0_f64.sqrt());
+        assert_approx_eq!(f64, error(&positions, &greedy).0, 3.0);
+    }
+
+    #[test]
+    fn transmitted_data_are_kept_but_not_selected() {
+        let mut positions =
+            positions(&[(0.0, 0.0), (1.0, 5.0), (2.0, 0.0), (3.0, 1.0), (4.0, 0.0)]);
+        let recipient = NodeId::new_v4();
+        let ids: Vec<_> = positions.iter().map(|datum| datum.id).collect();
+        for &id in &ids[..2] {
+            positions
+                .transmission_history_mut()
+                .record(recipient, id, Probability::ONE_HUNDRED);
+        }
+
+        // The climb to the peak is already known, so only the descent needs
+        // describing.
+        let selected: Vec<_> = positions
+            .most_novel_coordinates(&Optimal::default(), &recipient, 2)
+            .iter()
+            .map(|datum| datum.id)
+            .collect();
+        assert_eq!(selected, [ids[2], ids[4]]);
+    }
+
+    #[test]
+    fn too_few_for_both_ends() {
+        let positions = positions(&[(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]);
+        let recipient = NodeId::new_v4();
+        let last = positions.iter().last().map(|datum| datum.id);
+
+        assert!(positions
+            .most_novel_coordinates(&Optimal::default(), &recipient, 0)
+            .is_empty());
+        let selected = positions.most_novel_coordinates(&Optimal::default(), &recipient, 1);
+        assert_eq!(
+            selected.iter().map(|datum| datum.id).collect::<Vec<_>>(),
+            [last.unwrap()]
+        );
+    }
+
+    #[test]
+    fn known_ends_and_no_room() {
+        // Both ends are known, so the track can be described without selecting
+        // anything
+        let mut positions = positions(&[(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]);
+        let recipient = NodeId::new_v4();
+        let ids: Vec<_> = positions.iter().map(|datum| datum.id).collect();
+        for id in [ids[0], ids[2]] {
+            positions
+                .transmission_history_mut()
+                .record(recipient, id, Probability::ONE_HUNDRED);
+        }
+
+        for objective in [Objective::MaxError, Objective::TotalError] {
+            assert!(positions
+                .most_novel_coordinates(&Optimal::new(objective), &recipient, 0)
+                .is_empty());
+        }
+
+        // Only the last is known, and there's no room for the first
+        positions = self::positions(&[(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]);
+        let last = positions.iter().last().unwrap().id;
+        positions
+            .transmission_history_mut()
+            .record(recipient, last, Probability::ONE_HUNDRED);
+        assert!(positions
+            .most_novel_coordinates(&Optimal::default(), &recipient, 0)
+            .is_empty());
+
+        // A single datum, which is known
+        positions = self::positions(&[(0.0, 0.0)]);
+        let only = positions.iter().next().unwrap().id;
+        positions
+            .transmission_history_mut()
+            .record(recipient, only, Probability::ONE_HUNDRED);
+        assert!(positions
+            .most_novel_coordinates(&Optimal::default(), &recipient, 5)
+            .is_empty());
+    }
+
+    #[test]
+    fn long_tracks_are_searched_greedily() {
+        let coordinates: Vec<_> = (0..=1000)
+            .map(|i| {
+                let x = f64::from(u32::try_from(i).unwrap());
+                (x, (x / 10.0).sin() * 10.0)
+            })
+            .collect();
+        let positions = positions(&coordinates);
+        let recipient = NodeId::new_v4();
+
+        let optimal = positions.most_novel_coordinates(
+            &Optimal::default().with_greedy_fallback(1000),
+            &recipient,
+            20,
+        );
+        let mut greedy = positions.most_novel_coordinates(
+            &Search::new(rdp, None).with_endpoint_novelty(AlwaysIncludeEndpoints),
+            &recipient,
+            20,
+        );
+        greedy.sort();
+        assert_eq!(optimal, greedy);
+    }
+
+    proptest! {
+        #[test]
+        fn no_worse_than_search(
+            coordinates in prop::collection::vec((-100.0..100.0, -100.0..100.0), 2..30),
+            n_max in 2..12usize,
+        ) {
+            let positions = positions(&coordinates);
+            let recipient = NodeId::new_v4();
+            let greedy = positions.most_novel_coordinates(
+                &Search::new(rdp, None).with_endpoint_novelty(AlwaysIncludeEndpoints),
+                &recipient,
+                n_max,
+            );
+            let greedy = error(&positions, &greedy);
+
+            for objective in [Objective::MaxError, Objective::TotalError] {
+                let optimal = positions.most_novel_coordinates(&Optimal::new(objective), &recipient, n_max);
+                prop_assert!(optimal.len() <= n_max);
+                let optimal = error(&positions, &optimal);
+                match objective {
+                    Objective::MaxError => prop_assert!(optimal.0 <= greedy.0 + 1e-9),
+                    Objective::TotalError => prop_assert!(optimal.1 <= greedy.1 + 1e-9),
+                }
+            }
+        }
+
+        #[test]
+        fn matches_exhaustive_search(
+            coordinates in prop::collection::vec((-100.0..100.0, -100.0..100.0), 2..10),
+            n_max in 2..6usize,
+        ) {
+            let positions = positions(&coordinates);
+            let data: Vec<_> = positions.iter().collect();
+            let interior = data.len() - 2;
+
+            // Try every selection of interior data points
+            let best = (0_u32..1 << interior)
+                .filter(|mask| mask.count_ones() as usize + 2 <= n_max)
+                .map(|mask| {
+                    let selected: Vec<_> = (0..data.len())
+                        .filter(|&i| i == 0 || i == data.len() - 1 || mask & 1 << (i - 1) != 0)
+                        .map(|i| data[i])
+                        .collect();
+                    error(&positions, &selected)
+                })
+                .fold((f64::INFINITY, f64::INFINITY), |(max, total), (m, t)| {
+                    (max.min(m), total.min(t))
+                });
+
+            let recipient = NodeId::new_v4();
+            let max = positions.most_novel_coordinates(&Optimal::new(Objective::MaxError), &recipient, n_max);
+            let total = positions.most_novel_coordinates(&Optimal::new(Objective::TotalError), &recipient, n_max);
+            prop_assert!((error(&positions, &max).0 - best.0).abs() < 1e-9);
+            prop_assert!((error(&positions, &total).1 - best.1).abs() < 1e-9);
+        }
+    }
+}
@@ -5,7 +5,7 @@ pub struct Probability {
     value: u32,
 }
 
-impl std::ops::Mul<Probability> for f64 {
+impl core::ops::Mul<Probability> for f64 {
     type Output = Self;
 
     fn mul(self, rhs: Probability) -> Self::Output {
@@ -14,14 +14,26 @@ impl std::ops::Mul<Probability> for f64 {
 }
 
 impl Probability {
+    /// Certainty.
     pub const ONE_HUNDRED: Self = Self { value: u32::MAX };
+    /// Impossibility.
     pub const ZERO: Self = Self { value: 0 };
 
+    /// Creates a new probability from its raw representation, where
+    /// [`u32::MAX`] represents 100%.
     #[must_use]
     pub const fn new(value: u32) -> Self {
         Self { value }
     }
 
+    /// Returns the raw representation of the probability, where [`u32::MAX`]
+    /// represents 100%.
+    #[must_use]
+    pub const fn value(self) -> u32 {
+        self.value
+    }
+
+    /// Returns the probability of the event *not* occurring.
     #[must_use]
     pub const fn complement(self) -> Self {
         Self {
@@ -64,13 +76,13 @@ impl From<Probability> for f64 {
 }
 
 impl Ord for Probability {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.value.cmp(&other.value)
     }
 }
 
 impl PartialOrd for Probability {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
@@ -0,0 +1,484 @@
+//! A deterministic discrete-event simulator for evaluating sharing strategies.
+//!
+//! A number of simulated nodes each follow a randomly generated track, and
+//! periodically share their track with every other node over a lossy,
+//! bandwidth-limited link. Messages are encoded using the [`codec`] wire
+//! format, and each sender maintains a [`TransmissionHistory`] for each of its
+//! peers, in exactly the same way as a real deployment.
+//!
+//! Periodically, the simulator measures how well each receiver's
+//! reconstruction matches the sender's true track. This allows different
+//! [`SearchStrategy`]s (and configurations) to be compared before they are
+//! deployed.
+//!
+//! Simulations are fully deterministic. Given the same configuration (including
+//! the seed), a simulation will always produce the same report. No real network
+//! or clock is used.
+//!
+//! # Example
+//! ```
+//! use chrono::Duration;
+//! use position_share::{rdp, simulation::SimulationConfig, Search};
+//!
+//! let config = SimulationConfig {
+//!     duration: Duration::minutes(10),
+//!     ..SimulationConfig::default()
+//! };
+//!
+//! let report = position_share::simulation::run(&config, &Search::new(rdp, None));
+//!
+//! for sample in &report.samples {
+//!     println!(
+//!         "{} node {} -> node {}: {:?}",
+//!         sample.time, sample.sender, sample.receiver, sample.error
+//!     );
+//! }
+//! ```
+//!
+//! [`TransmissionHistory`]: crate::TransmissionHistory
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::{
+    codec::{self, Codec},
+    metrics, Coordinate, Datum, NodeId, Positions, Probability, SearchStrategy,
+};
+
+/// The configuration of a simulation.
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    /// The number of nodes in the network.
+    ///
+    /// Every node shares its track with every other node.
+    pub nodes: usize,
+    /// The simulated duration.
+    pub duration: Duration,
+    /// The interval between position fixes on each node.
+    pub fix_interval: Duration,
+    /// The interval between transmissions from each node to each of its peers.
+    pub transmit_interval: Duration,
+    /// The interval at which the reconstruction error is sampled.
+    pub sample_interval: Duration,
+    /// The speed of each node, in metres per second.
+    pub speed: f64,
+    /// The maximum change of heading between fixes, in radians.
+    pub max_turn: f64,
+    /// The link between each pair of nodes.
+    pub link: Link,
+    /// The seed for the random number generator.
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            nodes: 3,
+            duration: Duration::hours(1),
+            fix_interval: Duration::seconds(10),
+            transmit_interval: Duration::minutes(1),
+            sample_interval: Duration::minutes(5),
+            speed: 2.0,
+            max_turn: 0.3,
+            link: Link::default(),
+            seed: 0,
+        }
+    }
+}
+
+/// A lossy, bandwidth-limited link between two nodes.
+#[derive(Debug, Clone, Copy)]
+pub struct Link {
+    /// The maximum size of a single message, in bytes.
+    pub max_message_size: usize,
+    /// The probability (between 0.0 and 1.0) that a message is lost.
+    pub loss_probability: f64,
+    /// The delay between a message being sent and received.
+    pub latency: Duration,
+}
+
+impl Default for Link {
+    fn default() -> Self {
+        Self {
+            max_message_size: 256,
+            loss_probability: 0.2,
+            latency: Duration::seconds(5),
+        }
+    }
+}
+
+/// The results of a simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// The reconstruction error of every (sender, receiver) pair, sampled
+    /// over time.
+    ///
+    /// Ordering: by time, then by sender, then by receiver
+    pub samples: Vec<Sample>,
+}
+
+impl Report {
+    /// Returns the mean reconstruction error across all (sender, receiver)
+    /// pairs, at the final sample time.
+    ///
+    /// Returns `None` if there are no samples, or if no receiver has received
+    /// any data.
+    #[must_use]
+    pub fn final_mean_error(&self) -> Option<f64> {
+        let last = self.samples.last()?.time;
+        let errors: Vec<_> = self
+            .samples
+            .iter()
+            .filter(|sample| sample.time == last)
+            .filter_map(|sample| sample.error)
+            .collect();
+        #[allow(clippy::cast_precision_loss)]
+        (!errors.is_empty()).then(|| errors.iter().sum::<f64>() / errors.len() as f64)
+    }
+}
+
+/// A single measurement of reconstruction error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    /// The simulated time of the measurement.
+    pub time: DateTime<Utc>,
+    /// The index of the node whose track is being reconstructed.
+    pub sender: usize,
+    /// The index of the node reconstructing the track.
+    pub receiver: usize,
+    /// The number of data points the receiver has received from the sender.
+    pub received: usize,
+    /// The mean synchronised Euclidean distance between the sender's track
+    /// and the receiver's reconstruction (see [`metrics::mean_sed`]), or
+    /// `None` if the receiver hasn't received any data.
+    pub error: Option<f64>,
+}
+
+/// Runs a simulation to completion, with every node using the given search
+/// strategy.
+#[must_use]
+pub fn run(config: &SimulationConfig, strategy: &impl SearchStrategy) -> Report {
+    Simulation::new(config).run(strategy)
+}
+
+/// A small, fast, seedable pseudo-random number generator ([SplitMix64](https://prng.di.unimi.it/splitmix64.c)).
+///
+/// This is used instead of an external crate so that simulations remain
+/// reproducible across dependency upgrades.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value uniformly distributed in `[0, 1)`.
+    #[allow(clippy::cast_precision_loss)]
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    fn next_uuid(&mut self) -> Uuid {
+        Uuid::from_u64_pair(self.next_u64(), self.next_u64())
+    }
+}
+
+#[derive(Debug)]
+enum Event {
+    Fix {
+        node: usize,
+    },
+    Transmit {
+        from: usize,
+        to: usize,
+    },
+    Deliver {
+        from: usize,
+        to: usize,
+        message: Vec<u8>,
+    },
+    Sample,
+}
+
+/// An event, scheduled for a particular time.
+///
+/// Events are ordered by time and then by the order in which they were
+/// scheduled, so that the simulation is deterministic.
+#[derive(Debug)]
+struct Scheduled {
+    time: DateTime<Utc>,
+    sequence: u64,
+    event: Event,
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.time
+            .cmp(&other.time)
+            .then_with(|| self.sequence.cmp(&other.sequence))
+    }
+}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Scheduled {}
+
+#[derive(Debug)]
+struct Node {
+    id: NodeId,
+    /// The node's own track.
+    positions: Positions,
+    /// The reconstructed track of each other node, indexed by sender.
+    received: Vec<Positions>,
+    location: Coordinate,
+    heading: f64,
+}
+
+#[derive(Debug)]
+struct Simulation<'a> {
+    config: &'a SimulationConfig,
+    rng: Rng,
+    nodes: Vec<Node>,
+    queue: BinaryHeap<Reverse<Scheduled>>,
+    sequence: u64,
+    samples: Vec<Sample>,
+}
+
+impl<'a> Simulation<'a> {
+    fn new(config: &'a SimulationConfig) -> Self {
+        let mut rng = Rng(config.seed);
+        let nodes = (0..config.nodes)
+            .map(|_| Node {
+                id: rng.next_uuid(),
+                positions: Positions::default(),
+                received: (0..config.nodes).map(|_| Positions::default()).collect(),
+                location: Coordinate::new(1000.0 * rng.next_f64(), 1000.0 * rng.next_f64(), 0.0),
+                heading: std::f64::consts::TAU * rng.next_f64(),
+            })
+            .collect();
+
+        let mut simulation = Self {
+            config,
+            rng,
+            nodes,
+            queue: BinaryHeap::new(),
+            sequence: 0,
+            samples: Vec::new(),
+        };
+
+        let start = DateTime::UNIX_EPOCH;
+        for node in 0..config.nodes {
+            simulation.schedule(start, Event::Fix { node });
+            for to in (0..config.nodes).filter(|&to| to != node) {
+                simulation.schedule(
+                    start + config.transmit_interval,
+                    Event::Transmit { from: node, to },
+                );
+            }
+        }
+        simulation.schedule(start + config.sample_interval, Event::Sample);
+
+        simulation
+    }
+
+    fn schedule(&mut self, time: DateTime<Utc>, event: Event) {
+        self.queue.push(Reverse(Scheduled {
+            time,
+            sequence: self.sequence,
+            event,
+        }));
+        self.sequence += 1;
+    }
+
+    fn run(mut self, strategy: &impl SearchStrategy) -> Report {
+        let end = DateTime::UNIX_EPOCH + self.config.duration;
+
+        while let Some(Reverse(Scheduled {
+            time: now, event, ..
+        })) = self.queue.pop()
+        {
+            if now > end {
+                break;
+            }
+            match event {
+                Event::Fix { node } => {
+                    self.fix(node, now);
+                    self.schedule(now + self.config.fix_interval, Event::Fix { node });
+                }
+                Event::Transmit { from, to } => {
+                    self.transmit(strategy, from, to, now);
+                    self.schedule(
+                        now + self.config.transmit_interval,
+                        Event::Transmit { from, to },
+                    );
+                }
+                Event::Deliver { from, to, message } => {
+                    // Messages are produced by the simulator itself, so are always valid.
+                    if let Ok(datums) = codec::decode_batch(&message) {
+                        for datum in datums {
+                            // Decoded coordinates are always finite.
+                            let _ = self.nodes[to].received[from].insert(datum);
+                        }
+                    }
+                }
+                Event::Sample => {
+                    self.sample(now);
+                    self.schedule(now + self.config.sample_interval, Event::Sample);
+                }
+            }
+        }
+
+        Report {
+            samples: self.samples,
+        }
+    }
+
+    /// Moves a node along its track, and records its new position.
+    fn fix(&mut self, node: usize, now: DateTime<Utc>) {
+        let turn = self.config.max_turn * 2.0f64.mul_add(self.rng.next_f64(), -1.0);
+        let id = self.rng.next_uuid();
+        let node = &mut self.nodes[node];
+
+        #[allow(clippy::cast_precision_loss)]
+        let step = self.config.speed * self.config.fix_interval.num_milliseconds() as f64 / 1000.0;
+        node.heading += turn;
+        node.location.x = step.mul_add(node.heading.cos(), node.location.x);
+        node.location.y = step.mul_add(node.heading.sin(), node.location.y);
+
+        node.positions
+            .insert(Datum::new(id, now, node.location, ()))
+            .expect("simulated locations are finite");
+    }
+
+    /// Sends the most novel positions which fit in a single message.
+    fn transmit(
+        &mut self,
+        strategy: &impl SearchStrategy,
+        from: usize,
+        to: usize,
+        now: DateTime<Utc>,
+    ) {
+        let link = self.config.link;
+        let recipient = self.nodes[to].id;
+        let sender = &mut self.nodes[from];
+
+        // Every datum has the same encoded size, so this is an upper bound on
+        // how many will fit.
+        let empty_message_size = codec::encode_batch::<(), DateTime<Utc>, Coordinate>(&[]).len();
+        let Some(datum_size) = sender.positions.iter().next().map(|datum| {
+            let mut buf = Vec::new();
+            datum.encode(&mut buf);
+            buf.len()
+        }) else {
+            return;
+        };
+        let n_max = link.max_message_size.saturating_sub(empty_message_size) / datum_size;
+
+        let selection = sender
+            .positions
+            .most_novel_coordinates(strategy, &recipient, n_max);
+        if selection.is_empty() {
+            return;
+        }
+        let message = codec::encode_batch(&selection);
+        let ids: Vec<_> = selection.iter().map(|datum| datum.id).collect();
+
+        // The sender doesn't know whether the message will arrive, so it can
+        // only increase its estimate of the probability that the recipient
+        // has each datum.
+        let history = sender.positions.transmission_history_mut();
+        for id in ids {
+            let p_missing =
+                1.0 - f64::from(history.probability_recipient_has_datum(&recipient, &id)) / 100.0;
+            let p_has = 100.0 * (1.0 - p_missing * link.loss_probability);
+            if let Ok(probability) = Probability::try_from(p_has) {
+                history.record(recipient, id, probability);
+            }
+        }
+
+        if self.rng.next_f64() >= link.loss_probability {
+            self.schedule(now + link.latency, Event::Deliver { from, to, message });
+        }
+    }
+
+    /// Measures the reconstruction error of every (sender, receiver) pair.
+    fn sample(&mut self, now: DateTime<Utc>) {
+        for (sender, node) in self.nodes.iter().enumerate() {
+            for (receiver, peer) in self.nodes.iter().enumerate() {
+                if sender == receiver {
+                    continue;
+                }
+                let track: Vec<_> = node.positions.iter().collect();
+                let reconstruction: Vec<_> = peer.received[sender].iter().collect();
+                self.samples.push(Sample {
+                    time: now,
+                    sender,
+                    receiver,
+                    received: reconstruction.len(),
+                    error: metrics::mean_sed(&track, &reconstruction),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rdp, Search};
+
+    #[test]
+    fn deterministic() {
+        let config = SimulationConfig {
+            duration: Duration::minutes(20),
+            seed: 42,
+            ..SimulationConfig::default()
+        };
+        let strategy = Search::new(rdp, None);
+
+        let report = run(&config, &strategy);
+        assert!(!report.samples.is_empty());
+        assert_eq!(report, run(&config, &strategy));
+    }
+
+    #[test]
+    fn lossless_link_converges() {
+        let config = SimulationConfig {
+            nodes: 2,
+            duration: Duration::minutes(10),
+            transmit_interval: Duration::seconds(30),
+            sample_interval: Duration::seconds(60),
+            link: Link {
+                // Plenty of bandwidth for every position
+                max_message_size: 4096,
+                loss_probability: 0.0,
+                latency: Duration::seconds(1),
+            },
+            ..SimulationConfig::default()
+        };
+
+        let report = run(&config, &Search::new(rdp, None));
+
+        // Fixes are taken every 10 seconds, and sent every 30 seconds. Sampling
+        // every 60 seconds, the receiver is at most 30 seconds (3 fixes) behind.
+        let error = report.final_mean_error().unwrap();
+        assert!(error < 2.0, "error: {error}");
+    }
+}
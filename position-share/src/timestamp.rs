@@ -0,0 +1,148 @@
+use core::fmt::Debug;
+
+use chrono::{DateTime, Utc};
+
+/// The time at which a position was recorded.
+///
+/// Timestamps need only be ordered, and able to measure the time between two
+/// of them, so nodes without a real-time clock can use whatever they have to
+/// hand (such as a mission-elapsed-time counter). Implementations are provided
+/// for:
+///
+/// - [`chrono::DateTime<Utc>`] (the default), with [`chrono::Duration`]
+///   durations
+/// - [`std::time::SystemTime`] (with the `std` feature), with
+///   [`core::time::Duration`] durations
+/// - [`u64`] and [`i64`], as a number of milliseconds since an arbitrary
+///   epoch, with [`core::time::Duration`] durations
+///
+/// # Example
+#[cfg_attr(feature = "std", doc = "```")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// use position_share::{rdp, Coordinate, IdGenerator, NodeId, Positions, Search};
+///
+/// // Milliseconds since the vehicle was launched
+/// let mut positions: Positions<(), u64> = Positions::default();
+/// positions.add(0, Coordinate::new(0.0, 0.0, 0.0)).unwrap();
+/// positions.add(1_000, Coordinate::new(1.0, 1.0, 0.0)).unwrap();
+/// positions.add(2_000, Coordinate::new(2.0, 0.0, 0.0)).unwrap();
+///
+/// let most_novel = positions.most_novel_coordinates(&Search::new(rdp, None), &NodeId::new_v4(), 2);
+/// ```
+pub trait Timestamp: Copy + Ord + Debug {
+    /// The type of an interval between timestamps, such as a time-decay
+    /// half-life or a timeout.
+    type Duration: Copy + Ord + Debug;
+
+    /// Returns the number of seconds elapsed from `earlier` to `self`.
+    ///
+    /// This is negative if `earlier` is actually later than `self`.
+    fn seconds_since(&self, earlier: &Self) -> f64;
+
+    /// Returns the number of seconds in `duration`.
+    fn duration_seconds(duration: Self::Duration) -> f64;
+}
+
+impl Timestamp for DateTime<Utc> {
+    type Duration = chrono::Duration;
+
+    fn seconds_since(&self, earlier: &Self) -> f64 {
+        Self::duration_seconds(*self - *earlier)
+    }
+
+    fn duration_seconds(duration: Self::Duration) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let milliseconds = duration.num_milliseconds() as f64;
+        milliseconds / 1000.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl Timestamp for std::time::SystemTime {
+    type Duration = core::time::Duration;
+
+    fn seconds_since(&self, earlier: &Self) -> f64 {
+        match self.duration_since(*earlier) {
+            Ok(elapsed) => elapsed.as_secs_f64(),
+            Err(error) => -error.duration().as_secs_f64(),
+        }
+    }
+
+    fn duration_seconds(duration: Self::Duration) -> f64 {
+        duration.as_secs_f64()
+    }
+}
+
+impl Timestamp for u64 {
+    type Duration = core::time::Duration;
+
+    fn seconds_since(&self, earlier: &Self) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let milliseconds = if self >= earlier {
+            (self - earlier) as f64
+        } else {
+            -((earlier - self) as f64)
+        };
+        milliseconds / 1000.0
+    }
+
+    fn duration_seconds(duration: Self::Duration) -> f64 {
+        duration.as_secs_f64()
+    }
+}
+
+impl Timestamp for i64 {
+    type Duration = core::time::Duration;
+
+    fn seconds_since(&self, earlier: &Self) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let milliseconds = (i128::from(*self) - i128::from(*earlier)) as f64;
+        milliseconds / 1000.0
+    }
+
+    fn duration_seconds(duration: Self::Duration) -> f64 {
+        duration.as_secs_f64()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn seconds_since() {
+        let now = Utc::now();
+        let later = now + chrono::Duration::milliseconds(1_500);
+        assert_approx_eq!(f64, later.seconds_since(&now), 1.5);
+        assert_approx_eq!(f64, now.seconds_since(&later), -1.5);
+
+        let now = SystemTime::now();
+        let later = now + Duration::from_millis(250);
+        assert_approx_eq!(f64, later.seconds_since(&now), 0.25);
+        assert_approx_eq!(f64, now.seconds_since(&later), -0.25);
+
+        assert_approx_eq!(f64, 3_000_u64.seconds_since(&1_000), 2.0);
+        assert_approx_eq!(f64, 1_000_u64.seconds_since(&3_000), -2.0);
+        assert_approx_eq!(f64, (-500_i64).seconds_since(&500), -1.0);
+    }
+
+    #[test]
+    fn duration_seconds() {
+        assert_approx_eq!(
+            f64,
+            DateTime::<Utc>::duration_seconds(chrono::Duration::milliseconds(1_500)),
+            1.5
+        );
+        assert_approx_eq!(
+            f64,
+            SystemTime::duration_seconds(Duration::from_millis(250)),
+            0.25
+        );
+        assert_approx_eq!(f64, u64::duration_seconds(Duration::from_secs(2)), 2.0);
+        assert_approx_eq!(f64, i64::duration_seconds(Duration::from_secs(1)), 1.0);
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use alloc::collections::BTreeMap;
 
 use uuid::Uuid;
 
@@ -11,10 +11,23 @@ use crate::{probability::Probability, NodeId};
 #[derive(Debug, Clone, Default)]
 pub struct TransmissionHistory {
     /// Maps a recipient to a map of datums to their transmission probabilities.
-    history: HashMap<NodeId, HashMap<Uuid, Probability>>,
+    history: BTreeMap<NodeId, BTreeMap<Uuid, Probability>>,
 }
 
 impl TransmissionHistory {
+    /// Records the probability that a recipient has a datum.
+    ///
+    /// This replaces any previously recorded probability. Recording
+    /// [`Probability::ONE_HUNDRED`] marks the datum as known to have been
+    /// received, meaning it will never be selected for transmission to that
+    /// recipient again.
+    pub fn record(&mut self, recipient: NodeId, datum_id: Uuid, probability: Probability) {
+        self.history
+            .entry(recipient)
+            .or_default()
+            .insert(datum_id, probability);
+    }
+
     /// Returns the probability that a recipient has a datum.
     #[must_use]
     pub fn probability_recipient_has_datum(
@@ -31,3 +44,29 @@ impl TransmissionHistory {
             .unwrap_or(Probability::ZERO)
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let mut history = TransmissionHistory::default();
+        let recipient = NodeId::new_v4();
+        let datum = Uuid::new_v4();
+        assert_eq!(
+            history.probability_recipient_has_datum(&recipient, &datum),
+            Probability::ZERO
+        );
+
+        history.record(recipient, datum, Probability::ONE_HUNDRED);
+        assert_eq!(
+            history.probability_recipient_has_datum(&recipient, &datum),
+            Probability::ONE_HUNDRED
+        );
+        assert_eq!(
+            history.probability_recipient_has_datum(&NodeId::new_v4(), &datum),
+            Probability::ZERO
+        );
+    }
+}
@@ -0,0 +1,72 @@
+//! Checks that repeated searches with a [`SearchContext`] don't allocate.
+//!
+//! This is the only test in its binary, as the allocator counts the
+//! allocations of every thread.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use chrono::{Duration, Utc};
+use position_share::{rdp, Coordinate, NodeId, Positions, Search, SearchContext};
+
+/// Counts every allocation made by the process.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn steady_state_searches_do_not_allocate() {
+    // A meandering track, short enough to be searched on a single thread
+    let mut positions = Positions::default();
+    let start = Utc::now();
+    for i in 0..2000 {
+        let t = f64::from(i) / 10.0;
+        let coordinate = Coordinate::new(10.0 * t, 50.0 * t.sin(), -5.0 * (t / 3.0).cos());
+        positions
+            .add(start + Duration::seconds(i.into()), coordinate)
+            .unwrap();
+    }
+    let recipient = NodeId::new_v4();
+    let search = Search::new(rdp, Some(0.4));
+    let mut context = SearchContext::new();
+
+    // The first search grows the buffers to fit, after which searching
+    // shouldn't allocate at all
+    let expected: Vec<_> = positions
+        .most_novel_coordinates_with_context(&mut context, &search, &recipient, 100)
+        .iter()
+        .map(|datum| datum.id)
+        .collect();
+    let mut selected = Vec::with_capacity(expected.len());
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..10 {
+        let selection =
+            positions.most_novel_coordinates_with_context(&mut context, &search, &recipient, 100);
+        selected.clear();
+        selected.extend(selection.iter().map(|datum| datum.id));
+        assert_eq!(selected, expected);
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    assert_eq!(allocations, 0, "steady-state searches allocated");
+}